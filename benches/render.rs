@@ -0,0 +1,77 @@
+use mandelbrot::{escape_time, render, render_parallel, render_tiles, Config};
+use mandelbrot::simd::{escape_time_fast, escape_time_x4};
+use num::Complex;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// A small self-contained timing loop, run with `cargo bench`. Each benchmark
+// is repeated until it has run for at least a second and the mean time per
+// iteration is reported.
+fn bench<F: FnMut()>(name: &str, mut f: F) {
+
+    f();
+
+    let mut iterations = 0u32;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        iterations += 1;
+    }
+
+    println!("{:<24} {:>12.3?} / iter ({} iterations)", name, start.elapsed() / iterations, iterations);
+
+}
+
+fn main() {
+
+    let points: Vec<Complex<f64>> = [(-0.5, 0.0), (0.0, 0.0), (-1.0, 0.0), (0.25, 0.5),
+                                     (-0.75, 0.1), (0.3, 0.0), (-2.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .map(|&(re, im)| Complex { re, im })
+        .collect();
+
+    bench("escape_time", || {
+        for &point in &points {
+            black_box(escape_time(black_box(point), 1000));
+        }
+    });
+
+    bench("escape_time_fast", || {
+        for &point in &points {
+            black_box(escape_time_fast(black_box(point), 1000));
+        }
+    });
+
+    bench("escape_time_x4", || {
+        for lanes in points.chunks(4) {
+            black_box(escape_time_x4(black_box([lanes[0], lanes[1], lanes[2], lanes[3]]), 1000));
+        }
+    });
+
+    let bounds = (200, 150);
+    let config = Config { limit: 255, ..Config::default() };
+    let mut pixels = vec![0; bounds.0 * bounds.1 * config.channels()];
+
+    bench("render 200x150", || {
+        render(&mut pixels, bounds, Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 }, config);
+        black_box(&pixels);
+    });
+
+    // The minibrot near -1.755 fills a few rows in the middle of this view,
+    // which run to the limit while the rows around them escape quickly.
+    let bounds = (400, 300);
+    let config = Config { limit: 5000, ..Config::default() };
+    let (upper_left, lower_right) = (Complex { re: -1.80, im: 0.15 }, Complex { re: -1.70, im: -0.15 });
+    let mut pixels = vec![0; bounds.0 * bounds.1 * config.channels()];
+
+    bench("render_parallel minibrot", || {
+        render_parallel(&mut pixels, bounds, upper_left, lower_right, config);
+        black_box(&pixels);
+    });
+
+    bench("render_tiles minibrot", || {
+        render_tiles(&mut pixels, bounds, upper_left, lower_right, config);
+        black_box(&pixels);
+    });
+
+}
@@ -0,0 +1,91 @@
+use crate::{Config, Fractal};
+use crate::buddhabrot::SplitMix;
+use num::Complex;
+use rayon::prelude::*;
+use std::fmt;
+
+// Samples are drawn in fixed size chunks, each from its own generator, so the
+// estimate does not depend on how rayon splits the work.
+const CHUNK_SAMPLES: usize = 1 << 14;
+
+// Standard normal quantile of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+// The area of a set estimated from the fraction of random points of a box
+// that lie in it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AreaEstimate {
+    pub area: f64,
+    /// Half the width of the 95% confidence interval around the area.
+    pub margin: f64,
+    pub samples: usize,
+    pub inside: usize
+}
+
+impl fmt::Display for AreaEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.5} ± {:.5} (95% confidence, {} of {} samples inside)", self.area, self.margin, self.inside, self.samples)
+    }
+}
+
+// A box holding the whole set : the Mandelbrot set ends at -2 on the left and
+// before 0.5 and ±1.25 elsewhere, while the other sets stay within the bailout
+// radius, or the Julia constant when it is further out.
+pub fn area_bounds(config: &Config) -> (Complex<f64>, Complex<f64>) {
+    let radius = match config.fractal {
+        Fractal::Mandelbrot if config.power == 2 => return (Complex { re: -2.0, im: 1.25 }, Complex { re: 0.5, im: -1.25 }),
+        Fractal::Julia(c) => config.bailout.max(c.norm()),
+        _ => config.bailout
+    };
+    (Complex { re: -radius, im: radius }, Complex { re: radius, im: -radius })
+}
+
+// Monte Carlo estimate of the area of the set within the box, from `samples`
+// uniform points reproducible from `seed`. Points that have not escaped within
+// the iteration limit count as inside, so low limits overestimate the area.
+pub fn estimate_area(upper_left: Complex<f64>,
+                     lower_right: Complex<f64>,
+                     config: Config,
+                     samples: usize,
+                     seed: u64)
+   -> AreaEstimate
+{
+
+    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+
+    let inside: usize = (0..samples.div_ceil(CHUNK_SAMPLES))
+        .into_par_iter()
+        .map(|chunk| {
+            let mut random = SplitMix::new(seed, chunk as u64);
+            (0..CHUNK_SAMPLES.min(samples - chunk * CHUNK_SAMPLES))
+                .filter(|_| {
+                    let c = Complex { re: upper_left.re + random.next() * width, im: upper_left.im - random.next() * height };
+                    config.fractal.escape_time(c, &config).is_none()
+                })
+                .count()
+        })
+        .sum();
+
+    let fraction = inside as f64 / samples.max(1) as f64;
+    let error = (fraction * (1.0 - fraction) / samples.max(1) as f64).sqrt();
+
+    AreaEstimate { area: fraction * width * height, margin: Z_95 * error * width * height, samples, inside }
+
+}
+
+#[test]
+fn test_estimate_area() {
+    let config = Config { limit: 2000, ..Config::default() };
+    let (upper_left, lower_right) = area_bounds(&config);
+
+    let estimate = estimate_area(upper_left, lower_right, config, 200_000, 7);
+    assert!((1.48..1.54).contains(&estimate.area), "{}", estimate);
+    assert!(estimate.margin > 0.0 && estimate.margin < 0.015, "{}", estimate);
+    assert!((estimate.area - 1.5066).abs() < 2.0 * estimate.margin, "{}", estimate);
+    assert_eq!(estimate_area(upper_left, lower_right, config, 200_000, 7), estimate);
+    assert_ne!(estimate_area(upper_left, lower_right, config, 200_000, 8), estimate);
+
+    let unit = AreaEstimate { area: 1.5, margin: 0.01, samples: 100, inside: 24 };
+    assert_eq!(unit.to_string(), "1.50000 ± 0.01000 (95% confidence, 24 of 100 samples inside)");
+    assert_eq!(area_bounds(&Config { fractal: Fractal::Julia(Complex { re: 3.0, im: 4.0 }), ..config }).1, Complex { re: 5.0, im: -5.0 });
+}
@@ -0,0 +1,107 @@
+use crate::{Config, Palette, render};
+use crate::buddhabrot::SplitMix;
+use num::Complex;
+
+// Width of the previews the candidates are scored on.
+const PREVIEW_WIDTH: usize = 64;
+
+// Candidates are between 2 and 32 times narrower than the view they are
+// picked from.
+const MIN_ZOOM_BITS: f64 = 1.0;
+const MAX_ZOOM_BITS: f64 = 5.0;
+
+// Shannon entropy in bits of the levels of a grayscale preview. Uniform
+// regions, inside or far outside the set, score 0, while views crossing the
+// boundary spread their pixels over many escape counts.
+pub fn score(pixels: &[u8]) -> f64 {
+
+    let mut levels = [0usize; 256];
+    for pixel in pixels {
+        levels[*pixel as usize] += 1;
+    }
+
+    levels.iter()
+          .filter(|count| **count > 0)
+          .map(|count| { let p = *count as f64 / pixels.len() as f64; -p * p.log2() })
+          .sum()
+
+}
+
+// Picks, among `candidates` random windows within the view, the one whose
+// preview scores highest. Windows keep the aspect ratio of `bounds` and are
+// reproducible from `seed`. The view itself is returned if no candidate
+// scores above it.
+pub fn autofocus(bounds: (usize, usize),
+                 upper_left: Complex<f64>,
+                 lower_right: Complex<f64>,
+                 config: Config,
+                 candidates: usize,
+                 seed: u64)
+   -> (Complex<f64>, Complex<f64>)
+{
+
+    let config = Config { palette: Palette::Grayscale, supersample: 1, depth: 8, transparent: false, invert: false, ..config };
+    let preview = (PREVIEW_WIDTH, (PREVIEW_WIDTH * bounds.1 / bounds.0).max(1));
+    let mut pixels = vec![0; preview.0 * preview.1];
+
+    let mut score_view = |upper_left: Complex<f64>, lower_right: Complex<f64>| {
+        render(&mut pixels, preview, upper_left, lower_right, config);
+        score(&pixels)
+    };
+
+    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+    let mut best = (score_view(upper_left, lower_right), upper_left, lower_right);
+    let mut random = SplitMix::new(seed, 0);
+
+    for _ in 0..candidates {
+
+        let scale = 0.5f64.powf(MIN_ZOOM_BITS + random.next() * (MAX_ZOOM_BITS - MIN_ZOOM_BITS));
+        let size = (width * scale, height * scale);
+        let corner = Complex { re: upper_left.re + random.next() * (width - size.0),
+                               im: upper_left.im - random.next() * (height - size.1) };
+        let candidate = (corner, Complex { re: corner.re + size.0, im: corner.im - size.1 });
+
+        let score = score_view(candidate.0, candidate.1);
+        if score > best.0 {
+            best = (score, candidate.0, candidate.1);
+        }
+
+    }
+
+    (best.1, best.2)
+
+}
+
+#[test]
+fn test_score() {
+    let bounds = (PREVIEW_WIDTH, 48);
+    let config = Config::default();
+    let preview = |upper_left: Complex<f64>, lower_right: Complex<f64>| {
+        let mut pixels = vec![0; bounds.0 * bounds.1];
+        render(&mut pixels, bounds, upper_left, lower_right, config);
+        score(&pixels)
+    };
+
+    let seahorse = preview(Complex { re: -0.76, im: 0.14 }, Complex { re: -0.73, im: 0.12 });
+    let exterior = preview(Complex { re: 2.5, im: 2.5 }, Complex { re: 3.0, im: 2.0 });
+    let interior = preview(Complex { re: -0.2, im: 0.1 }, Complex { re: 0.0, im: -0.1 });
+    assert!(seahorse > exterior && seahorse > interior, "{} {} {}", seahorse, exterior, interior);
+    assert_eq!(interior, 0.0);
+    assert_eq!(score(&[7; 100]), 0.0);
+    assert_eq!(score(&[0, 1, 2, 3]), 2.0);
+}
+
+#[test]
+fn test_autofocus() {
+    let bounds = (160, 120);
+    let (upper_left, lower_right) = (Complex { re: -2.5, im: 1.5 }, Complex { re: 1.5, im: -1.5 });
+    let config = Config { palette: Palette::Fire, ..Config::default() };
+
+    let view = autofocus(bounds, upper_left, lower_right, config, 16, 3);
+    assert_eq!(view, autofocus(bounds, upper_left, lower_right, config, 16, 3));
+    assert!(view.0.re >= upper_left.re && view.0.im <= upper_left.im);
+    assert!(view.1.re <= lower_right.re && view.1.im >= lower_right.im);
+    assert!(((view.1.re - view.0.re) / (view.0.im - view.1.im) - 4.0 / 3.0).abs() < 1e-9);
+
+    assert_eq!(autofocus(bounds, upper_left, lower_right, config, 0, 3), (upper_left, lower_right));
+}
@@ -0,0 +1,87 @@
+use crate::{parse_bounds, parse_complex, validate_bounds, RenderConfig};
+
+// One render per line, laid out as on the command line :
+//
+//     output WIDTHxHEIGHT upper_left lower_right [iterations] [palette]
+//
+// with # comments and blank lines ignored. Each job comes with its line
+// number, so malformed lines can be reported and skipped.
+pub fn parse_manifest(s: &str) -> Vec<(usize, Result<RenderConfig, String>)> {
+
+    s.lines()
+     .enumerate()
+     .map(|(number, line)| (number + 1, line.split('#').next().unwrap_or("").trim()))
+     .filter(|(_, line)| !line.is_empty())
+     .map(|(number, line)| (number, parse_job(line).map_err(|message| format!("line {} : {}", number, message))))
+     .collect()
+
+}
+
+fn parse_job(line: &str) -> Result<RenderConfig, String> {
+
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if !(4..=6).contains(&fields.len()) {
+        return Err(format!("expected output, bounds, upper left and lower right corners, then optionally iterations and palette, got {} fields", fields.len()));
+    }
+
+    let bounds = parse_bounds(fields[1]).map_err(|error| error.to_string())?;
+    let upper_left = parse_complex(fields[2]).map_err(|error| error.to_string())?;
+    let lower_right = parse_complex(fields[3]).map_err(|error| error.to_string())?;
+    validate_bounds(bounds, upper_left, lower_right).map_err(|error| error.to_string())?;
+
+    let iterations = match fields.get(4) {
+        None => None,
+        Some(iterations) => match iterations.parse() {
+            Ok(iterations) if iterations > 0 => Some(iterations),
+            _ => return Err(format!("invalid iterations '{}'", iterations))
+        }
+    };
+
+    Ok(RenderConfig {
+        output: Some(fields[0].to_string()),
+        bounds: Some(bounds),
+        upper_left: Some(upper_left),
+        lower_right: Some(lower_right),
+        iterations,
+        palette: fields.get(5).map(|palette| palette.parse()).transpose()?
+    })
+
+}
+
+#[test]
+fn test_parse_manifest() {
+    use crate::Palette;
+    use num::Complex;
+
+    let manifest = "# Two views of the set\n\
+                    whole.png 800x600 -2.5,1.2 1,-1.2\n\
+                    \n\
+                    seahorse.png 400x300 -0.8,0.2 -0.7,0.1 1000 ultra # zoomed in\n";
+
+    let jobs = parse_manifest(manifest);
+    assert_eq!(jobs, vec![
+        (2, Ok(RenderConfig {
+            output: Some("whole.png".to_string()),
+            bounds: Some((800, 600)),
+            upper_left: Some(Complex { re: -2.5, im: 1.2 }),
+            lower_right: Some(Complex { re: 1.0, im: -1.2 }),
+            iterations: None,
+            palette: None
+        })),
+        (4, Ok(RenderConfig {
+            output: Some("seahorse.png".to_string()),
+            bounds: Some((400, 300)),
+            upper_left: Some(Complex { re: -0.8, im: 0.2 }),
+            lower_right: Some(Complex { re: -0.7, im: 0.1 }),
+            iterations: Some(1000),
+            palette: Some(Palette::Ultra)
+        }))
+    ]);
+
+    let jobs = parse_manifest("a.png 10x10 -1,1\nb.png 10x10 1,1 -1,-1\nc.png 10x10 -1,1 1,-1 0\nd.png 10x10 -1,1 1,-1 9 sepia\n");
+    let errors: Vec<String> = jobs.into_iter().map(|(_, job)| job.unwrap_err()).collect();
+    assert!(errors[0].starts_with("line 1 : expected output"));
+    assert!(errors[1].starts_with("line 2 : Invalid bounds"));
+    assert_eq!(errors[2], "line 3 : invalid iterations '0'");
+    assert!(errors[3].starts_with("line 4 : Unknown palette sepia"));
+}
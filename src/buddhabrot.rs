@@ -0,0 +1,240 @@
+use crate::{escape, in_cardioid_or_bulb, point_to_pixel, step, Config};
+use num::Complex;
+use rayon::prelude::*;
+
+// Samples are drawn in fixed size chunks, each from its own generator, so the
+// density does not depend on how rayon splits the work.
+const CHUNK_SAMPLES: usize = 1 << 14;
+
+// The whole set lies within this distance of the origin on both axes.
+const SAMPLE_RADIUS: f64 = 2.0;
+
+// Which orbits add to the density : those of escaping points make the
+// Buddhabrot, and those of points that stay bounded the anti-Buddhabrot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Orbits {
+    Escaping,
+    Bounded
+}
+
+// Counts how often the orbits of escaping points pass through each pixel. The
+// points are `samples` random values of c over the whole set, not only the
+// view, since orbits from outside the view still cross it.
+pub fn buddhabrot_density(bounds: (usize, usize),
+                          upper_left: Complex<f64>,
+                          lower_right: Complex<f64>,
+                          config: Config,
+                          samples: usize,
+                          seed: u64)
+   -> Vec<u64>
+{
+    orbit_density(bounds, upper_left, lower_right, config, samples, seed, Orbits::Escaping)
+}
+
+// Like `buddhabrot_density`, but counting the first `limit` iterates of the
+// points that never escape, which trace the attracting cycles of the set.
+pub fn antibuddhabrot_density(bounds: (usize, usize),
+                              upper_left: Complex<f64>,
+                              lower_right: Complex<f64>,
+                              config: Config,
+                              samples: usize,
+                              seed: u64)
+   -> Vec<u64>
+{
+    orbit_density(bounds, upper_left, lower_right, config, samples, seed, Orbits::Bounded)
+}
+
+// The iteration limit and sample count of each of the red, green and blue
+// channels of a Nebulabrot.
+pub type NebulaChannels = [(usize, usize); 3];
+
+// A Buddhabrot per channel, each with its own limit and samples but all from
+// the same seed. Orbits that escape late only show up in the channels with
+// the higher limits.
+pub fn nebulabrot_density(bounds: (usize, usize),
+                          upper_left: Complex<f64>,
+                          lower_right: Complex<f64>,
+                          config: Config,
+                          channels: NebulaChannels,
+                          seed: u64)
+   -> [Vec<u64>; 3]
+{
+    channels.map(|(limit, samples)| buddhabrot_density(bounds, upper_left, lower_right, Config { limit, ..config }, samples, seed))
+}
+
+fn orbit_density(bounds: (usize, usize),
+                 upper_left: Complex<f64>,
+                 lower_right: Complex<f64>,
+                 config: Config,
+                 samples: usize,
+                 seed: u64,
+                 orbits: Orbits)
+   -> Vec<u64>
+{
+
+    let bailout_sqr = config.bailout * config.bailout;
+    let origin = Complex { re: 0.0, im: 0.0 };
+
+    (0..samples.div_ceil(CHUNK_SAMPLES))
+        .into_par_iter()
+        .fold(|| vec![0; bounds.0 * bounds.1], |mut density, chunk| {
+
+            let mut random = SplitMix::new(seed, chunk as u64);
+
+            for _ in 0..CHUNK_SAMPLES.min(samples - chunk * CHUNK_SAMPLES) {
+
+                let c = Complex { re: (random.next() * 2.0 - 1.0) * SAMPLE_RADIUS,
+                                  im: (random.next() * 2.0 - 1.0) * SAMPLE_RADIUS };
+                let bounded = config.power == 2 && in_cardioid_or_bulb(c);
+                if bounded && orbits == Orbits::Escaping {
+                    continue;
+                }
+
+                let escape = if bounded { None } else { escape(origin, c, config.limit, config.power, bailout_sqr, false) };
+                let steps = match (escape, orbits) {
+                    (Some((time, _)), Orbits::Escaping) => time,
+                    (None, Orbits::Bounded) => config.limit,
+                    _ => continue
+                };
+
+                let mut z = origin;
+                for _ in 0..steps {
+                    z = step(z, c, config.power);
+                    let (x, y) = point_to_pixel(bounds, z, upper_left, lower_right);
+                    if x >= 0.0 && y >= 0.0 && (x as usize) < bounds.0 && (y as usize) < bounds.1 {
+                        density[y as usize * bounds.0 + x as usize] += 1;
+                    }
+                }
+
+            }
+
+            density
+
+        })
+        .reduce(|| vec![0; bounds.0 * bounds.1], |mut total, density| {
+            for (total, count) in total.iter_mut().zip(density) {
+                *total += count;
+            }
+            total
+        })
+
+}
+
+// Scales the density to 8-bit gray levels, the busiest pixel being white.
+pub fn render_density(pixels: &mut [u8], density: &[u64]) {
+
+    assert!(pixels.len() == density.len());
+
+    let max = density.iter().copied().max().unwrap_or(0).max(1);
+    for (pixel, count) in pixels.iter_mut().zip(density) {
+        *pixel = level(*count, max);
+    }
+
+}
+
+// Scales each density of a Nebulabrot to its own channel of 8-bit RGB pixels,
+// so the rarer late escapes are not drowned out by the early ones.
+pub fn render_nebula(pixels: &mut [u8], densities: &[Vec<u64>; 3]) {
+
+    assert!(densities.iter().all(|density| pixels.len() == density.len() * 3));
+
+    for (channel, density) in densities.iter().enumerate() {
+        let max = density.iter().copied().max().unwrap_or(0).max(1);
+        for (pixel, count) in pixels.chunks_mut(3).zip(density) {
+            pixel[channel] = level(*count, max);
+        }
+    }
+
+}
+
+fn level(count: u64, max: u64) -> u8 {
+    (count as f64 * 255.0 / max as f64).round() as u8
+}
+
+// Sebastiano Vigna's SplitMix64, enough for sampling and reproducible from
+// its seed.
+pub(crate) struct SplitMix {
+    state: u64
+}
+
+impl SplitMix {
+
+    // Streams start from scrambled states, since neighbouring states would
+    // give the same sequence shifted by a few draws.
+    pub(crate) fn new(seed: u64, stream: u64) -> Self {
+        SplitMix { state: mix(seed ^ mix(stream.wrapping_add(GAMMA))) }
+    }
+
+    // Uniform in [0, 1).
+    pub(crate) fn next(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(GAMMA);
+        (mix(self.state) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+}
+
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[test]
+fn test_buddhabrot_density() {
+    let bounds = (64, 48);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let config = Config { limit: 100, ..Config::default() };
+
+    let density = buddhabrot_density(bounds, upper_left, lower_right, config, 50_000, 7);
+    let sum: u64 = density.iter().sum();
+    assert!(sum > 0);
+
+    assert_eq!(buddhabrot_density(bounds, upper_left, lower_right, config, 50_000, 7).iter().sum::<u64>(), sum);
+    assert_ne!(buddhabrot_density(bounds, upper_left, lower_right, config, 50_000, 8).iter().sum::<u64>(), sum);
+
+    // The image is symmetric about the real axis, as the set is.
+    let rows: Vec<u64> = density.chunks(bounds.0).map(|row| row.iter().sum()).collect();
+    assert!(rows.iter().take(bounds.1 / 2).sum::<u64>().abs_diff(rows.iter().skip(bounds.1 / 2).sum::<u64>()) * 10 < sum);
+
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    render_density(&mut pixels, &density);
+    assert_eq!(pixels.iter().max(), Some(&255));
+}
+
+#[test]
+fn test_antibuddhabrot_density() {
+    let bounds = (64, 48);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let config = Config { limit: 100, ..Config::default() };
+
+    let density = antibuddhabrot_density(bounds, upper_left, lower_right, config, 20_000, 7);
+    assert_eq!(antibuddhabrot_density(bounds, upper_left, lower_right, config, 20_000, 7), density);
+    assert_ne!(buddhabrot_density(bounds, upper_left, lower_right, config, 20_000, 7), density);
+
+    // Bounded orbits never leave the disc of radius 2, so the pixels at the
+    // corners of the view, outside it, are never reached.
+    assert!(density.iter().sum::<u64>() > 0);
+    assert_eq!((density[0], density[bounds.0 - 1], density[bounds.0 * bounds.1 - 1]), (0, 0, 0));
+}
+
+#[test]
+fn test_nebulabrot() {
+    let bounds = (48, 36);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let channels = [(500, 20_000), (100, 20_000), (20, 40_000)];
+
+    let densities = nebulabrot_density(bounds, upper_left, lower_right, Config::default(), channels, 3);
+    assert_eq!(nebulabrot_density(bounds, upper_left, lower_right, Config::default(), channels, 3), densities);
+    assert_eq!(densities[1], buddhabrot_density(bounds, upper_left, lower_right, Config { limit: 100, ..Config::default() }, 20_000, 3));
+
+    let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+    render_nebula(&mut pixels, &densities);
+    let sums: Vec<u64> = (0..3).map(|channel| pixels.iter().skip(channel).step_by(3).map(|level| *level as u64).sum()).collect();
+    assert!(sums.iter().all(|sum| *sum > 0), "{:?}", sums);
+    assert!(sums[0] != sums[1] && sums[1] != sums[2] && sums[0] != sums[2], "{:?}", sums);
+    for channel in 0..3 {
+        assert_eq!(pixels.iter().skip(channel).step_by(3).max(), Some(&255));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{render_row, Config, RenderError};
+use num::Complex;
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"MANDELBROT CHECKPOINT 1\n";
+
+// A partially rendered image. The file holds the render parameters on one
+// line, then one byte per row telling whether it is done, then the pixels.
+pub struct Checkpoint {
+    bounds: (usize, usize),
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+    config: Config,
+    rows: Vec<bool>,
+    pixels: Vec<u8>
+}
+
+impl Checkpoint {
+
+    pub fn new(bounds: (usize, usize), upper_left: Complex<f64>, lower_right: Complex<f64>, config: Config) -> Self {
+        Checkpoint {
+            bounds,
+            upper_left,
+            lower_right,
+            config,
+            rows: vec![false; bounds.1],
+            pixels: vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()]
+        }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn completed_rows(&self) -> usize {
+        self.rows.iter().filter(|done| **done).count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.rows.iter().all(|done| *done)
+    }
+
+    // Renders up to `count` of the missing rows and returns how many it did.
+    pub fn render_rows(&mut self, count: usize) -> usize {
+
+        let missing: Vec<usize> = self.rows.iter().enumerate().filter(|(_, done)| !**done).map(|(row, _)| row).take(count).collect();
+        let end = match missing.last() {
+            None => return 0,
+            Some(row) => row + 1
+        };
+
+        let (bounds, upper_left, lower_right, config) = (self.bounds, self.upper_left, self.lower_right, self.config);
+        self.pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+                   .zip(self.rows.par_iter_mut())
+                   .enumerate()
+                   .take(end)
+                   .filter(|(_, (_, done))| !**done)
+                   .for_each(|(row, (line, done))| {
+                       render_row(line, bounds, (0, row), upper_left, lower_right, config);
+                       *done = true;
+                   });
+
+        missing.len()
+
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RenderError> {
+
+        let path = path.as_ref();
+
+        let mut contents = Vec::with_capacity(MAGIC.len() + self.rows.len() + self.pixels.len() + 256);
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(self.parameters().as_bytes());
+        contents.push(b'\n');
+        contents.extend(self.rows.iter().map(|done| *done as u8));
+        contents.extend_from_slice(&self.pixels);
+
+        // Written aside first so an interruption never leaves a torn file.
+        let temporary = path.with_extension("partial");
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)?;
+
+        Ok(())
+
+    }
+
+    pub fn resume<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RenderError> {
+
+        let path = path.as_ref();
+        let contents = fs::read(path)?;
+        let invalid = |message: &str| RenderError::Checkpoint(format!("{} {}", path.display(), message));
+
+        let contents = contents.strip_prefix(MAGIC).ok_or_else(|| invalid("is not a checkpoint file"))?;
+        let newline = contents.iter().position(|byte| *byte == b'\n').ok_or_else(|| invalid("is truncated"))?;
+        if contents[..newline] != *self.parameters().as_bytes() {
+            return Err(invalid("was written for different bounds or render parameters"));
+        }
+
+        let data = &contents[newline + 1..];
+        if data.len() != self.rows.len() + self.pixels.len() {
+            return Err(invalid("is truncated"));
+        }
+
+        let (rows, pixels) = data.split_at(self.rows.len());
+        self.rows = rows.iter().map(|done| *done != 0).collect();
+        self.pixels.copy_from_slice(pixels);
+
+        Ok(())
+
+    }
+
+    fn parameters(&self) -> String {
+        format!("{:?} {:?} {:?} {:?}", self.bounds, self.upper_left, self.lower_right, self.config)
+    }
+
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    use crate::{render, Coloring};
+
+    let bounds = (37, 23);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+    let config = Config { coloring: Coloring::Smooth, ..Config::default() };
+    let path = std::env::temp_dir().join("mandelbrot_test_checkpoint.bin");
+
+    let mut expected = vec![0; bounds.0 * bounds.1];
+    render(&mut expected, bounds, upper_left, lower_right, config);
+
+    let mut first = Checkpoint::new(bounds, upper_left, lower_right, config);
+    assert_eq!(first.render_rows(11), 11);
+    first.save(&path).unwrap();
+
+    let mut second = Checkpoint::new(bounds, upper_left, lower_right, config);
+    second.resume(&path).unwrap();
+    assert_eq!(second.completed_rows(), 11);
+    assert!(!second.is_complete());
+    assert_eq!(second.render_rows(100), bounds.1 - 11);
+    assert!(second.is_complete());
+    assert_eq!(second.render_rows(100), 0);
+    assert_eq!(second.pixels(), &expected[..]);
+
+    let mut other = Checkpoint::new(bounds, upper_left, lower_right, Config::default());
+    assert!(matches!(other.resume(&path), Err(RenderError::Checkpoint(_))));
+    let mut other = Checkpoint::new((37, 24), upper_left, lower_right, config);
+    assert!(matches!(other.resume(&path), Err(RenderError::Checkpoint(_))));
+}
@@ -0,0 +1,89 @@
+use crate::RenderError;
+use image::GenericImage;
+use std::fmt;
+use std::io;
+
+// How far a render is from a reference image of the same size and channels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difference {
+    pub pixels: usize,
+    pub total: usize,
+    /// Largest difference of any channel, out of 255.
+    pub max: u8,
+    /// Mean of the largest channel difference of each differing pixel.
+    pub mean: f64
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} pixels differ, by at most {} and {:.2} on average", self.pixels, self.total, self.max, self.mean)
+    }
+}
+
+pub fn compare(rendered: &[u8], baseline: &[u8], channels: usize) -> Difference {
+
+    assert!(rendered.len() == baseline.len());
+
+    let differences: Vec<u8> = rendered.chunks(channels)
+                                       .zip(baseline.chunks(channels))
+                                       .map(|(a, b)| a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0))
+                                       .filter(|difference| *difference > 0)
+                                       .collect();
+
+    Difference {
+        pixels: differences.len(),
+        total: rendered.len() / channels.max(1),
+        max: differences.iter().max().copied().unwrap_or(0),
+        mean: differences.iter().fold(0.0, |sum, difference| sum + *difference as f64) / differences.len().max(1) as f64
+    }
+
+}
+
+// Loads an 8 bit reference image converted to the given number of channels.
+pub fn load_baseline(filename: &str, bounds: (usize, usize), channels: usize) -> Result<Vec<u8>, RenderError> {
+
+    let invalid = |message: String| RenderError::Io(io::Error::new(io::ErrorKind::InvalidData, message));
+
+    let image = image::open(filename).map_err(|error| invalid(format!("Could not read {} : {}", filename, error)))?;
+    let (width, height) = image.dimensions();
+    if (width as usize, height as usize) != bounds {
+        return Err(invalid(format!("{} is {}x{}, expected {}x{}", filename, width, height, bounds.0, bounds.1)));
+    }
+
+    Ok(match channels {
+        1 => image.to_luma().into_raw(),
+        3 => image.to_rgb().into_raw(),
+        _ => image.to_rgba().into_raw()
+    })
+
+}
+
+#[test]
+fn test_compare() {
+    let baseline: Vec<u8> = (0..48).collect();
+
+    let same = compare(&baseline, &baseline, 3);
+    assert_eq!(same, Difference { pixels: 0, total: 16, max: 0, mean: 0.0 });
+    assert_eq!(same.to_string(), "0 of 16 pixels differ, by at most 0 and 0.00 on average");
+
+    let mut changed = baseline.clone();
+    changed[7] += 9;
+    changed[8] -= 3;
+    assert_eq!(compare(&changed, &baseline, 3), Difference { pixels: 1, total: 16, max: 9, mean: 9.0 });
+    assert_eq!(compare(&changed, &baseline, 1), Difference { pixels: 2, total: 48, max: 9, mean: 6.0 });
+}
+
+#[test]
+fn test_load_baseline() {
+    use crate::write_image;
+    use image::ColorType;
+
+    let pixels: Vec<u8> = (0..12).map(|i| i * 20).collect();
+    let path = std::env::temp_dir().join("mandelbrot_test_load_baseline.png");
+    write_image(path.to_str().unwrap(), &pixels, (4, 3), ColorType::Gray(8)).unwrap();
+
+    let baseline = load_baseline(path.to_str().unwrap(), (4, 3), 1).unwrap();
+    assert_eq!(compare(&pixels, &baseline, 1).pixels, 0);
+    assert_eq!(load_baseline(path.to_str().unwrap(), (4, 3), 3).unwrap().len(), 36);
+    assert!(load_baseline(path.to_str().unwrap(), (3, 4), 1).unwrap_err().to_string().contains("is 4x3, expected 3x4"));
+}
@@ -0,0 +1,50 @@
+use crate::Config;
+
+// Bayer's 8x8 ordered dithering matrix, in which every run of neighbouring
+// thresholds is spread as evenly as possible over the tile.
+const BAYER: [[u8; 8]; 8] = [[ 0, 32,  8, 40,  2, 34, 10, 42],
+                             [48, 16, 56, 24, 50, 18, 58, 26],
+                             [12, 44,  4, 36, 14, 46,  6, 38],
+                             [60, 28, 52, 20, 62, 30, 54, 22],
+                             [ 3, 35, 11, 43,  1, 33,  9, 41],
+                             [51, 19, 59, 27, 49, 17, 57, 25],
+                             [15, 47,  7, 39, 13, 45,  5, 37],
+                             [63, 31, 55, 23, 61, 29, 53, 21]];
+
+// The threshold of a pixel, between 0 and 1 and averaging 1/2 over each tile.
+fn threshold(pixel: (usize, usize)) -> f64 {
+    (BAYER[pixel.1 % 8][pixel.0 % 8] as f64 + 0.5) / 64.0
+}
+
+// Quantizes the unrounded color channels of a pixel with ordered dithering : a
+// channel 30% of the way between two levels takes the upper one at 30% of the
+// pixels of each tile. The pattern only depends on where the pixel is, so
+// renders stay reproducible. Colors are left as they are without --dither.
+pub(crate) fn dither(color: [f64; 4], pixel: (usize, usize), config: Config) -> [f64; 4] {
+
+    if !config.dither {
+        return color;
+    }
+
+    let threshold = threshold(pixel);
+    let [red, green, blue, alpha] = color;
+    [(red + threshold).floor(), (green + threshold).floor(), (blue + threshold).floor(), alpha]
+
+}
+
+#[test]
+fn test_dither() {
+    let mut thresholds: Vec<f64> = (0..64).map(|index| threshold((index % 8, index / 8))).collect();
+    assert_eq!(thresholds.iter().sum::<f64>(), 32.0);
+    thresholds.sort_by(f64::total_cmp);
+    thresholds.dedup();
+    assert_eq!(thresholds.len(), 64);
+    assert_eq!(threshold((9, 17)), threshold((1, 1)));
+
+    // A level a quarter of the way up is rounded up at a quarter of the pixels.
+    let config = Config { dither: true, ..Config::default() };
+    let raised = (0..64).filter(|index| dither([10.25, 0.0, 255.0, 255.0], (index % 8, index / 8), config)[0] == 11.0).count();
+    assert_eq!(raised, 16);
+    assert!((0..64).all(|index| dither([10.25, 0.0, 255.0, 255.0], (index % 8, index / 8), config)[1..] == [0.0, 255.0, 255.0]));
+    assert_eq!(dither([10.25, 0.0, 0.0, 0.0], (3, 3), Config::default()), [10.25, 0.0, 0.0, 0.0]);
+}
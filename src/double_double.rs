@@ -0,0 +1,293 @@
+use num::traits::{Float, Num, NumCast, One, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::num::FpCategory;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+// An unevaluated sum of two f64 values, hi + lo with |lo| <= ulp(hi) / 2,
+// giving about 106 bits of mantissa. The arithmetic, comparisons and square
+// root are done at full precision, which is all escape iteration needs; the
+// transcendental functions are only evaluated on the f64 approximation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    hi: f64,
+    lo: f64
+}
+
+impl DoubleDouble {
+
+    pub fn new(hi: f64, lo: f64) -> Self {
+        let (hi, lo) = quick_two_sum(hi, lo);
+        DoubleDouble { hi, lo }
+    }
+
+    pub fn from_f64(value: f64) -> Self {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+    pub fn hi(self) -> f64 {
+        self.hi
+    }
+
+    pub fn lo(self) -> f64 {
+        self.lo
+    }
+
+    fn approximate(self, f: fn(f64) -> f64) -> Self {
+        DoubleDouble::from_f64(f(self.hi))
+    }
+
+}
+
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    (sum, b - (sum - a))
+}
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    (sum, (a - (sum - b_virtual)) + (b - b_virtual))
+}
+
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(value: f64) -> Self {
+        DoubleDouble::from_f64(value)
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let (sum, error) = two_sum(self.hi, other.hi);
+        let (low_sum, low_error) = two_sum(self.lo, other.lo);
+        let (sum, error) = quick_two_sum(sum, error + low_sum);
+        DoubleDouble::new(sum, error + low_error)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (product, error) = two_product(self.hi, other.hi);
+        DoubleDouble::new(product, error + (self.hi * other.lo + self.lo * other.hi))
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let first = self.hi / other.hi;
+        let remainder = self - other * DoubleDouble::from_f64(first);
+        let second = remainder.hi / other.hi;
+        let remainder = remainder - other * DoubleDouble::from_f64(second);
+        let third = remainder.hi / other.hi;
+        DoubleDouble::new(first, second) + DoubleDouble::from_f64(third)
+    }
+}
+
+impl Rem for DoubleDouble {
+    type Output = Self;
+
+    fn rem(self, other: Self) -> Self {
+        self - other * (self / other).trunc()
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        DoubleDouble { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+impl PartialOrd for DoubleDouble {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.hi.partial_cmp(&other.hi) {
+            Some(Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+            ordering => ordering
+        }
+    }
+}
+
+impl Zero for DoubleDouble {
+    fn zero() -> Self {
+        DoubleDouble::from_f64(0.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.hi == 0.0
+    }
+}
+
+impl One for DoubleDouble {
+    fn one() -> Self {
+        DoubleDouble::from_f64(1.0)
+    }
+}
+
+impl Num for DoubleDouble {
+    type FromStrRadixErr = <f64 as Num>::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        <f64 as Num>::from_str_radix(s, radix).map(DoubleDouble::from_f64)
+    }
+}
+
+impl ToPrimitive for DoubleDouble {
+    fn to_i64(&self) -> Option<i64> {
+        self.to_f64().and_then(|value| value.to_i64())
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.to_f64().and_then(|value| value.to_u64())
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(self.hi + self.lo)
+    }
+}
+
+impl NumCast for DoubleDouble {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        n.to_f64().map(DoubleDouble::from_f64)
+    }
+}
+
+impl Float for DoubleDouble {
+    fn nan() -> Self { DoubleDouble::from_f64(f64::NAN) }
+    fn infinity() -> Self { DoubleDouble::from_f64(f64::INFINITY) }
+    fn neg_infinity() -> Self { DoubleDouble::from_f64(f64::NEG_INFINITY) }
+    fn neg_zero() -> Self { DoubleDouble::from_f64(-0.0) }
+    fn min_value() -> Self { DoubleDouble::from_f64(f64::MIN) }
+    fn min_positive_value() -> Self { DoubleDouble::from_f64(f64::MIN_POSITIVE) }
+    fn epsilon() -> Self { DoubleDouble::from_f64(f64::EPSILON * f64::EPSILON) }
+    fn max_value() -> Self { DoubleDouble::from_f64(f64::MAX) }
+
+    fn is_nan(self) -> bool { self.hi.is_nan() }
+    fn is_infinite(self) -> bool { self.hi.is_infinite() }
+    fn is_finite(self) -> bool { self.hi.is_finite() }
+    fn is_normal(self) -> bool { self.hi.is_normal() }
+    fn classify(self) -> FpCategory { self.hi.classify() }
+    fn is_sign_positive(self) -> bool { self.hi.is_sign_positive() }
+    fn is_sign_negative(self) -> bool { self.hi.is_sign_negative() }
+
+    fn floor(self) -> Self {
+        let hi = self.hi.floor();
+        if hi == self.hi { DoubleDouble::new(hi, self.lo.floor()) } else { DoubleDouble::from_f64(hi) }
+    }
+
+    fn ceil(self) -> Self {
+        let hi = self.hi.ceil();
+        if hi == self.hi { DoubleDouble::new(hi, self.lo.ceil()) } else { DoubleDouble::from_f64(hi) }
+    }
+
+    fn round(self) -> Self {
+        (self + DoubleDouble::from_f64(0.5)).floor()
+    }
+
+    fn trunc(self) -> Self {
+        if self.hi < 0.0 { self.ceil() } else { self.floor() }
+    }
+
+    fn fract(self) -> Self { self - self.trunc() }
+    fn abs(self) -> Self { if self.hi < 0.0 { -self } else { self } }
+    fn signum(self) -> Self { DoubleDouble::from_f64(self.hi.signum()) }
+    fn mul_add(self, a: Self, b: Self) -> Self { self * a + b }
+    fn recip(self) -> Self { DoubleDouble::one() / self }
+
+    fn powi(self, n: i32) -> Self {
+        let (mut base, mut exponent, mut result) = (self, n.unsigned_abs(), DoubleDouble::one());
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        if n < 0 { result.recip() } else { result }
+    }
+
+    fn sqrt(self) -> Self {
+        if self.hi <= 0.0 {
+            return DoubleDouble::from_f64(self.hi.sqrt());
+        }
+        // One Newton step from the f64 root doubles the correct bits.
+        let root = DoubleDouble::from_f64(self.hi.sqrt());
+        root + (self - root * root) / (root + root)
+    }
+
+    fn hypot(self, other: Self) -> Self { (self * self + other * other).sqrt() }
+    fn max(self, other: Self) -> Self { if self < other { other } else { self } }
+    fn min(self, other: Self) -> Self { if self > other { other } else { self } }
+    fn abs_sub(self, other: Self) -> Self { if self > other { self - other } else { DoubleDouble::zero() } }
+
+    fn powf(self, n: Self) -> Self { DoubleDouble::from_f64(self.hi.powf(n.hi)) }
+    fn log(self, base: Self) -> Self { DoubleDouble::from_f64(self.hi.log(base.hi)) }
+    fn atan2(self, other: Self) -> Self { DoubleDouble::from_f64(self.hi.atan2(other.hi)) }
+    fn sin_cos(self) -> (Self, Self) { (self.sin(), self.cos()) }
+    fn integer_decode(self) -> (u64, i16, i8) { self.hi.integer_decode() }
+
+    fn exp(self) -> Self { self.approximate(f64::exp) }
+    fn exp2(self) -> Self { self.approximate(f64::exp2) }
+    fn ln(self) -> Self { self.approximate(f64::ln) }
+    fn log2(self) -> Self { self.approximate(f64::log2) }
+    fn log10(self) -> Self { self.approximate(f64::log10) }
+    fn cbrt(self) -> Self { self.approximate(f64::cbrt) }
+    fn sin(self) -> Self { self.approximate(f64::sin) }
+    fn cos(self) -> Self { self.approximate(f64::cos) }
+    fn tan(self) -> Self { self.approximate(f64::tan) }
+    fn asin(self) -> Self { self.approximate(f64::asin) }
+    fn acos(self) -> Self { self.approximate(f64::acos) }
+    fn atan(self) -> Self { self.approximate(f64::atan) }
+    fn exp_m1(self) -> Self { self.approximate(f64::exp_m1) }
+    fn ln_1p(self) -> Self { self.approximate(f64::ln_1p) }
+    fn sinh(self) -> Self { self.approximate(f64::sinh) }
+    fn cosh(self) -> Self { self.approximate(f64::cosh) }
+    fn tanh(self) -> Self { self.approximate(f64::tanh) }
+    fn asinh(self) -> Self { self.approximate(f64::asinh) }
+    fn acosh(self) -> Self { self.approximate(f64::acosh) }
+    fn atanh(self) -> Self { self.approximate(f64::atanh) }
+}
+
+#[test]
+fn test_double_double_arithmetic() {
+    let one = DoubleDouble::one();
+    let tiny = DoubleDouble::from_f64(1e-20);
+
+    // 1 + 1e-20 is exactly 1 in f64 but keeps the small part here.
+    let sum = one + tiny;
+    assert_eq!(sum.hi(), 1.0);
+    assert_eq!(sum.lo(), 1e-20);
+    assert_eq!(((sum - one) - tiny).hi(), 0.0);
+    assert!(sum > one);
+
+    let third = one / DoubleDouble::from_f64(3.0);
+    assert!((third * DoubleDouble::from_f64(3.0) - one).abs() < DoubleDouble::from_f64(1e-30));
+
+    let two = DoubleDouble::from_f64(2.0);
+    let root = two.sqrt();
+    assert!((root * root - two).abs() < DoubleDouble::from_f64(1e-30));
+
+    assert_eq!(DoubleDouble::from_f64(2.5).floor(), DoubleDouble::from_f64(2.0));
+    assert_eq!(DoubleDouble::from_f64(-2.5).trunc(), DoubleDouble::from_f64(-2.0));
+    assert_eq!(DoubleDouble::from_f64(7.0) % DoubleDouble::from_f64(3.0), one);
+    assert_eq!(two.powi(10), DoubleDouble::from_f64(1024.0));
+    assert_eq!(two.powi(-1), DoubleDouble::from_f64(0.5));
+}
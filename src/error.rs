@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum RenderError {
+    ParseBounds(String),
+    ParseComplex(String),
+    InvalidBounds(String),
+    InvalidBuffer(String),
+    Checkpoint(String),
+    Io(io::Error)
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::ParseBounds(value) => write!(f, "Could not parse bounds '{}', expected WIDTHxHEIGHT like 1920x1080", value),
+            RenderError::ParseComplex(value) => write!(f, "Could not parse complex number '{}', expected a form like -0.5,1 or -0.5+1i", value),
+            RenderError::InvalidBounds(message) => write!(f, "Invalid bounds : {}", message),
+            RenderError::InvalidBuffer(message) => write!(f, "Invalid buffer : {}", message),
+            RenderError::Checkpoint(message) => write!(f, "Cannot resume : {}", message),
+            RenderError::Io(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RenderError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for RenderError {
+    fn from(error: io::Error) -> Self {
+        RenderError::Io(error)
+    }
+}
+
+#[test]
+fn test_render_error_display() {
+    assert_eq!(RenderError::ParseBounds("1920by1080".to_string()).to_string(),
+               "Could not parse bounds '1920by1080', expected WIDTHxHEIGHT like 1920x1080");
+    assert_eq!(RenderError::ParseComplex("1.5+".to_string()).to_string(),
+               "Could not parse complex number '1.5+', expected a form like -0.5,1 or -0.5+1i");
+    assert_eq!(RenderError::InvalidBounds("Image bounds 0x10 must be at least 1x1".to_string()).to_string(),
+               "Invalid bounds : Image bounds 0x10 must be at least 1x1");
+    assert_eq!(RenderError::InvalidBuffer("100 bytes, expected 300".to_string()).to_string(),
+               "Invalid buffer : 100 bytes, expected 300");
+    assert_eq!(RenderError::Checkpoint("render.ckpt is truncated".to_string()).to_string(),
+               "Cannot resume : render.ckpt is truncated");
+
+    let error = RenderError::from(io::Error::new(io::ErrorKind::NotFound, "frames/out.png is missing"));
+    assert_eq!(error.to_string(), "frames/out.png is missing");
+    assert!(error.source().is_some());
+    assert!(RenderError::ParseBounds(String::new()).source().is_none());
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Most stops a gradient holds. They are kept inline so palettes, and the
+// configs holding them, stay Copy without the gradient being shared.
+pub const MAX_STOPS: usize = 32;
+
+// A gradient file lists one `position r g b` stop per line, with positions
+// between 0 and 1. Blank lines and lines starting with # are ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gradient {
+    stops: [(f64, [u8; 3]); MAX_STOPS],
+    len: usize
+}
+
+impl Gradient {
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read gradient {} : {}", path.display(), error))?;
+
+        contents.parse().map_err(|message| format!("Invalid gradient {} : {}", path.display(), message))
+
+    }
+
+    // A gradient through the given stops, in any order.
+    pub fn from_stops(mut stops: Vec<(f64, [u8; 3])>) -> Result<Self, String> {
+
+        if stops.is_empty() {
+            return Err("expected at least one stop".to_string());
+        }
+        if stops.len() > MAX_STOPS {
+            return Err(format!("expected at most {} stops, got {}", MAX_STOPS, stops.len()));
+        }
+        if let Some((position, _)) = stops.iter().find(|(position, _)| !(0.0..=1.0).contains(position)) {
+            return Err(format!("position {} must be between 0 and 1", position));
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut gradient = Gradient { stops: [(0.0, [0; 3]); MAX_STOPS], len: stops.len() };
+        gradient.stops[..stops.len()].copy_from_slice(&stops);
+
+        Ok(gradient)
+
+    }
+
+    pub fn stops(&self) -> &[(f64, [u8; 3])] {
+        &self.stops[..self.len]
+    }
+
+    pub fn sample(&self, t: f64) -> [u8; 3] {
+        self.levels(t).map(|level| level.round() as u8)
+    }
+
+    // The color at `t` before it is rounded to bytes.
+    pub fn levels(&self, t: f64) -> [f64; 3] {
+
+        let t = t.clamp(0.0, 1.0);
+
+        let stops = self.stops();
+        let index = match stops.iter().position(|stop| stop.0 >= t) {
+            None => return stops[stops.len() - 1].1.map(f64::from),
+            Some(0) => return stops[0].1.map(f64::from),
+            Some(index) => index
+        };
+
+        let (start, from) = stops[index - 1];
+        let (end, to) = stops[index];
+        let f = (t - start) / (end - start);
+        [0, 1, 2].map(|channel| from[channel] as f64 + f * (to[channel] as f64 - from[channel] as f64))
+
+    }
+
+}
+
+impl FromStr for Gradient {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        let mut stops = Vec::new();
+
+        for (number, line) in s.lines().enumerate() {
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || format!("line {} : expected 'position r g b', got '{}'", number + 1, line);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                return Err(invalid());
+            }
+
+            let position: f64 = fields[0].parse().map_err(|_| invalid())?;
+            if !(0.0..=1.0).contains(&position) {
+                return Err(format!("line {} : position {} must be between 0 and 1", number + 1, position));
+            }
+
+            let mut color = [0; 3];
+            for (channel, field) in color.iter_mut().zip(&fields[1..]) {
+                *channel = field.parse().map_err(|_| invalid())?;
+            }
+
+            stops.push((position, color));
+
+        }
+
+        Gradient::from_stops(stops)
+
+    }
+}
+
+#[test]
+fn test_gradient_sample() {
+    let gradient: Gradient = "# black to white\n0.0 0 0 0\n1.0 255 255 255\n".parse().unwrap();
+    assert_eq!(gradient.sample(0.0), [0, 0, 0]);
+    assert_eq!(gradient.sample(0.5), [128, 128, 128]);
+    assert_eq!(gradient.sample(1.0), [255, 255, 255]);
+    assert_eq!(gradient.sample(-1.0), [0, 0, 0]);
+    assert_eq!(gradient.sample(2.0), [255, 255, 255]);
+
+    let gradient: Gradient = "0.75 0 0 255\n0.25 255 0 0\n".parse().unwrap();
+    assert_eq!(gradient.sample(0.0), [255, 0, 0]);
+    assert_eq!(gradient.sample(0.5), [128, 0, 128]);
+    assert_eq!(gradient.sample(1.0), [0, 0, 255]);
+
+    assert!("".parse::<Gradient>().is_err());
+    assert!("0.5 0 0".parse::<Gradient>().is_err());
+    assert!("1.5 0 0 0".parse::<Gradient>().is_err());
+    assert!("0.5 0 0 256".parse::<Gradient>().is_err());
+
+    let many: String = (0..=MAX_STOPS).map(|stop| format!("{} 0 0 0\n", stop as f64 / MAX_STOPS as f64)).collect();
+    assert_eq!(many.parse::<Gradient>(), Err(format!("expected at most {} stops, got {}", MAX_STOPS, MAX_STOPS + 1)));
+    assert_eq!(many.lines().skip(1).collect::<Vec<_>>().join("\n").parse::<Gradient>().map(|gradient| gradient.stops().len()), Ok(MAX_STOPS));
+}
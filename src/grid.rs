@@ -0,0 +1,165 @@
+use crate::point_to_pixel;
+use num::Complex;
+
+pub const GRID_COLOR: [u8; 3] = [255, 64, 64];
+
+// Glyphs 3 pixels wide and 5 high, one row per byte with the leftmost pixel
+// in the highest of its 3 bits.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        'e' => [0b000, 0b111, 0b111, 0b100, 0b111],
+        'i' => [0b010, 0b000, 0b010, 0b010, 0b010],
+        _ => [0; 5]
+    }
+}
+
+// The multiples of `spacing` between `from` and `to`.
+fn multiples(from: f64, to: f64, spacing: f64) -> impl DoubleEndedIterator<Item = f64> {
+    let (first, last) = ((from / spacing).ceil() as i64, (to / spacing).floor() as i64);
+    (first..=last).map(move |index| index as f64 * spacing)
+}
+
+// Enough decimals to tell the multiples of `spacing` apart, so the labels
+// read 0.25 rather than 0.25000000000000006.
+fn decimals(spacing: f64) -> usize {
+    (0..10).find(|decimals| {
+               let scaled = spacing * 10f64.powi(*decimals as i32);
+               (scaled - scaled.round()).abs() < 1e-6 * scaled
+           })
+           .unwrap_or(10)
+}
+
+// A line's pixel column or row, with the part of the coordinates it marks.
+pub type GridLine = (usize, f64);
+
+// The pixel columns and rows of the lines at round real and imaginary parts.
+pub fn grid_lines(bounds: (usize, usize),
+                  upper_left: Complex<f64>,
+                  lower_right: Complex<f64>,
+                  spacing: f64)
+   -> (Vec<GridLine>, Vec<GridLine>)
+{
+
+    let column = |re: f64| point_to_pixel(bounds, Complex { re, im: upper_left.im }, upper_left, lower_right).0.round();
+    let row = |im: f64| point_to_pixel(bounds, Complex { re: upper_left.re, im }, upper_left, lower_right).1.round();
+
+    let columns = multiples(upper_left.re, lower_right.re, spacing).map(|re| (column(re), re))
+                                                                   .filter(|(x, _)| *x >= 0.0 && *x < bounds.0 as f64)
+                                                                   .map(|(x, re)| (x as usize, re))
+                                                                   .collect();
+    let rows = multiples(lower_right.im, upper_left.im, spacing).rev()
+                                                                .map(|im| (row(im), im))
+                                                                .filter(|(y, _)| *y >= 0.0 && *y < bounds.1 as f64)
+                                                                .map(|(y, im)| (y as usize, im))
+                                                                .collect();
+
+    (columns, rows)
+
+}
+
+// Draws lines every `spacing` along both axes over an 8 bit image, with the
+// real part of each vertical line labelled along the top edge and the
+// imaginary part of each horizontal line along the left edge.
+pub fn draw_grid(pixels: &mut [u8],
+                 bounds: (usize, usize),
+                 channels: usize,
+                 upper_left: Complex<f64>,
+                 lower_right: Complex<f64>,
+                 spacing: f64)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * channels);
+
+    let (columns, rows) = grid_lines(bounds, upper_left, lower_right, spacing);
+    let decimals = decimals(spacing);
+    let scale = (bounds.1 / 300).max(1);
+
+    let mut plot = |x: usize, y: usize| {
+        if x < bounds.0 && y < bounds.1 {
+            let pixel = &mut pixels[(y * bounds.0 + x) * channels..][..channels];
+            for (channel, value) in pixel.iter_mut().zip(GRID_COLOR.iter().chain(&[255])) {
+                *channel = *value;
+            }
+        }
+    };
+
+    for &(x, _) in &columns {
+        (0..bounds.1).for_each(|y| plot(x, y));
+    }
+    for &(y, _) in &rows {
+        (0..bounds.0).for_each(|x| plot(x, y));
+    }
+
+    let mut label = |text: String, left: usize, top: usize| {
+        for (index, c) in text.chars().enumerate() {
+            for (dy, bits) in glyph(c).iter().enumerate() {
+                for dx in (0..3).filter(|dx| bits >> (2 - dx) & 1 == 1) {
+                    for (sx, sy) in (0..scale).flat_map(|sx| (0..scale).map(move |sy| (sx, sy))) {
+                        plot(left + (index * 4 + dx) * scale + sx, top + dy * scale + sy);
+                    }
+                }
+            }
+        }
+    };
+
+    let margin = 2 * scale;
+    for &(x, re) in &columns {
+        label(format!("{:.*}", decimals, re), x + margin, margin);
+    }
+    // Rows at the very top would be labelled over the real parts.
+    for &(y, im) in rows.iter().filter(|(y, _)| *y > 6 * scale) {
+        label(format!("{:.*}i", decimals, im), margin, y + margin);
+    }
+
+}
+
+// Expands a grayscale image to RGB, so colored lines can be drawn over it.
+pub fn gray_to_rgb(pixels: &[u8]) -> Vec<u8> {
+    pixels.iter().flat_map(|gray| [*gray; 3]).collect()
+}
+
+#[test]
+fn test_grid_lines() {
+    let bounds = (400, 300);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.5 }, Complex { re: 2.0, im: -1.5 });
+
+    let (columns, rows) = grid_lines(bounds, upper_left, lower_right, 1.0);
+    assert_eq!(columns, vec![(0, -2.0), (100, -1.0), (200, 0.0), (300, 1.0)]);
+    assert_eq!(rows, vec![(50, 1.0), (150, 0.0), (250, -1.0)]);
+
+    let (columns, _) = grid_lines(bounds, upper_left, lower_right, 0.25);
+    assert_eq!(columns.len(), 16);
+    assert_eq!(columns[9], (225, 0.25));
+    assert_eq!((decimals(1.0), decimals(0.25), decimals(0.1), decimals(5e-4)), (0, 2, 1, 4));
+
+    // Lines cover whole rows and columns, away from the labels.
+    let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+    draw_grid(&mut pixels, bounds, 3, upper_left, lower_right, 1.0);
+    let pixel = |x: usize, y: usize| &pixels[(y * bounds.0 + x) * 3..][..3];
+    for y in [20, 120, 299] {
+        assert_eq!(pixel(100, y), GRID_COLOR);
+        assert_eq!(pixel(200, y), GRID_COLOR);
+    }
+    for x in [50, 250, 399] {
+        assert_eq!(pixel(x, 150), GRID_COLOR);
+    }
+    assert_eq!(pixel(150, 100), [0, 0, 0]);
+    assert_eq!(pixel(101, 100), [0, 0, 0]);
+
+    // The label of the line at re = 0 starts just right of it along the top.
+    assert!((202..214).any(|x| (2..7).any(|y| pixel(x, y) == GRID_COLOR)));
+
+    assert_eq!(gray_to_rgb(&[3, 200]), vec![3, 3, 3, 200, 200, 200]);
+}
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+// The order pixels are computed in. Rows hands whole rows to the threads,
+// Hilbert keeps consecutive pixels next to each other in the plane and Tiles
+// hands out square tiles, so slow interior rows are shared among threads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChunkOrder {
+    Rows,
+    Hilbert,
+    Tiles
+}
+
+impl ChunkOrder {
+    pub const NAMES: [&'static str; 3] = ["rows", "hilbert", "tiles"];
+}
+
+impl FromStr for ChunkOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rows" => Ok(ChunkOrder::Rows),
+            "hilbert" => Ok(ChunkOrder::Hilbert),
+            "tiles" => Ok(ChunkOrder::Tiles),
+            _ => Err(format!("Unknown chunk order {}, expected one of {}", s, ChunkOrder::NAMES.join(", ")))
+        }
+    }
+}
+
+// The point at distance `d` along the Hilbert curve filling a square of
+// `side` pixels, a power of two.
+pub(crate) fn hilbert_point(side: usize, d: usize) -> (usize, usize) {
+
+    let (mut x, mut y, mut t) = (0, 0, d);
+    let mut s = 1;
+
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+
+}
+
+#[test]
+fn test_hilbert_point() {
+    assert_eq!((0..4).map(|d| hilbert_point(2, d)).collect::<Vec<_>>(), vec![(0, 0), (0, 1), (1, 1), (1, 0)]);
+
+    for side in [1, 2, 8, 32] {
+        let order: Vec<(usize, usize)> = (0..side * side).map(|d| hilbert_point(side, d)).collect();
+        let mut visits = vec![0; side * side];
+        for &(x, y) in &order {
+            visits[y * side + x] += 1;
+        }
+        assert!(visits.iter().all(|visits| *visits == 1), "{}", side);
+
+        // Every step moves to a neighboring pixel.
+        assert!(order.windows(2).all(|pair| pair[0].0.abs_diff(pair[1].0) + pair[0].1.abs_diff(pair[1].1) == 1));
+    }
+
+    assert_eq!("hilbert".parse(), Ok(ChunkOrder::Hilbert));
+    assert_eq!("tiles".parse(), Ok(ChunkOrder::Tiles));
+    assert!("spiral".parse::<ChunkOrder>().is_err());
+}
@@ -0,0 +1,624 @@
+use crate::{Coloring, Config, Fractal, Gradient, Palette, Precision, Sampling, Trap};
+use num::Complex;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+// The JSON values render parameters are written as. Numbers keep the text
+// they were read from, so seeds beyond 2^53 survive the round trip.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+
+    fn integer<T: fmt::Display>(value: T) -> Json {
+        Json::Number(value.to_string())
+    }
+
+    // Infinities and NaN have no JSON form and are written as null, which
+    // reads back as an invalid value.
+    fn float(value: f64) -> Json {
+        if value.is_finite() { Json::Number(format!("{:?}", value)) } else { Json::Null }
+    }
+
+    fn complex(value: Complex<f64>) -> Json {
+        Json::Array(vec![Json::float(value.re), Json::float(value.im)])
+    }
+
+    fn tagged(name: &str, value: Json) -> Json {
+        Json::Object(vec![(name.to_string(), value)])
+    }
+
+    fn parse<T: FromStr>(&self) -> Option<T> {
+        match self {
+            Json::Number(text) => text.parse().ok(),
+            _ => None
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    fn as_complex(&self) -> Option<Complex<f64>> {
+        match self.as_array()? {
+            [re, im] => Some(Complex { re: re.parse()?, im: im.parse()? }),
+            _ => None
+        }
+    }
+
+    // The name of a variant, written either as a string or, with its value,
+    // as an object holding the one key.
+    fn as_tagged(&self) -> Option<(&str, Option<&Json>)> {
+        match self {
+            Json::String(name) => Some((name, None)),
+            Json::Object(members) if members.len() == 1 => Some((&members[0].0, Some(&members[0].1))),
+            _ => None
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(text) => f.write_str(text),
+            Json::String(text) => write_string(f, text),
+            Json::Array(values) => {
+                f.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    value.write(f, indent)?;
+                }
+                f.write_str("]")
+            },
+            Json::Object(members) if members.is_empty() => f.write_str("{}"),
+            Json::Object(members) => {
+                f.write_str("{\n")?;
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",\n")?;
+                    }
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    write_string(f, key)?;
+                    f.write_str(": ")?;
+                    value.write(f, indent + 2)?;
+                }
+                write!(f, "\n{:1$}}}", "", indent)
+            }
+        }
+    }
+
+}
+
+// Objects are written one member per line, arrays on a single line.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?
+        }
+    }
+    f.write_str("\"")
+
+}
+
+impl FromStr for Json {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        let mut parser = Parser { text: s, position: 0 };
+        let value = parser.value()?;
+
+        parser.skip_whitespace();
+        if parser.position < s.len() {
+            return Err(parser.error("the end of the input"));
+        }
+
+        Ok(value)
+
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize
+}
+
+impl Parser<'_> {
+
+    fn error(&self, expected: &str) -> String {
+        format!("expected {} at byte {}", expected, self.position)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    // Skips whitespace, then the given byte if it comes next.
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(byte);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.eat(byte) { Ok(()) } else { Err(self.error(&format!("'{}'", byte as char))) }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                let mut members = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        members.push((key, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return Err(self.error("',' or '}'"));
+                        }
+                    }
+                }
+                Ok(Json::Object(members))
+            },
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        values.push(self.value()?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return Err(self.error("',' or ']'"));
+                        }
+                    }
+                }
+                Ok(Json::Array(values))
+            },
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.position;
+                while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+                    self.position += 1;
+                }
+                let text = &self.text[start..self.position];
+                if text.parse::<f64>().is_err() {
+                    self.position = start;
+                    return Err(self.error("a number"));
+                }
+                Ok(Json::Number(text.to_string()))
+            },
+            _ => {
+                for (word, value) in [("true", Json::Bool(true)), ("false", Json::Bool(false)), ("null", Json::Null)] {
+                    if self.text[self.position..].starts_with(word) {
+                        self.position += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("a value"))
+            }
+        }
+
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+
+        if self.peek() != Some(b'"') {
+            return Err(self.error("a string"));
+        }
+        self.position += 1;
+
+        let mut string = String::new();
+        loop {
+            let c = self.text[self.position..].chars().next().ok_or_else(|| self.error("'\"'"))?;
+            self.position += c.len_utf8();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("an escape"))?;
+                    self.position += 1;
+                    string.push(match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode()?,
+                        _ => return Err(self.error("an escape"))
+                    });
+                },
+                c => string.push(c)
+            }
+        }
+
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let code = self.text.get(self.position..self.position + 4)
+                            .filter(|digits| digits.bytes().all(|digit| digit.is_ascii_hexdigit()))
+                            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                            .ok_or_else(|| self.error("4 hex digits"))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    // A \u escape, characters beyond the basic plane coming as a pair of
+    // surrogates.
+    fn unicode(&mut self) -> Result<char, String> {
+
+        let mut code = self.hex()?;
+        if (0xd800..0xdc00).contains(&code) {
+            if !self.text[self.position..].starts_with("\\u") {
+                return Err(self.error("a low surrogate"));
+            }
+            self.position += 2;
+            let low = self.hex()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("a low surrogate"));
+            }
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+
+        char::from_u32(code).ok_or_else(|| self.error("a character"))
+
+    }
+
+}
+
+// Everything a render is made from : the view, where it is known, and every
+// field of the config, defaults included. Keys are the config's field names,
+// and variants are written by name, with any value they carry under it, as
+// in "fractal": {"julia": [-0.8, 0.156]}. Keys left out of a document keep
+// their defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonConfig {
+    pub output: Option<String>,
+    pub bounds: Option<(usize, usize)>,
+    pub upper_left: Option<Complex<f64>>,
+    pub lower_right: Option<Complex<f64>>,
+    pub config: Config
+}
+
+impl JsonConfig {
+
+    // Reads a document from a file, or from standard input given "-".
+    pub fn load(path: &str) -> Result<Self, String> {
+
+        let mut contents = String::new();
+        let read = if path == "-" { io::stdin().read_to_string(&mut contents).map(|_| ()) } else { fs::read_to_string(path).map(|text| contents = text) };
+        read.map_err(|error| format!("Could not read JSON config {} : {}", path, error))?;
+
+        contents.parse().map_err(|message| format!("Invalid JSON config {} : {}", path, message))
+
+    }
+
+    pub fn to_json(&self) -> Json {
+
+        let config = &self.config;
+        let mut members = Vec::new();
+
+        if let Some(output) = &self.output {
+            members.push(("output", Json::String(output.clone())));
+        }
+        if let Some((width, height)) = self.bounds {
+            members.push(("bounds", Json::Array(vec![Json::integer(width), Json::integer(height)])));
+        }
+        if let Some(upper_left) = self.upper_left {
+            members.push(("upper_left", Json::complex(upper_left)));
+        }
+        if let Some(lower_right) = self.lower_right {
+            members.push(("lower_right", Json::complex(lower_right)));
+        }
+
+        let fractal = match config.fractal {
+            Fractal::Mandelbrot => Json::String("mandelbrot".to_string()),
+            Fractal::Julia(c) => Json::tagged("julia", Json::complex(c)),
+            Fractal::BurningShip => Json::String("burning_ship".to_string()),
+            Fractal::Newton => Json::String("newton".to_string())
+        };
+        let coloring = match config.coloring {
+            Coloring::EscapeTime => Json::String("escape_time".to_string()),
+            Coloring::Smooth => Json::String("smooth".to_string()),
+            Coloring::Distance => Json::String("distance".to_string()),
+            Coloring::OrbitTrap(trap) => Json::tagged("orbit_trap", Json::String(match trap {
+                Trap::Point => "point",
+                Trap::Line => "line",
+                Trap::Circle => "circle"
+            }.to_string())),
+            Coloring::Stripe(density) => Json::tagged("stripe", Json::float(density)),
+            Coloring::Lighting(light) => Json::tagged("lighting", Json::Object(vec![("azimuth".to_string(), Json::float(light.azimuth)),
+                                                                                     ("elevation".to_string(), Json::float(light.elevation))]))
+        };
+        let palette = match (config.palette.name(), config.palette) {
+            (Some(name), _) => Json::String(name.to_string()),
+            (None, Palette::Gradient(gradient)) => Json::tagged("gradient", Json::Array(gradient.stops().iter().map(|(position, color)| {
+                Json::Array(std::iter::once(Json::float(*position)).chain(color.iter().map(Json::integer)).collect())
+            }).collect())),
+            (None, palette) => unreachable!("{:?} has a name", palette)
+        };
+        let sampling = match config.sampling {
+            Sampling::Grid => Json::String("grid".to_string()),
+            Sampling::Random(seed) => Json::tagged("random", Json::integer(seed)),
+            Sampling::Sobol(seed) => Json::tagged("sobol", Json::integer(seed))
+        };
+        let precision = match config.precision {
+            Precision::Single => "single",
+            Precision::Double => "double",
+            Precision::DoubleDouble => "double_double"
+        };
+
+        members.extend([
+            ("limit", Json::integer(config.limit)),
+            ("fractal", fractal),
+            ("coloring", coloring),
+            ("palette", palette),
+            ("supersample", Json::integer(config.supersample)),
+            ("sampling", sampling),
+            ("supersample_threshold", Json::float(config.supersample_threshold)),
+            ("precision", Json::String(precision.to_string())),
+            ("power", Json::integer(config.power)),
+            ("bailout", Json::float(config.bailout)),
+            ("smooth_bias", Json::integer(config.smooth_bias)),
+            ("depth", Json::integer(config.depth)),
+            ("invert", Json::Bool(config.invert)),
+            ("interior", Json::Array(config.interior.iter().map(Json::integer).collect())),
+            ("transparent", Json::Bool(config.transparent)),
+            ("mirror", Json::Bool(config.mirror)),
+            ("gamma", Json::float(config.gamma)),
+            ("log_scale", Json::Bool(config.log_scale)),
+            ("dither", Json::Bool(config.dither))
+        ]);
+
+        Json::Object(members.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+
+        let members = match json {
+            Json::Object(members) => members,
+            _ => return Err("expected an object".to_string())
+        };
+
+        let mut parsed = JsonConfig::default();
+        let config = &mut parsed.config;
+
+        for (key, value) in members {
+
+            let invalid = || format!("invalid {} value {}", key, value);
+
+            match key.as_str() {
+                "output" => parsed.output = Some(value.as_str().ok_or_else(invalid)?.to_string()),
+                "bounds" => parsed.bounds = match value.as_array() {
+                    Some([width, height]) => Some((width.parse().ok_or_else(invalid)?, height.parse().ok_or_else(invalid)?)),
+                    _ => return Err(invalid())
+                },
+                "upper_left" => parsed.upper_left = Some(value.as_complex().ok_or_else(invalid)?),
+                "lower_right" => parsed.lower_right = Some(value.as_complex().ok_or_else(invalid)?),
+                "limit" => config.limit = value.parse().ok_or_else(invalid)?,
+                "fractal" => config.fractal = match value.as_tagged() {
+                    Some(("mandelbrot", None)) => Fractal::Mandelbrot,
+                    Some(("julia", Some(c))) => Fractal::Julia(c.as_complex().ok_or_else(invalid)?),
+                    Some(("burning_ship", None)) => Fractal::BurningShip,
+                    Some(("newton", None)) => Fractal::Newton,
+                    _ => return Err(invalid())
+                },
+                "coloring" => config.coloring = match value.as_tagged() {
+                    Some(("escape_time", None)) => Coloring::EscapeTime,
+                    Some(("smooth", None)) => Coloring::Smooth,
+                    Some(("distance", None)) => Coloring::Distance,
+                    Some(("orbit_trap", Some(trap))) => Coloring::OrbitTrap(trap.as_str().ok_or_else(invalid)?.parse()?),
+                    Some(("stripe", Some(density))) => Coloring::Stripe(density.parse().ok_or_else(invalid)?),
+                    Some(("lighting", Some(Json::Object(angles)))) => {
+                        let angle = |name: &str| angles.iter().find(|(key, _)| key == name).and_then(|(_, angle)| angle.parse());
+                        let (azimuth, elevation): (f64, f64) = (angle("azimuth").ok_or_else(invalid)?, angle("elevation").ok_or_else(invalid)?);
+                        Coloring::Lighting(format!("{},{}", azimuth, elevation).parse()?)
+                    },
+                    _ => return Err(invalid())
+                },
+                "palette" => config.palette = match value.as_tagged() {
+                    Some((name, None)) => name.parse()?,
+                    Some(("gradient", Some(stops))) => {
+                        let stop = |stop: &Json| match stop.as_array()? {
+                            [position, red, green, blue] => Some((position.parse()?, [red.parse()?, green.parse()?, blue.parse()?])),
+                            _ => None
+                        };
+                        let stops = stops.as_array().ok_or_else(invalid)?.iter().map(stop).collect::<Option<Vec<_>>>().ok_or_else(invalid)?;
+                        Palette::Gradient(Gradient::from_stops(stops).map_err(|message| format!("invalid gradient : {}", message))?)
+                    },
+                    _ => return Err(invalid())
+                },
+                "supersample" => config.supersample = value.parse().ok_or_else(invalid)?,
+                "sampling" => config.sampling = match value.as_tagged() {
+                    Some(("grid", None)) => Sampling::Grid,
+                    Some(("random", Some(seed))) => Sampling::Random(seed.parse().ok_or_else(invalid)?),
+                    Some(("sobol", Some(seed))) => Sampling::Sobol(seed.parse().ok_or_else(invalid)?),
+                    _ => return Err(invalid())
+                },
+                "supersample_threshold" => config.supersample_threshold = value.parse().ok_or_else(invalid)?,
+                "precision" => config.precision = match value.as_str() {
+                    Some("single") => Precision::Single,
+                    Some("double") => Precision::Double,
+                    Some("double_double") => Precision::DoubleDouble,
+                    _ => return Err(invalid())
+                },
+                "power" => config.power = value.parse().ok_or_else(invalid)?,
+                "bailout" => config.bailout = value.parse().ok_or_else(invalid)?,
+                "smooth_bias" => config.smooth_bias = value.parse().ok_or_else(invalid)?,
+                "depth" => config.depth = value.parse().ok_or_else(invalid)?,
+                "invert" => config.invert = value.as_bool().ok_or_else(invalid)?,
+                "interior" => config.interior = match value.as_array() {
+                    Some([red, green, blue]) => [red.parse().ok_or_else(invalid)?, green.parse().ok_or_else(invalid)?, blue.parse().ok_or_else(invalid)?],
+                    _ => return Err(invalid())
+                },
+                "transparent" => config.transparent = value.as_bool().ok_or_else(invalid)?,
+                "mirror" => config.mirror = value.as_bool().ok_or_else(invalid)?,
+                "gamma" => config.gamma = value.parse().ok_or_else(invalid)?,
+                "log_scale" => config.log_scale = value.as_bool().ok_or_else(invalid)?,
+                "dither" => config.dither = value.as_bool().ok_or_else(invalid)?,
+                _ => return Err(format!("unknown key {}", key))
+            }
+
+        }
+
+        Ok(parsed)
+
+    }
+
+}
+
+impl fmt::Display for JsonConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+impl FromStr for JsonConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonConfig::from_json(&s.parse()?)
+    }
+}
+
+#[test]
+fn test_json() {
+    let text = " {\"a\": [1, -2.5e3, true, null], \"b\" : {}, \"c\": \"q\\\"\\u00e9\\ud83d\\ude00\\n\", \"d\": []} ";
+    let json: Json = text.parse().unwrap();
+    assert_eq!(json, Json::Object(vec![
+        ("a".to_string(), Json::Array(vec![Json::Number("1".to_string()), Json::Number("-2.5e3".to_string()), Json::Bool(true), Json::Null])),
+        ("b".to_string(), Json::Object(vec![])),
+        ("c".to_string(), Json::String("q\"é😀\n".to_string())),
+        ("d".to_string(), Json::Array(vec![]))
+    ]));
+    assert_eq!(json.to_string(), "{\n  \"a\": [1, -2.5e3, true, null],\n  \"b\": {},\n  \"c\": \"q\\\"é😀\\n\",\n  \"d\": []\n}");
+    assert_eq!(json.to_string().parse::<Json>(), Ok(json));
+
+    assert_eq!("[1,]".parse::<Json>(), Err("expected a value at byte 3".to_string()));
+    assert_eq!("{\"a\" 1}".parse::<Json>(), Err("expected ':' at byte 5".to_string()));
+    assert_eq!("1 2".parse::<Json>(), Err("expected the end of the input at byte 2".to_string()));
+    assert_eq!("[1 2]".parse::<Json>(), Err("expected ',' or ']' at byte 3".to_string()));
+    assert!("\"open".parse::<Json>().is_err());
+    assert!("1.2.3".parse::<Json>().is_err());
+}
+
+#[test]
+fn test_json_config_round_trip() {
+    use crate::Light;
+
+    let gradient = "0 0 7 100\n0.5 255 170 0\n1 0 7 100\n".parse::<Gradient>().unwrap();
+
+    let configs = [
+        JsonConfig::default(),
+        JsonConfig {
+            output: Some("seahorse \"valley\".png".to_string()),
+            bounds: Some((800, 600)),
+            upper_left: Some(Complex { re: -0.7453, im: 0.1127 }),
+            lower_right: Some(Complex { re: -0.7451, im: 0.11255 }),
+            config: Config {
+                limit: 2000,
+                fractal: Fractal::Julia(Complex { re: -0.8, im: 0.156 }),
+                coloring: Coloring::Lighting(Light { azimuth: 30.0, elevation: 60.0 }),
+                palette: Palette::Gradient(gradient),
+                supersample: 3,
+                sampling: Sampling::Sobol(u64::MAX),
+                supersample_threshold: 0.1,
+                precision: Precision::Single,
+                power: 3,
+                bailout: 1e10,
+                smooth_bias: 2,
+                depth: 16,
+                invert: true,
+                interior: [255, 128, 0],
+                transparent: true,
+                mirror: true,
+                gamma: 2.2,
+                log_scale: true,
+                dither: true
+            }
+        },
+        JsonConfig { config: Config { coloring: Coloring::OrbitTrap(Trap::Circle), palette: Palette::Inferno, sampling: Sampling::Random(7), ..Config::default() }, ..JsonConfig::default() },
+        JsonConfig { config: Config { fractal: Fractal::Newton, coloring: Coloring::Stripe(5.0), precision: Precision::DoubleDouble, ..Config::default() }, ..JsonConfig::default() }
+    ];
+
+    for config in configs {
+        assert_eq!(config.to_string().parse::<JsonConfig>(), Ok(config.clone()), "{}", config);
+    }
+
+    // Every key is written, defaults included, and missing keys keep them.
+    let text = JsonConfig::default().to_string();
+    assert!(text.contains("\"limit\": 255") && text.contains("\"fractal\": \"mandelbrot\"") && text.contains("\"gamma\": 1.0"), "{}", text);
+    assert_eq!("{\"limit\": 50}".parse::<JsonConfig>(), Ok(JsonConfig { config: Config { limit: 50, ..Config::default() }, ..JsonConfig::default() }));
+
+    assert_eq!("{\"zoom\": 2}".parse::<JsonConfig>(), Err("unknown key zoom".to_string()));
+    assert_eq!("{\"limit\": -1}".parse::<JsonConfig>(), Err("invalid limit value -1".to_string()));
+    assert!("{\"fractal\": \"julia\"}".parse::<JsonConfig>().is_err());
+    assert!("{\"palette\": \"sepia\"}".parse::<JsonConfig>().unwrap_err().starts_with("Unknown palette sepia"));
+    assert!("{\"palette\": {\"gradient\": [[2, 0, 0, 0]]}}".parse::<JsonConfig>().unwrap_err().contains("must be between 0 and 1"));
+    let stops: Vec<String> = (0..=crate::gradient::MAX_STOPS).map(|_| "[0.5, 0, 0, 0]".to_string()).collect();
+    assert!(format!("{{\"palette\": {{\"gradient\": [{}]}}}}", stops.join(", ")).parse::<JsonConfig>().unwrap_err().contains("expected at most"));
+    assert!("[]".parse::<JsonConfig>().is_err());
+}
@@ -0,0 +1,3214 @@
+use num::{Complex, Float};
+use rayon::prelude::*;
+use std::str::FromStr;
+use image::ColorType;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use dither::dither;
+
+pub mod area;
+pub mod autofocus;
+pub mod batch;
+pub mod buddhabrot;
+pub mod checkpoint;
+pub mod compare;
+pub mod dither;
+pub mod double_double;
+pub mod error;
+pub mod gradient;
+pub mod grid;
+pub mod hilbert;
+pub mod json;
+pub mod mapped;
+pub mod mask;
+pub mod output;
+pub mod palette;
+pub mod perturbation;
+pub mod presets;
+pub mod profile;
+pub mod progress;
+pub mod render_config;
+pub mod resample;
+pub mod sampling;
+pub mod schedule;
+pub mod seamless;
+#[cfg(feature = "tile-server")]
+pub mod server;
+pub mod simd;
+pub mod stats;
+pub mod terminal;
+pub mod tile;
+pub mod webp;
+
+pub use area::{area_bounds, estimate_area, AreaEstimate};
+pub use autofocus::autofocus;
+pub use batch::parse_manifest;
+pub use buddhabrot::{antibuddhabrot_density, buddhabrot_density, nebulabrot_density, render_density, render_nebula, NebulaChannels};
+pub use checkpoint::Checkpoint;
+pub use compare::{compare, load_baseline, Difference};
+pub use double_double::DoubleDouble;
+pub use error::RenderError;
+pub use gradient::Gradient;
+pub use hilbert::ChunkOrder;
+pub use json::{Json, JsonConfig};
+pub use mapped::MappedBuffer;
+pub use mask::load_mask;
+pub use output::{read_png_text, write_counts, write_image, write_image_with_metadata, write_ppm, write_raw16, METADATA_KEYWORD};
+pub use palette::Palette;
+pub use perturbation::render_perturbation;
+pub use presets::{find_preset, Preset, PRESETS};
+pub use profile::Profile;
+pub use progress::Progress;
+pub use render_config::RenderConfig;
+pub use sampling::Sampling;
+pub use schedule::IterationSchedule;
+pub use seamless::make_seamless;
+pub use stats::RenderStats;
+pub use tile::{write_streamed_png, write_tiled_png};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fractal {
+    Mandelbrot,
+    Julia(Complex<f64>),
+    BurningShip,
+    Newton
+}
+
+impl Fractal {
+    pub fn escape_time<T: Float>(&self, point: Complex<T>, config: &Config) -> Option<usize> {
+        self.escape(point, config).map(|(time, _)| time)
+    }
+
+    pub fn escape_time_smooth<T: Float>(&self, point: Complex<T>, config: &Config) -> Option<f64> {
+        match *self {
+            Fractal::Newton => newton(point, config.limit).map(|(_, time)| time as f64),
+            _ => self.escape(point, config).map(|(time, z)| {
+                let (c, fold) = match *self {
+                    Fractal::Julia(c) => (cast(c), false),
+                    _ => (point, *self == Fractal::BurningShip)
+                };
+                biased_smooth_time(time, z, c, config.power, fold, config.smooth_bias)
+            })
+        }
+    }
+
+    pub fn escape_time_distance<T: Float>(&self, point: Complex<T>, config: &Config) -> Option<f64> {
+
+        let origin = Complex { re: T::zero(), im: T::zero() };
+        let bailout = float(config.bailout * config.bailout);
+
+        match *self {
+            Fractal::Mandelbrot if config.power == 2 && in_cardioid_or_bulb(point) => None,
+            Fractal::Mandelbrot => escape_distance(origin, point, config.limit, config.power, bailout, false, false),
+            Fractal::Julia(c) => escape_distance(point, cast(c), config.limit, config.power, bailout, false, true),
+            Fractal::BurningShip => escape_distance(origin, point, config.limit, config.power, bailout, true, false),
+            Fractal::Newton => None
+        }
+
+    }
+
+    pub fn escape_time_orbit_trap<T: Float>(&self, point: Complex<T>, config: &Config, trap: Trap) -> Option<f64> {
+
+        let origin = Complex { re: T::zero(), im: T::zero() };
+        let bailout = float(config.bailout * config.bailout);
+
+        match *self {
+            Fractal::Mandelbrot if config.power == 2 && in_cardioid_or_bulb(point) => None,
+            Fractal::Mandelbrot => escape_orbit_trap(origin, point, config.limit, config.power, bailout, false, trap),
+            Fractal::Julia(c) => escape_orbit_trap(point, cast(c), config.limit, config.power, bailout, false, trap),
+            Fractal::BurningShip => escape_orbit_trap(origin, point, config.limit, config.power, bailout, true, trap),
+            Fractal::Newton => None
+        }
+
+    }
+
+    // The smooth escape count along with the stripe average.
+    pub fn escape_time_stripe<T: Float>(&self, point: Complex<T>, config: &Config, density: f64) -> Option<(f64, f64)> {
+
+        let origin = Complex { re: T::zero(), im: T::zero() };
+        let bailout = float(config.bailout * config.bailout);
+
+        match *self {
+            Fractal::Mandelbrot if config.power == 2 && in_cardioid_or_bulb(point) => None,
+            Fractal::Mandelbrot => escape_stripe(origin, point, config.limit, config.power, bailout, false, density),
+            Fractal::Julia(c) => escape_stripe(point, cast(c), config.limit, config.power, bailout, false, density),
+            Fractal::BurningShip => escape_stripe(origin, point, config.limit, config.power, bailout, true, density),
+            Fractal::Newton => None
+        }
+
+    }
+
+    // The unit vector along z / dz where the point escaped, which points away
+    // from the set like the slope of the distance estimate.
+    pub fn escape_time_normal<T: Float>(&self, point: Complex<T>, config: &Config) -> Option<Complex<f64>> {
+
+        let origin = Complex { re: T::zero(), im: T::zero() };
+        let bailout = float(config.bailout * config.bailout);
+
+        match *self {
+            Fractal::Mandelbrot if config.power == 2 && in_cardioid_or_bulb(point) => None,
+            Fractal::Mandelbrot => escape_normal(origin, point, config.limit, config.power, bailout, false, false),
+            Fractal::Julia(c) => escape_normal(point, cast(c), config.limit, config.power, bailout, false, true),
+            Fractal::BurningShip => escape_normal(origin, point, config.limit, config.power, bailout, true, false),
+            Fractal::Newton => None
+        }
+
+    }
+
+    fn escape<T: Float>(&self, point: Complex<T>, config: &Config) -> Option<(usize, Complex<T>)> {
+
+        let origin = Complex { re: T::zero(), im: T::zero() };
+        let bailout = float(config.bailout * config.bailout);
+
+        match *self {
+            Fractal::Mandelbrot if config.power == 2 && in_cardioid_or_bulb(point) => None,
+            Fractal::Mandelbrot => escape(origin, point, config.limit, config.power, bailout, false),
+            Fractal::Julia(c) => escape(point, cast(c), config.limit, config.power, bailout, false),
+            Fractal::BurningShip => escape(origin, point, config.limit, config.power, bailout, true),
+            Fractal::Newton => newton(point, config.limit).map(|(_, time)| (time, point))
+        }
+
+    }
+
+    // How many steps escape-time coloring takes on the point, which is 0 for
+    // points the cardioid and bulb check excludes up front.
+    pub fn executed_iterations<T: Float>(&self, point: Complex<T>, config: &Config) -> usize {
+
+        let origin = Complex { re: T::zero(), im: T::zero() };
+        let bailout = float(config.bailout * config.bailout);
+
+        match *self {
+            Fractal::Mandelbrot if config.power == 2 && in_cardioid_or_bulb(point) => 0,
+            Fractal::Mandelbrot => iterate_with(origin, point, config.limit, config.power, bailout, false, Derivative::Skip, |_| {}).steps,
+            Fractal::Julia(c) => iterate_with(point, cast(c), config.limit, config.power, bailout, false, Derivative::Skip, |_| {}).steps,
+            Fractal::BurningShip => iterate_with(origin, point, config.limit, config.power, bailout, true, Derivative::Skip, |_| {}).steps,
+            Fractal::Newton => newton(point, config.limit).map_or(config.limit, |(_, time)| time)
+        }
+
+    }
+
+    // The iterates after the starting value, up to the first one past the
+    // bailout. Newton's method has no escape orbit, so its orbit is empty.
+    pub fn orbit(&self, point: Complex<f64>, config: &Config) -> Vec<Complex<f64>> {
+
+        let origin = Complex { re: 0.0, im: 0.0 };
+        let bailout = config.bailout * config.bailout;
+
+        match *self {
+            Fractal::Mandelbrot => orbit(origin, point, config.limit, config.power, bailout, false),
+            Fractal::Julia(c) => orbit(point, c, config.limit, config.power, bailout, false),
+            Fractal::BurningShip => orbit(origin, point, config.limit, config.power, bailout, true),
+            Fractal::Newton => Vec::new()
+        }
+
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coloring {
+    EscapeTime,
+    Smooth,
+    Distance,
+    OrbitTrap(Trap),
+    /// Stripe average coloring, with the number of stripes per turn.
+    Stripe(f64),
+    /// Lambert lighting of the surface whose normals are z / dz.
+    Lighting(Light)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trap {
+    Point,
+    Line,
+    Circle
+}
+
+impl Trap {
+
+    pub const NAMES: [&'static str; 3] = ["point", "line", "circle"];
+
+    // The traps are the origin, the real axis and the unit circle.
+    pub fn distance<T: Float>(&self, z: Complex<T>) -> f64 {
+        let distance = match *self {
+            Trap::Point => z.norm(),
+            Trap::Line => z.im.abs(),
+            Trap::Circle => (z.norm() - T::one()).abs()
+        };
+        distance.to_f64().unwrap()
+    }
+
+}
+
+impl FromStr for Trap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "point" => Ok(Trap::Point),
+            "line" => Ok(Trap::Line),
+            "circle" => Ok(Trap::Circle),
+            _ => Err(format!("Unknown trap {}, expected one of {}", s, Trap::NAMES.join(", ")))
+        }
+    }
+}
+
+// A light at infinity, with angles in degrees : the azimuth counterclockwise
+// from the positive real axis and the elevation above the plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub azimuth: f64,
+    pub elevation: f64
+}
+
+impl Light {
+
+    // How brightly the surface is lit where its slope points along the unit
+    // vector `normal`, the surface normal being (normal, 1) / sqrt(2).
+    pub fn brightness(&self, normal: Complex<f64>) -> f64 {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        let along = normal.re * azimuth.cos() + normal.im * azimuth.sin();
+        ((along * elevation.cos() + elevation.sin()) / 2f64.sqrt()).max(0.0)
+    }
+
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light { azimuth: 45.0, elevation: 45.0 }
+    }
+}
+
+impl FromStr for Light {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_pair::<f64>(s, ',') {
+            Some((azimuth, elevation)) if azimuth.is_finite() && (0.0..=90.0).contains(&elevation) => Ok(Light { azimuth, elevation }),
+            _ => Err(format!("Invalid light {}, expected azimuth,elevation in degrees with the elevation between 0 and 90", s))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    Single,
+    Double,
+    DoubleDouble
+}
+
+impl Precision {
+    // The narrowest type with at least `bits` of mantissa.
+    pub fn from_bits(bits: u32) -> Result<Self, String> {
+        match bits {
+            1..=24 => Ok(Precision::Single),
+            25..=53 => Ok(Precision::Double),
+            54..=106 => Ok(Precision::DoubleDouble),
+            _ => Err(format!("Precision must be between 1 and 106 bits, got {}", bits))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub limit: usize,
+    pub fractal: Fractal,
+    pub coloring: Coloring,
+    pub palette: Palette,
+    pub supersample: usize,
+    /// Pattern of the supersamples within each pixel.
+    pub sampling: Sampling,
+    /// Supersampling stops once the standard error of each channel's running
+    /// mean, in levels, is within this tolerance. 0 takes every sample.
+    pub supersample_threshold: f64,
+    pub precision: Precision,
+    pub power: u32,
+    /// Escape radius. Smooth coloring is more accurate with a large radius,
+    /// so raising it (e.g. to 100) removes most of its residual banding.
+    pub bailout: f64,
+    /// Extra steps smooth coloring takes past the escape, which leave the
+    /// count alone but grow |z| so its fractional part is accurate even at
+    /// the default radius.
+    pub smooth_bias: usize,
+    pub depth: u8,
+    pub invert: bool,
+    /// Color of points in the set, of which grayscale images use the first channel.
+    pub interior: [u8; 3],
+    /// Adds an alpha channel that is clear for points in the set.
+    pub transparent: bool,
+    /// Copies the rows below the real axis from the ones above it when the
+    /// view is centered on the axis and the fractal is symmetric about it.
+    pub mirror: bool,
+    /// Shades and palette channels are raised to 1 / gamma, so above 1 it
+    /// lifts dark regions.
+    pub gamma: f64,
+    /// Shades escape counts by ln(1 + count) / ln(1 + limit) rather than by
+    /// count / limit, which spreads out the many quickly escaping points.
+    pub log_scale: bool,
+    /// Quantizes colors with ordered dithering rather than rounding, which
+    /// breaks up the bands of smooth gradients.
+    pub dither: bool
+}
+
+impl Config {
+    pub fn channels(&self) -> usize {
+        if self.transparent { 4 } else if self.palette.is_grayscale() { 1 } else { 3 }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.channels() * self.depth as usize / 8
+    }
+
+    pub fn color_type(&self) -> ColorType {
+        match self.channels() {
+            1 => ColorType::Gray(self.depth),
+            3 => ColorType::RGB(self.depth),
+            _ => ColorType::RGBA(self.depth)
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            limit: 255,
+            fractal: Fractal::Mandelbrot,
+            coloring: Coloring::EscapeTime,
+            palette: Palette::Grayscale,
+            supersample: 1,
+            sampling: Sampling::Grid,
+            supersample_threshold: 0.0,
+            precision: Precision::Double,
+            power: 2,
+            bailout: 2.0,
+            smooth_bias: 0,
+            depth: 8,
+            invert: false,
+            interior: [0; 3],
+            transparent: false,
+            mirror: false,
+            gamma: 1.0,
+            log_scale: false,
+            dither: false
+        }
+    }
+}
+
+fn float<T: Float>(value: f64) -> T {
+    T::from(value).unwrap()
+}
+
+fn cast<T: Float>(c: Complex<f64>) -> Complex<T> {
+    Complex { re: float(c.re), im: float(c.im) }
+}
+
+pub fn escape_time<T: Float>(c: Complex<T>, limit: usize) -> Option<usize> {
+    if in_cardioid_or_bulb(c) {
+        return None;
+    }
+    escape(Complex { re: T::zero(), im: T::zero() }, c, limit, 2, float(4.0), false).map(|(time, _)| time)
+}
+
+pub fn in_cardioid_or_bulb<T: Float>(c: Complex<T>) -> bool {
+
+    let quarter = float::<T>(0.25);
+    let q = (c.re - quarter) * (c.re - quarter) + c.im * c.im;
+    let in_cardioid = q * (q + (c.re - quarter)) <= quarter * c.im * c.im;
+    let in_bulb = (c.re + T::one()) * (c.re + T::one()) + c.im * c.im <= float(1.0 / 16.0);
+
+    in_cardioid || in_bulb
+
+}
+
+pub fn julia_escape_time<T: Float>(z: Complex<T>, c: Complex<T>, limit: usize) -> Option<usize> {
+    multibrot_escape_time(z, c, limit, 2)
+}
+
+pub fn multibrot_escape_time<T: Float>(z: Complex<T>, c: Complex<T>, limit: usize, power: u32) -> Option<usize> {
+    escape(z, c, limit, power, float(4.0), false).map(|(time, _)| time)
+}
+
+pub fn burning_ship_escape_time<T: Float>(c: Complex<T>, limit: usize, power: u32) -> Option<usize> {
+    escape(Complex { re: T::zero(), im: T::zero() }, c, limit, power, float(4.0), true).map(|(time, _)| time)
+}
+
+fn escape<T: Float>(z: Complex<T>,
+                    c: Complex<T>,
+                    limit: usize,
+                    power: u32,
+                    bailout_sqr: T,
+                    fold: bool)
+   -> Option<(usize, Complex<T>)>
+{
+    let iterated = iterate_with(z, c, limit, power, bailout_sqr, fold, Derivative::Skip, |_| {});
+    iterated.count.map(|time| (time, iterated.z))
+}
+
+// Where the orbit of a point stood when iteration stopped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Iterated<T = f64> {
+    /// The step at which |z| passed the bailout radius, or None for points
+    /// that reached the limit or were caught in a cycle.
+    pub count: Option<usize>,
+    /// The last iterate, which is past the bailout radius exactly when the
+    /// point escaped.
+    pub z: Complex<T>,
+    /// The derivative of z with respect to c, or to the starting point for
+    /// Julia sets.
+    pub dz: Complex<T>,
+    /// Steps actually taken, short of the limit when a cycle is caught early.
+    pub steps: usize
+}
+
+// Iterates z -> z^2 + c from `z0` for the Mandelbrot set at c, or the Julia
+// set of c, tracking the derivative with respect to c. This is the loop every
+// coloring builds on, for tools that color from the final z themselves.
+//
+// Unlike the internal helpers, which take `(z, c)` and a squared bailout,
+// this takes `(c, z0)` in the order callers think of them and the bailout
+// radius itself.
+pub fn iterate<T: Float>(c: Complex<T>, z0: Complex<T>, limit: usize, bailout: T) -> Iterated<T> {
+    iterate_with(z0, c, limit, 2, bailout * bailout, false, Derivative::Parameter, |_| {})
+}
+
+// Which derivative of the orbit to carry along, if any.
+#[derive(Clone, Copy, PartialEq)]
+enum Derivative {
+    Skip,
+    Parameter,
+    Start
+}
+
+// The iteration loop shared by all colorings, with the absolute values taken
+// before each step for the Burning Ship, and `visit` called on each iterate
+// after the starting value.
+#[allow(clippy::too_many_arguments)]
+fn iterate_with<T: Float, F: FnMut(Complex<T>)>(mut z: Complex<T>,
+                                                c: Complex<T>,
+                                                limit: usize,
+                                                power: u32,
+                                                bailout_sqr: T,
+                                                fold: bool,
+                                                derivative: Derivative,
+                                                mut visit: F)
+   -> Iterated<T>
+{
+    let one = Complex { re: T::one(), im: T::zero() };
+    let mut dz = if derivative == Derivative::Start { one } else { Complex { re: T::zero(), im: T::zero() } };
+    let mut periodicity = Periodicity::new(z);
+    for i in 0..limit {
+        if z.norm_sqr() > bailout_sqr {
+            return Iterated { count: Some(i), z, dz, steps: i };
+        }
+        // An iterate after the last check could only escape past the limit.
+        if i + 1 == limit {
+            break;
+        }
+        if fold {
+            z = Complex { re: z.re.abs(), im: z.im.abs() };
+        }
+        if derivative != Derivative::Skip {
+            dz = z.powu(power - 1) * dz * float::<T>(power as f64);
+            if derivative == Derivative::Parameter {
+                dz = dz + one;
+            }
+        }
+        z = step(z, c, power);
+        visit(z);
+        if periodicity.is_periodic(z) {
+            return Iterated { count: None, z, dz, steps: i + 1 };
+        }
+    }
+    Iterated { count: None, z, dz, steps: limit }
+}
+
+// Like `escape`, but keeping every iterate and without the periodicity check,
+// which would cut the orbit short.
+fn orbit<T: Float>(mut z: Complex<T>,
+                   c: Complex<T>,
+                   limit: usize,
+                   power: u32,
+                   bailout_sqr: T,
+                   fold: bool)
+   -> Vec<Complex<T>>
+{
+    let mut orbit = Vec::new();
+    for _ in 0..limit {
+        if z.norm_sqr() > bailout_sqr {
+            break;
+        }
+        if fold {
+            z = Complex { re: z.re.abs(), im: z.im.abs() };
+        }
+        z = step(z, c, power);
+        orbit.push(z);
+    }
+    orbit
+}
+
+fn step<T: Float>(z: Complex<T>, c: Complex<T>, power: u32) -> Complex<T> {
+    if power == 2 {
+        z * z + c
+    } else {
+        z.powu(power) + c
+    }
+}
+
+struct Periodicity<T> {
+    reference: Complex<T>,
+    epsilon: T,
+    steps: usize,
+    period: usize
+}
+
+const PERIODICITY_EPSILON: f64 = 1e-20;
+
+impl<T: Float> Periodicity<T> {
+
+    fn new(z: Complex<T>) -> Self {
+        // Types finer than f64 shrink the threshold with their squared epsilon,
+        // so near misses that deep zooms can resolve are not mistaken for cycles.
+        let scale = (T::epsilon() / float(f64::EPSILON)).powi(2).min(T::one());
+        Periodicity { reference: z, epsilon: float::<T>(PERIODICITY_EPSILON) * scale, steps: 0, period: 8 }
+    }
+
+    fn is_periodic(&mut self, z: Complex<T>) -> bool {
+
+        if (z - self.reference).norm_sqr() < self.epsilon {
+            return true;
+        }
+
+        self.steps += 1;
+        if self.steps == self.period {
+            self.reference = z;
+            self.steps = 0;
+            self.period *= 2;
+        }
+
+        false
+
+    }
+
+}
+
+pub fn escape_time_smooth<T: Float>(c: Complex<T>, limit: usize) -> Option<f64> {
+    if in_cardioid_or_bulb(c) {
+        return None;
+    }
+    julia_escape_time_smooth(Complex { re: T::zero(), im: T::zero() }, c, limit)
+}
+
+pub fn julia_escape_time_smooth<T: Float>(z: Complex<T>, c: Complex<T>, limit: usize) -> Option<f64> {
+    multibrot_escape_time_smooth(z, c, limit, 2)
+}
+
+pub fn multibrot_escape_time_smooth<T: Float>(z: Complex<T>, c: Complex<T>, limit: usize, power: u32) -> Option<f64> {
+    escape(z, c, limit, power, float(4.0), false).map(|(time, z)| smooth_time(time, z, power))
+}
+
+pub fn burning_ship_escape_time_smooth<T: Float>(c: Complex<T>, limit: usize, power: u32) -> Option<f64> {
+    escape(Complex { re: T::zero(), im: T::zero() }, c, limit, power, float(4.0), true).map(|(time, z)| smooth_time(time, z, power))
+}
+
+pub fn escape_time_distance<T: Float>(c: Complex<T>, limit: usize) -> Option<f64> {
+    if in_cardioid_or_bulb(c) {
+        return None;
+    }
+    escape_distance(Complex { re: T::zero(), im: T::zero() }, c, limit, 2, float(4.0), false, false)
+}
+
+// Tracks the derivative dz of the orbit with respect to c (or z0 for Julia
+// sets) to estimate the distance from the starting point to the set.
+fn escape_distance<T: Float>(z: Complex<T>,
+                             c: Complex<T>,
+                             limit: usize,
+                             power: u32,
+                             bailout_sqr: T,
+                             fold: bool,
+                             julia: bool)
+   -> Option<f64>
+{
+    let derivative = if julia { Derivative::Start } else { Derivative::Parameter };
+    let iterated = iterate_with(z, c, limit, power, bailout_sqr, fold, derivative, |_| {});
+    iterated.count.map(|_| {
+        let norm = iterated.z.norm().to_f64().unwrap();
+        norm * norm.ln() / iterated.dz.norm().to_f64().unwrap()
+    })
+}
+
+fn escape_normal<T: Float>(z: Complex<T>,
+                           c: Complex<T>,
+                           limit: usize,
+                           power: u32,
+                           bailout_sqr: T,
+                           fold: bool,
+                           julia: bool)
+   -> Option<Complex<f64>>
+{
+    let derivative = if julia { Derivative::Start } else { Derivative::Parameter };
+    let iterated = iterate_with(z, c, limit, power, bailout_sqr, fold, derivative, |_| {});
+    iterated.count.map(|_| {
+        let u = iterated.z / iterated.dz;
+        let u = Complex { re: u.re.to_f64().unwrap(), im: u.im.to_f64().unwrap() };
+        u / u.norm()
+    })
+}
+
+pub fn escape_time_orbit_trap<T: Float>(c: Complex<T>, limit: usize, trap: Trap) -> Option<f64> {
+    if in_cardioid_or_bulb(c) {
+        return None;
+    }
+    escape_orbit_trap(Complex { re: T::zero(), im: T::zero() }, c, limit, 2, float(4.0), false, trap)
+}
+
+// Returns the closest the orbit came to the trap before escaping.
+fn escape_orbit_trap<T: Float>(z: Complex<T>,
+                               c: Complex<T>,
+                               limit: usize,
+                               power: u32,
+                               bailout_sqr: T,
+                               fold: bool,
+                               trap: Trap)
+   -> Option<f64>
+{
+    let mut min_distance = f64::INFINITY;
+    let iterated = iterate_with(z, c, limit, power, bailout_sqr, fold, Derivative::Skip, |z| min_distance = min_distance.min(trap.distance(z)));
+    iterated.count.map(|_| min_distance)
+}
+
+// Averages sin(density * arg(z)) over the iterates. The averages with and
+// without the last iterate are blended by the fractional part of the smooth
+// count, so the stripes flow continuously across escape count boundaries.
+fn escape_stripe<T: Float>(z: Complex<T>,
+                           c: Complex<T>,
+                           limit: usize,
+                           power: u32,
+                           bailout_sqr: T,
+                           fold: bool,
+                           density: f64)
+   -> Option<(f64, f64)>
+{
+    let (mut sum, mut last) = (0.0, 0.0);
+    let iterated = iterate_with(z, c, limit, power, bailout_sqr, fold, Derivative::Skip, |z| {
+        last = (density * z.im.to_f64().unwrap().atan2(z.re.to_f64().unwrap())).sin();
+        sum += last;
+    });
+    iterated.count.map(|i| {
+        let time = smooth_time(i, iterated.z, power);
+        if i == 0 {
+            return (time, 0.0);
+        }
+        let average = sum / i as f64;
+        let previous = if i > 1 { (sum - last) / (i - 1) as f64 } else { average };
+        let fraction = (time - i as f64).clamp(0.0, 1.0);
+        (time, fraction * average + (1.0 - fraction) * previous)
+    })
+}
+
+// Squared distance to a root below which Newton's method has converged.
+const NEWTON_TOLERANCE: f64 = 1e-12;
+
+// Newton's method on z^3 - 1 from `z`, returning which cube root of unity it
+// converges to, in the order 1, e^(2i pi/3), e^(-2i pi/3), and after how many
+// steps. Starting points whose derivative vanishes never converge.
+pub fn newton<T: Float>(mut z: Complex<T>, limit: usize) -> Option<(usize, usize)> {
+
+    let half_root_three = float::<T>(3f64.sqrt() / 2.0);
+    let roots = [Complex { re: T::one(), im: T::zero() },
+                 Complex { re: float(-0.5), im: half_root_three },
+                 Complex { re: float(-0.5), im: -half_root_three }];
+
+    for i in 0..limit {
+
+        if let Some(root) = roots.iter().position(|root| (z - *root).norm_sqr() < float(NEWTON_TOLERANCE)) {
+            return Some((root, i));
+        }
+
+        let z_sqr = z * z;
+        if z_sqr.norm_sqr() == T::zero() {
+            return None;
+        }
+        z = z - (z_sqr * z - T::one()) / (z_sqr * float::<T>(3.0));
+
+    }
+
+    None
+
+}
+
+// The escape count made continuous. Each step raises |z| to the given power
+// once it is large, so the double logarithm is normalized by that power.
+fn smooth_time<T: Float>(time: usize, z: Complex<T>, power: u32) -> f64 {
+    time as f64 + 1.0 - z.norm().to_f64().unwrap().ln().ln() / (power as f64).ln()
+}
+
+// The smooth count after up to `bias` more steps from the escaped `z`. The
+// double logarithm assumes |z| is large, which it barely is right past a small
+// bailout radius, so each step brings the count closer to the exact value.
+// Steps that would overflow are not taken.
+pub(crate) fn biased_smooth_time<T: Float>(time: usize, mut z: Complex<T>, c: Complex<T>, power: u32, fold: bool, bias: usize) -> f64 {
+    let mut taken = 0;
+    for _ in 0..bias {
+        let folded = if fold { Complex { re: z.re.abs(), im: z.im.abs() } } else { z };
+        let next = step(folded, c, power);
+        if !next.norm_sqr().is_finite() {
+            break;
+        }
+        z = next;
+        taken += 1;
+    }
+    smooth_time(time + taken, z, power)
+}
+
+pub fn parse_pair<T: FromStr>(s: &str, separator: char) -> Option<(T, T)> {
+    
+    match s.find(separator) {
+        None => None,
+        Some(index) => {
+            match (T::from_str(&s[..index]), T::from_str(&s[index + 1..])) {
+                (Ok(x), Ok(y)) => Some((x, y)),
+                _ => None
+            }
+        }
+    }
+
+}
+
+// A pixel window written as x,y,width,height.
+pub fn parse_crop(s: &str) -> Option<((usize, usize), (usize, usize))> {
+
+    let values: Vec<usize> = s.split(',').map(|value| value.trim().parse().ok()).collect::<Option<_>>()?;
+
+    match values[..] {
+        [x, y, width, height] => Some(((x, y), (width, height))),
+        _ => None
+    }
+
+}
+
+// Either RRGGBB in hexadecimal, or a single decimal byte for a gray.
+pub fn parse_color(s: &str) -> Option<[u8; 3]> {
+
+    if s.len() == 6 {
+        let channel = |index: usize| u8::from_str_radix(s.get(index..index + 2)?, 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+
+    s.parse().ok().map(|value| [value; 3])
+
+}
+
+pub fn parse_bounds(s: &str) -> Result<(usize, usize), RenderError> {
+    parse_pair(s, 'x').ok_or_else(|| RenderError::ParseBounds(s.to_string()))
+}
+
+pub fn parse_complex(s: &str) -> Result<Complex<f64>, RenderError> {
+    parse_complex_parts(s).ok_or_else(|| RenderError::ParseComplex(s.to_string()))
+}
+
+fn parse_complex_parts(s: &str) -> Option<Complex<f64>> {
+
+    if s.contains(',') {
+        let (re, im) = parse_pair::<String>(s, ',')?;
+        return Some(Complex { re: parse_component(&re)?, im: parse_component(&im)? });
+    }
+
+    let imaginary = match s.strip_suffix('i') {
+        None => return parse_component(s).map(|re| Complex { re, im: 0.0 }),
+        Some(imaginary) => imaginary
+    };
+
+    // The sign separating both parts is the last one that does not start the
+    // string or belong to an exponent like 1e-3.
+    let split = imaginary.char_indices()
+        .rev()
+        .find(|&(index, c)| (c == '+' || c == '-') && index > 0 && !imaginary[..index].ends_with(['e', 'E']))
+        .map(|(index, _)| index);
+
+    let (re, im) = match split {
+        None => (0.0, imaginary),
+        Some(index) => (parse_component(&imaginary[..index])?, &imaginary[index..])
+    };
+
+    let im = match im {
+        "" | "+" => 1.0,
+        "-" => -1.0,
+        im => parse_component(im)?
+    };
+
+    Some(Complex { re, im })
+
+}
+
+// A plain float such as -7.5e-3, or a fraction of two of them such as 1/3.
+fn parse_component(s: &str) -> Option<f64> {
+
+    match parse_pair::<f64>(s, '/') {
+        Some((_, 0.0)) => None,
+        Some((num, den)) => Some(num / den),
+        None if s.contains('/') => None,
+        None => s.parse().ok()
+    }
+
+}
+
+pub fn validate_bounds(bounds: (usize, usize),
+                       upper_left: Complex<f64>,
+                       lower_right: Complex<f64>)
+   -> Result<(), RenderError>
+{
+
+    if bounds.0 == 0 || bounds.1 == 0 {
+        return Err(RenderError::InvalidBounds(format!("Image bounds {}x{} must be at least 1x1", bounds.0, bounds.1)));
+    }
+    if upper_left.re >= lower_right.re {
+        return Err(RenderError::InvalidBounds(format!("Upper left real part {} must be less than lower right real part {}", upper_left.re, lower_right.re)));
+    }
+    if upper_left.im <= lower_right.im {
+        return Err(RenderError::InvalidBounds(format!("Upper left imaginary part {} must be greater than lower right imaginary part {}", upper_left.im, lower_right.im)));
+    }
+
+    Ok(())
+
+}
+
+pub fn pixel_to_point<T: Float>(bounds: (usize, usize),
+                                pixel: (usize, usize),
+                                upper_left: Complex<T>,
+                                lower_right: Complex<T>)
+   -> Complex<T>
+{
+    subpixel_to_point(bounds, (float(pixel.0 as f64), float(pixel.1 as f64)), upper_left, lower_right)
+}
+
+pub fn subpixel_to_point<T: Float>(bounds: (usize, usize),
+                                   pixel: (T, T),
+                                   upper_left: Complex<T>,
+                                   lower_right: Complex<T>)
+   -> Complex<T>
+{
+
+    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+
+    // Rows are measured from the middle of the view, so that in a view centered
+    // on the real axis rows y and height - y get exactly conjugate points.
+    let middle = (upper_left.im + lower_right.im) / float(2.0);
+
+    Complex::<T> { re: upper_left.re + pixel.0 * width / float(bounds.0 as f64),
+                   im: middle + (float::<T>(bounds.1 as f64 / 2.0) - pixel.1) * height / float(bounds.1 as f64) }
+
+}
+
+pub fn point_to_pixel(bounds: (usize, usize),
+                      point: Complex<f64>,
+                      upper_left: Complex<f64>,
+                      lower_right: Complex<f64>)
+   -> (f64, f64)
+{
+
+    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+
+    ((point.re - upper_left.re) * bounds.0 as f64 / width,
+     (upper_left.im - point.im) * bounds.1 as f64 / height)
+
+}
+
+// Keeps the upper left corner and the width of the region and moves the
+// lower edge so the region has the same aspect ratio as the image.
+pub fn fit_aspect(bounds: (usize, usize), upper_left: Complex<f64>, lower_right: Complex<f64>) -> Complex<f64> {
+    let width = lower_right.re - upper_left.re;
+    Complex { re: lower_right.re, im: upper_left.im - width * bounds.1 as f64 / bounds.0 as f64 }
+}
+
+// How much wider a pixel covers than it is tall, 1 when nothing is stretched.
+pub fn aspect_distortion(bounds: (usize, usize), upper_left: Complex<f64>, lower_right: Complex<f64>) -> f64 {
+    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+    (width / bounds.0 as f64) / (height / bounds.1 as f64)
+}
+
+// The view is 4 / zoom units wide, so zoom 1 frames the whole set, and its
+// height follows the aspect ratio of the image.
+pub fn view_from_center(bounds: (usize, usize), center: Complex<f64>, zoom: f64) -> (Complex<f64>, Complex<f64>) {
+
+    let width = 4.0 / zoom;
+    let height = width * bounds.1 as f64 / bounds.0 as f64;
+    let half = Complex { re: width / 2.0, im: -height / 2.0 };
+
+    (center - half, center + half)
+
+}
+
+pub fn render(pixels: &mut [u8],
+              bounds: (usize, usize),
+              upper_left: Complex<f64>,
+              lower_right: Complex<f64>,
+              config: Config)
+{
+    if let Err(error) = render_region(pixels, bounds, upper_left, lower_right, config) {
+        panic!("{}", error);
+    }
+}
+
+// Renders into a buffer the caller owns, such as a framebuffer or a texture,
+// on the calling thread. The buffer must hold exactly the pixels of `bounds`
+// in the layout of `config`, or it is left untouched and an error returned.
+pub fn render_region(buffer: &mut [u8],
+                     bounds: (usize, usize),
+                     upper_left: Complex<f64>,
+                     lower_right: Complex<f64>,
+                     config: Config)
+   -> Result<(), RenderError>
+{
+
+    let expected = bounds.0.checked_mul(bounds.1).and_then(|pixels| pixels.checked_mul(config.bytes_per_pixel()));
+    if expected != Some(buffer.len()) {
+        return Err(RenderError::InvalidBuffer(match expected {
+            Some(expected) => format!("{} bytes, expected {} for {}x{} pixels of {} bytes", buffer.len(), expected, bounds.0, bounds.1, config.bytes_per_pixel()),
+            None => format!("{}x{} pixels of {} bytes do not fit in memory", bounds.0, bounds.1, config.bytes_per_pixel())
+        }));
+    }
+
+    let rows = mirror_rows(bounds, upper_left, lower_right, config);
+    for (row, line) in buffer.chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1)).take(rows).enumerate() {
+        render_row(line, bounds, (0, row), upper_left, lower_right, config);
+    }
+    mirror(buffer, bounds, rows, config);
+
+    Ok(())
+
+}
+
+// Side of the squares a thread walks at a time along the Hilbert curve.
+const HILBERT_SIDE: usize = 32;
+
+// Splits a band of whole rows, each `row_length` bytes, into the pieces of the
+// tiles `width` bytes wide across it, left to right, each tile a list of its
+// rows. Tiles can then be rendered in place on separate threads.
+fn band_tiles(band: &mut [u8], row_length: usize, width: usize) -> Vec<Vec<&mut [u8]>> {
+
+    let mut tiles: Vec<Vec<&mut [u8]>> = Vec::new();
+
+    for line in band.chunks_mut(row_length) {
+        for (index, piece) in line.chunks_mut(width).enumerate() {
+            if index == tiles.len() {
+                tiles.push(Vec::new());
+            }
+            tiles[index].push(piece);
+        }
+    }
+
+    tiles
+
+}
+
+// Like `render_parallel`, but the threads take squares of pixels, each walked
+// along a Hilbert curve rather than row by row, so consecutive pixels stay
+// close in the plane. Only the order changes, each pixel gets the same color
+// as in `render`, and the pixels are written in place.
+pub fn render_hilbert(pixels: &mut [u8],
+                      bounds: (usize, usize),
+                      upper_left: Complex<f64>,
+                      lower_right: Complex<f64>,
+                      config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+    let row_length = (bounds.0 * bytes_per_pixel).max(1);
+    let rows = mirror_rows(bounds, upper_left, lower_right, config);
+
+    pixels[..rows * bounds.0 * bytes_per_pixel].par_chunks_mut(row_length * HILBERT_SIDE)
+                                               .enumerate()
+                                               .flat_map(|(band, lines)| {
+                                                   band_tiles(lines, row_length, HILBERT_SIDE * bytes_per_pixel)
+                                                       .into_par_iter()
+                                                       .enumerate()
+                                                       .map(move |(square, lines)| ((square * HILBERT_SIDE, band * HILBERT_SIDE), lines))
+                                               })
+                                               .for_each(|((left, top), mut lines)| {
+                                                   for (x, y) in (0..HILBERT_SIDE * HILBERT_SIDE).map(|d| hilbert::hilbert_point(HILBERT_SIDE, d)) {
+                                                       if let Some(pixel) = lines.get_mut(y).and_then(|line| line.get_mut(x * bytes_per_pixel..(x + 1) * bytes_per_pixel)) {
+                                                           render_row(pixel, bounds, (left + x, top + y), upper_left, lower_right, config);
+                                                       }
+                                                   }
+                                               });
+    mirror(pixels, bounds, rows, config);
+
+}
+
+// Side of the square tiles `render_tiles` hands to the threads.
+const TILE_SIDE: usize = 32;
+
+// Like `render_parallel`, but the threads take square tiles rather than whole
+// rows. When the interior, where every pixel runs to the limit, crosses only
+// a few rows, those rows are split into tiles shared among threads instead
+// of each landing on one. Each band of rows is split into its tiles in place,
+// and pixels are mapped through the corners of the whole image, so each one
+// gets the same color as in `render`.
+pub fn render_tiles(pixels: &mut [u8],
+                    bounds: (usize, usize),
+                    upper_left: Complex<f64>,
+                    lower_right: Complex<f64>,
+                    config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+    let row_length = (bounds.0 * bytes_per_pixel).max(1);
+    let rows = mirror_rows(bounds, upper_left, lower_right, config);
+
+    pixels[..rows * bounds.0 * bytes_per_pixel].par_chunks_mut(row_length * TILE_SIDE)
+                                               .enumerate()
+                                               .flat_map(|(band, lines)| {
+                                                   band_tiles(lines, row_length, TILE_SIDE * bytes_per_pixel)
+                                                       .into_par_iter()
+                                                       .enumerate()
+                                                       .map(move |(tile, lines)| ((tile * TILE_SIDE, band * TILE_SIDE), lines))
+                                               })
+                                               .for_each(|((left, top), lines)| {
+                                                   for (row, line) in lines.into_iter().enumerate() {
+                                                       render_row(line, bounds, (left, top + row), upper_left, lower_right, config);
+                                                   }
+                                               });
+    mirror(pixels, bounds, rows, config);
+
+}
+
+// Block sizes of the passes of `render_progressive`, from every 8th pixel
+// down to every pixel.
+const PROGRESSIVE_STEPS: [usize; 4] = [8, 4, 2, 1];
+
+// Renders in passes of increasing resolution, calling `on_pass` with the block
+// size and the image after each one, so a rough image is ready almost at once.
+// Each pass only computes the pixels the earlier ones skipped and fills the
+// blocks they stand for, and the last pass leaves exactly the image of
+// `render_parallel`.
+pub fn render_progressive<F: FnMut(usize, &[u8])>(pixels: &mut [u8],
+                                                  bounds: (usize, usize),
+                                                  upper_left: Complex<f64>,
+                                                  lower_right: Complex<f64>,
+                                                  config: Config,
+                                                  mut on_pass: F)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+    let row_length = (bounds.0 * bytes_per_pixel).max(1);
+    let rows = mirror_rows(bounds, upper_left, lower_right, config);
+
+    for (pass, &step) in PROGRESSIVE_STEPS.iter().enumerate() {
+
+        pixels[..rows * row_length].par_chunks_mut(row_length * step)
+                                   .enumerate()
+                                   .for_each(|(band, block_rows)| {
+
+            let top = band * step;
+            let mut pixel = vec![0; bytes_per_pixel];
+
+            for column in (0..bounds.0).step_by(step) {
+
+                // Computed by the previous pass, whose block already covers this one.
+                if pass > 0 && top % (2 * step) == 0 && column % (2 * step) == 0 {
+                    continue;
+                }
+
+                render_row(&mut pixel, bounds, (column, top), upper_left, lower_right, config);
+                let block = column * bytes_per_pixel..(column + step).min(bounds.0) * bytes_per_pixel;
+                for line in block_rows.chunks_mut(row_length) {
+                    for target in line[block.clone()].chunks_mut(bytes_per_pixel) {
+                        target.copy_from_slice(&pixel);
+                    }
+                }
+
+            }
+
+        });
+
+        mirror(pixels, bounds, rows, config);
+        on_pass(step, pixels);
+
+    }
+
+}
+
+// Rows are split among threads, but every pixel is computed on its own and its
+// supersamples are summed in the same order on any thread, so the buffer is
+// bit for bit the same whatever the number of threads. Parallel renders must
+// keep it that way : no per-pixel math may depend on where rows are split, and
+// anything reduced across threads must be integers or reduced in a fixed order.
+pub fn render_parallel(pixels: &mut [u8],
+                       bounds: (usize, usize),
+                       upper_left: Complex<f64>,
+                       lower_right: Complex<f64>,
+                       config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let rows = mirror_rows(bounds, upper_left, lower_right, config);
+    pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+          .take(rows)
+          .enumerate()
+          .for_each(|(row, line)| render_row(line, bounds, (0, row), upper_left, lower_right, config));
+    mirror(pixels, bounds, rows, config);
+
+}
+
+// Row y shows the conjugates of the points of row height - y, which have the
+// same color when the fractal is symmetric about the real axis. Only the rows
+// down to the axis then need rendering. Supersampled rows are not mirror
+// images of each other, and neither are Newton's roots, orbit traps or stripes.
+// Dithered rows are not either, since the threshold follows the absolute row.
+fn mirror_rows(bounds: (usize, usize), upper_left: Complex<f64>, lower_right: Complex<f64>, config: Config) -> usize {
+
+    let symmetric = match config.fractal {
+        Fractal::Mandelbrot => true,
+        Fractal::Julia(c) => c.im == 0.0,
+        Fractal::BurningShip | Fractal::Newton => false
+    };
+
+    if config.mirror && symmetric && config.supersample == 1 && !config.dither
+       && !matches!(config.coloring, Coloring::OrbitTrap(_) | Coloring::Stripe(_) | Coloring::Lighting(_))
+       && upper_left.im == -lower_right.im {
+        bounds.1 / 2 + 1
+    } else {
+        bounds.1
+    }
+
+}
+
+fn mirror(pixels: &mut [u8], bounds: (usize, usize), rows: usize, config: Config) {
+
+    let stride = bounds.0 * config.bytes_per_pixel();
+    for row in rows..bounds.1 {
+        let source = bounds.1 - row;
+        pixels.copy_within(source * stride..(source + 1) * stride, row * stride);
+    }
+
+}
+
+pub fn render_parallel_with_progress(pixels: &mut [u8],
+                                     bounds: (usize, usize),
+                                     upper_left: Complex<f64>,
+                                     lower_right: Complex<f64>,
+                                     config: Config,
+                                     progress: &Progress)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+          .enumerate()
+          .for_each(|(row, line)| {
+              render_row(line, bounds, (0, row), upper_left, lower_right, config);
+              progress.tick();
+          });
+
+}
+
+// Like `render_parallel`, but rows not yet started are skipped once `cancel`
+// is set, leaving their pixels as they were. Rows already under way are
+// finished, so the buffer only ever holds whole rendered rows. Returns how
+// many rows were rendered, before any mirroring.
+pub fn render_parallel_cancellable(pixels: &mut [u8],
+                                   bounds: (usize, usize),
+                                   upper_left: Complex<f64>,
+                                   lower_right: Complex<f64>,
+                                   config: Config,
+                                   cancel: &AtomicBool)
+   -> usize
+{
+    render_cancellable_with(pixels, bounds, upper_left, lower_right, config, cancel, |_| {})
+}
+
+// `render_parallel_cancellable`, calling `on_row` with the number of rows
+// rendered so far after each one.
+fn render_cancellable_with<F: Fn(usize) + Sync>(pixels: &mut [u8],
+                                                bounds: (usize, usize),
+                                                upper_left: Complex<f64>,
+                                                lower_right: Complex<f64>,
+                                                config: Config,
+                                                cancel: &AtomicBool,
+                                                on_row: F)
+   -> usize
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let rows = mirror_rows(bounds, upper_left, lower_right, config);
+    let rendered = AtomicUsize::new(0);
+    pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+          .take(rows)
+          .enumerate()
+          .for_each(|(row, line)| {
+              if !cancel.load(Ordering::Relaxed) {
+                  render_row(line, bounds, (0, row), upper_left, lower_right, config);
+                  on_row(rendered.fetch_add(1, Ordering::Relaxed) + 1);
+              }
+          });
+    mirror(pixels, bounds, rows, config);
+
+    rendered.into_inner()
+
+}
+
+// Like `render_parallel`, while a sampling thread notes which row each render
+// thread is on every `interval`.
+pub fn render_parallel_with_profile(pixels: &mut [u8],
+                                    bounds: (usize, usize),
+                                    upper_left: Complex<f64>,
+                                    lower_right: Complex<f64>,
+                                    config: Config,
+                                    interval: Duration)
+   -> Profile
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let working: Vec<AtomicUsize> = (0..rayon::current_num_threads()).map(|_| AtomicUsize::new(profile::IDLE)).collect();
+    let done = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+
+        let sampler = scope.spawn(|| profile::sample_rows(&working, &done, bounds.1, interval));
+
+        pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+              .enumerate()
+              .for_each(|(row, line)| {
+                  let slot = &working[rayon::current_thread_index().unwrap_or(0) % working.len()];
+                  slot.store(row, Ordering::Relaxed);
+                  render_row(line, bounds, (0, row), upper_left, lower_right, config);
+                  slot.store(profile::IDLE, Ordering::Relaxed);
+              });
+
+        done.store(true, Ordering::Relaxed);
+        Profile { samples: sampler.join().unwrap(), interval }
+
+    })
+
+}
+
+pub fn render_parallel_with_stats(pixels: &mut [u8],
+                                  bounds: (usize, usize),
+                                  upper_left: Complex<f64>,
+                                  lower_right: Complex<f64>,
+                                  config: Config)
+   -> RenderStats
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let timings = pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+                        .enumerate()
+                        .map(|(row, line)| {
+                            let start = Instant::now();
+                            render_row(line, bounds, (0, row), upper_left, lower_right, config);
+                            start.elapsed()
+                        })
+                        .collect();
+
+    RenderStats { timings }
+
+}
+
+pub fn render_tile(pixels: &mut [u8],
+                   bounds: (usize, usize),
+                   origin: (usize, usize),
+                   size: (usize, usize),
+                   upper_left: Complex<f64>,
+                   lower_right: Complex<f64>,
+                   config: Config)
+{
+
+    assert!(pixels.len() == size.0 * size.1 * config.bytes_per_pixel());
+    assert!(origin.0 + size.0 <= bounds.0 && origin.1 + size.1 <= bounds.1);
+
+    // Pixels are mapped through the full image bounds rather than per-tile
+    // corners, so a tile is bit-identical to the same region of a full render.
+    pixels.par_chunks_mut((size.0 * config.bytes_per_pixel()).max(1))
+          .enumerate()
+          .for_each(|(row, line)| render_row(line, bounds, (origin.0, origin.1 + row), upper_left, lower_right, config));
+
+}
+
+// Renders only the window at `origin` of a full size image, leaving the rest
+// of `pixels` untouched.
+pub fn render_crop(pixels: &mut [u8],
+                   bounds: (usize, usize),
+                   origin: (usize, usize),
+                   size: (usize, usize),
+                   upper_left: Complex<f64>,
+                   lower_right: Complex<f64>,
+                   config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+    assert!(origin.0 + size.0 <= bounds.0 && origin.1 + size.1 <= bounds.1);
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+    let window = origin.0 * bytes_per_pixel..(origin.0 + size.0) * bytes_per_pixel;
+
+    pixels.par_chunks_mut((bounds.0 * bytes_per_pixel).max(1))
+          .enumerate()
+          .skip(origin.1)
+          .take(size.1)
+          .for_each(|(row, line)| render_row(&mut line[window.clone()], bounds, (origin.0, row), upper_left, lower_right, config));
+
+}
+
+// Renders only the pixels set in `mask`, one flag per pixel, leaving the
+// others untouched. Each run of set pixels along a row is rendered at once.
+pub fn render_masked(pixels: &mut [u8],
+                     bounds: (usize, usize),
+                     mask: &[bool],
+                     upper_left: Complex<f64>,
+                     lower_right: Complex<f64>,
+                     config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+    assert!(mask.len() == bounds.0 * bounds.1);
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+
+    pixels.par_chunks_mut((bounds.0 * bytes_per_pixel).max(1))
+          .zip(mask.par_chunks(bounds.0.max(1)))
+          .enumerate()
+          .for_each(|(row, (line, flags))| {
+              let mut column = 0;
+              while column < bounds.0 {
+                  let run = flags[column..].iter().take_while(|flag| **flag).count();
+                  if run > 0 {
+                      let window = column * bytes_per_pixel..(column + run) * bytes_per_pixel;
+                      render_row(&mut line[window], bounds, (column, row), upper_left, lower_right, config);
+                  }
+                  column += run + flags[column + run..].iter().take_while(|flag| !**flag).count();
+              }
+          });
+
+}
+
+pub fn render_counts(counts: &mut [Option<usize>],
+                     bounds: (usize, usize),
+                     upper_left: Complex<f64>,
+                     lower_right: Complex<f64>,
+                     config: Config)
+{
+
+    assert!(counts.len() == bounds.0 * bounds.1);
+
+    counts.par_chunks_mut(bounds.0.max(1))
+          .enumerate()
+          .for_each(|(row, line)| {
+              for (column, count) in line.iter_mut().enumerate() {
+                  *count = match config.precision {
+                      Precision::Single => {
+                          let point = pixel_to_point::<f32>(bounds, (column, row), cast(upper_left), cast(lower_right));
+                          config.fractal.escape_time(point, &config)
+                      }
+                      Precision::Double => {
+                          let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+                          config.fractal.escape_time(point, &config)
+                      }
+                      Precision::DoubleDouble => {
+                          let point = pixel_to_point::<DoubleDouble>(bounds, (column, row), cast(upper_left), cast(lower_right));
+                          config.fractal.escape_time(point, &config)
+                      }
+                  };
+              }
+          });
+
+}
+
+// One grayscale byte per pixel holding how far the f32 and f64 escape counts
+// disagree, with points in the set counted as `limit`. Black means they match.
+pub fn render_precision_diff(pixels: &mut [u8],
+                             bounds: (usize, usize),
+                             upper_left: Complex<f64>,
+                             lower_right: Complex<f64>,
+                             config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1);
+
+    let mut single = vec![None; bounds.0 * bounds.1];
+    let mut double = vec![None; bounds.0 * bounds.1];
+    render_counts(&mut single, bounds, upper_left, lower_right, Config { precision: Precision::Single, ..config });
+    render_counts(&mut double, bounds, upper_left, lower_right, Config { precision: Precision::Double, ..config });
+
+    for ((pixel, single), double) in pixels.iter_mut().zip(single).zip(double) {
+        let difference = single.unwrap_or(config.limit).abs_diff(double.unwrap_or(config.limit));
+        *pixel = difference.min(u8::MAX as usize) as u8;
+    }
+
+}
+
+// Renders one sample per pixel, then supersamples with the `config.supersample`
+// grid only the pixels whose escape count differs from a neighbour's by more
+// than `threshold`, with points in the set counted as `limit`. Returns how many
+// pixels were refined.
+pub fn render_adaptive(pixels: &mut [u8],
+                       bounds: (usize, usize),
+                       upper_left: Complex<f64>,
+                       lower_right: Complex<f64>,
+                       config: Config,
+                       threshold: usize)
+   -> usize
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    render_parallel(pixels, bounds, upper_left, lower_right, Config { supersample: 1, ..config });
+
+    let mut counts = vec![None; bounds.0 * bounds.1];
+    render_counts(&mut counts, bounds, upper_left, lower_right, config);
+    let count = |column: usize, row: usize| counts[row * bounds.0 + column].unwrap_or(config.limit);
+
+    let pixel_size = (lower_right.re - upper_left.re) / bounds.0 as f64;
+
+    pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+          .enumerate()
+          .map(|(row, line)| {
+
+              let mut refined = 0;
+
+              for (column, pixel) in line.chunks_mut(config.bytes_per_pixel()).enumerate() {
+
+                  let center = count(column, row);
+                  let edge = (row.saturating_sub(1)..(row + 2).min(bounds.1))
+                      .flat_map(|row| (column.saturating_sub(1)..(column + 2).min(bounds.0)).map(move |column| (column, row)))
+                      .any(|(column, row)| count(column, row).abs_diff(center) > threshold);
+                  if !edge {
+                      continue;
+                  }
+
+                  let color = match config.precision {
+                      Precision::Single => supersampled_color::<f32>(bounds, (column, row), cast(upper_left), cast(lower_right), pixel_size, config),
+                      Precision::Double => supersampled_color(bounds, (column, row), upper_left, lower_right, pixel_size, config),
+                      Precision::DoubleDouble => supersampled_color::<DoubleDouble>(bounds, (column, row), cast(upper_left), cast(lower_right), pixel_size, config)
+                  };
+                  write_pixel(pixel, dither(color, (column, row), config), config);
+                  refined += 1;
+
+              }
+
+              refined
+
+          })
+          .sum()
+
+}
+
+pub fn histogram_equalize(counts: &[Option<usize>], limit: usize) -> Vec<Option<f64>> {
+
+    let mut histogram = vec![0; limit + 1];
+    for count in counts.iter().flatten() {
+        histogram[*count] += 1;
+    }
+
+    let mut below = Vec::with_capacity(histogram.len());
+    let mut total = 0;
+    for bucket in &histogram {
+        below.push(total);
+        total += bucket;
+    }
+
+    counts.iter()
+          .map(|count| count.map(|count| 1.0 - below[count] as f64 / total as f64))
+          .collect()
+
+}
+
+pub fn render_histogram(pixels: &mut [u8],
+                        bounds: (usize, usize),
+                        upper_left: Complex<f64>,
+                        lower_right: Complex<f64>,
+                        config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let mut counts = vec![None; bounds.0 * bounds.1];
+    render_counts(&mut counts, bounds, upper_left, lower_right, config);
+
+    let shades = histogram_equalize(&counts, config.limit);
+    for (pixel, shade) in pixels.chunks_mut(config.bytes_per_pixel()).zip(shades) {
+        write_pixel(pixel, shade_color(shade, config), config);
+    }
+
+}
+
+// A heatmap of the steps taken on each pixel, from none in black to the
+// limit in white, through the inferno colormap.
+pub fn render_iterations_map(pixels: &mut [u8],
+                             bounds: (usize, usize),
+                             upper_left: Complex<f64>,
+                             lower_right: Complex<f64>,
+                             config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+    pixels.par_chunks_mut((bounds.0 * 3).max(1))
+          .enumerate()
+          .for_each(|(row, line)| {
+              for (column, pixel) in line.chunks_mut(3).enumerate() {
+                  let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+                  let executed = config.fractal.executed_iterations(point, &config);
+                  pixel.copy_from_slice(&Palette::Inferno.color(executed as f64 / config.limit as f64));
+              }
+          });
+
+}
+
+// Colors escape counts from `render_counts` with the palette shifted by
+// `phase`, a fraction of its length, wrapping around past its end. Frames of a
+// color cycle then share the counts and only differ in their phase.
+pub fn render_cycle(pixels: &mut [u8], counts: &[Option<usize>], config: Config, phase: f64) {
+
+    assert!(pixels.len() == counts.len() * config.bytes_per_pixel());
+
+    pixels.par_chunks_mut(config.bytes_per_pixel())
+          .zip(counts.par_iter())
+          .for_each(|(pixel, count)| {
+              let shade = count.map(|time| (linear_shade(time, config) + phase).rem_euclid(1.0));
+              write_pixel(pixel, shade_color(shade, config), config);
+          });
+
+}
+
+// Renders the view of `view_from_center` with corners computed in double-double
+// precision, for zooms so deep that the corners round to the same f64 values.
+pub fn render_deep(pixels: &mut [u8],
+                   bounds: (usize, usize),
+                   center: Complex<f64>,
+                   zoom: f64,
+                   config: Config)
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    let (upper_left, lower_right) = deep_view(bounds, cast(center), zoom);
+
+    pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+          .enumerate()
+          .for_each(|(row, line)| render_row_with(line, bounds, (0, row), upper_left, lower_right, config));
+
+}
+
+fn deep_view(bounds: (usize, usize), center: Complex<DoubleDouble>, zoom: f64) -> (Complex<DoubleDouble>, Complex<DoubleDouble>) {
+
+    let width = float::<DoubleDouble>(4.0) / float(zoom);
+    let height = width * float(bounds.1 as f64) / float(bounds.0 as f64);
+    let half = Complex { re: width / float(2.0), im: -height / float(2.0) };
+
+    (center - half, center + half)
+
+}
+
+fn render_row(line: &mut [u8],
+              bounds: (usize, usize),
+              start: (usize, usize),
+              upper_left: Complex<f64>,
+              lower_right: Complex<f64>,
+              config: Config)
+{
+    if config.precision == Precision::Single {
+        render_row_with::<f32>(line, bounds, start, cast(upper_left), cast(lower_right), config);
+    } else if config.precision == Precision::DoubleDouble {
+        render_row_with::<DoubleDouble>(line, bounds, start, cast(upper_left), cast(lower_right), config);
+    } else if config.fractal == Fractal::Mandelbrot && config.power == 2 && config.bailout == 2.0
+              && config.coloring == Coloring::EscapeTime && config.supersample <= 1 && !config.dither {
+        render_row_x4(line, bounds, start, upper_left, lower_right, config);
+    } else {
+        render_row_with::<f64>(line, bounds, start, upper_left, lower_right, config);
+    }
+}
+
+fn render_row_x4(line: &mut [u8],
+                 bounds: (usize, usize),
+                 start: (usize, usize),
+                 upper_left: Complex<f64>,
+                 lower_right: Complex<f64>,
+                 config: Config)
+{
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+
+    for (index, pixels) in line.chunks_mut(4 * bytes_per_pixel).enumerate() {
+
+        let column = start.0 + 4 * index;
+        let points = [0, 1, 2, 3].map(|lane| pixel_to_point(bounds, (column + lane, start.1), upper_left, lower_right));
+        let times = simd::escape_time_x4(points, config.limit);
+
+        for (pixel, time) in pixels.chunks_mut(bytes_per_pixel).zip(times) {
+            write_pixel(pixel, shade_color(time.map(|time| linear_shade(time, config)), config), config);
+        }
+
+    }
+
+}
+
+fn render_row_with<T: Float>(line: &mut [u8],
+                             bounds: (usize, usize),
+                             start: (usize, usize),
+                             upper_left: Complex<T>,
+                             lower_right: Complex<T>,
+                             config: Config)
+{
+
+    let samples = config.supersample.max(1);
+    let row = start.1;
+    let pixel_size = ((lower_right.re - upper_left.re) / float(bounds.0 as f64)).to_f64().unwrap();
+
+    for (index, pixel) in line.chunks_mut(config.bytes_per_pixel()).enumerate() {
+
+        let column = start.0 + index;
+
+        let color = if samples == 1 {
+            color(pixel_to_point(bounds, (column, row), upper_left, lower_right), pixel_size, config)
+        } else {
+            supersampled_color(bounds, (column, row), upper_left, lower_right, pixel_size, config)
+        };
+
+        write_pixel(pixel, dither(color, (column, row), config), config);
+
+    }
+
+}
+
+fn supersampled_color<T: Float>(bounds: (usize, usize),
+                                pixel: (usize, usize),
+                                upper_left: Complex<T>,
+                                lower_right: Complex<T>,
+                                pixel_size: f64,
+                                config: Config)
+   -> [f64; 4]
+{
+    supersample(bounds, pixel, upper_left, lower_right, pixel_size, config).0
+}
+
+// Samples taken before the running averages are trusted to have converged.
+const MIN_SUPERSAMPLES: usize = 4;
+
+// The averaged color of a pixel along with the number of samples it took.
+// With a threshold, the running mean and variance of each channel are kept
+// with Welford's method and sampling stops early once they agree.
+fn supersample<T: Float>(bounds: (usize, usize),
+                         pixel: (usize, usize),
+                         upper_left: Complex<T>,
+                         lower_right: Complex<T>,
+                         pixel_size: f64,
+                         config: Config)
+   -> ([f64; 4], usize)
+{
+
+    let samples = config.supersample.max(1);
+    let (column, row) = pixel;
+
+    let mut sum = [0.0; 4];
+    let mut mean = [0.0; 4];
+    let mut squares = [0.0; 4];
+    let mut taken = 0;
+
+    for offset in config.sampling.offsets(pixel, samples) {
+
+        let pixel = (float(column as f64 + offset.0), float(row as f64 + offset.1));
+        let point = subpixel_to_point(bounds, pixel, upper_left, lower_right);
+
+        taken += 1;
+        let color = color(point, pixel_size, config);
+        for (total, channel) in sum.iter_mut().zip(color.iter()) {
+            *total += *channel;
+        }
+        for ((mean, squares), channel) in mean.iter_mut().zip(squares.iter_mut()).zip(color.iter()) {
+            let delta = *channel - *mean;
+            *mean += delta / taken as f64;
+            *squares += delta * (*channel - *mean);
+        }
+
+        let threshold = config.supersample_threshold;
+        if threshold > 0.0 && taken >= MIN_SUPERSAMPLES
+           && squares.iter().all(|squares| (squares / ((taken - 1) * taken) as f64).sqrt() <= threshold) {
+            break;
+        }
+
+    }
+
+    (sum.map(|total| quantize(total / taken as f64, config)), taken)
+
+}
+
+fn color<T: Float>(point: Complex<T>, pixel_size: f64, config: Config) -> [f64; 4] {
+    if config.fractal == Fractal::Newton {
+        return newton_color(newton(point, config.limit), config);
+    }
+    shade_color(shade(point, pixel_size, config), config)
+}
+
+const NEWTON_COLORS: [[u8; 3]; 3] = [[230, 60, 50], [70, 190, 90], [60, 110, 230]];
+
+// Each root has its own hue, or gray level in grayscale, darkened by how long
+// the point took to converge.
+fn newton_color(convergence: Option<(usize, usize)>, config: Config) -> [f64; 4] {
+
+    let max = ((1u32 << config.depth) - 1) as f64;
+
+    match convergence {
+        None => shade_color(None, config),
+        Some((root, time)) => {
+            let shade = linear_shade(time, config);
+            let [red, green, blue] = if config.palette.is_grayscale() {
+                [(shade * (root + 1) as f64 / 3.0 * max).round(); 3]
+            } else {
+                NEWTON_COLORS[root].map(|channel| (channel as f64 * shade * max / 255.0).round())
+            };
+            [red, green, blue, max]
+        }
+    }
+
+}
+
+// Channel values are on the 0..=2^depth - 1 scale. Grayscale keeps the full
+// precision of the shade, the other palettes are widened from 8 bits. The
+// last channel is the alpha, only written for transparent interiors.
+fn shade_color(shade: Option<f64>, config: Config) -> [f64; 4] {
+
+    let max = ((1u32 << config.depth) - 1) as f64;
+
+    let correct = |value: f64| value.powf(config.gamma.recip());
+
+    let ([red, green, blue], alpha) = match shade {
+        None => (config.interior.map(|channel| channel as f64 * max / 255.0), 0.0),
+        Some(t) if config.palette.is_grayscale() => ([quantize(correct(t.clamp(0.0, 1.0)) * max, config); 3], max),
+        Some(t) => {
+            let levels = if config.dither { config.palette.levels(t) } else { config.palette.color(t).map(f64::from) };
+            (levels.map(|level| quantize(correct(level / 255.0) * max, config)), max)
+        }
+    };
+
+    [red, green, blue, if config.transparent { alpha } else { max }]
+
+}
+
+// Channels are rounded once colored, unless they are left for `dither`.
+fn quantize(value: f64, config: Config) -> f64 {
+    if config.dither { value } else { value.round() }
+}
+
+// Samples wider than a byte are stored big-endian, as PNG and PGM expect.
+fn write_pixel(pixel: &mut [u8], color: [f64; 4], config: Config) {
+
+    let width = config.depth as usize / 8;
+
+    for (channel, value) in pixel.chunks_mut(width).zip(&color[..config.channels()]) {
+        let bytes = (value.round() as u16).to_be_bytes();
+        channel.copy_from_slice(&bytes[2 - width..]);
+    }
+
+    if config.invert {
+        invert(&mut pixel[..width * config.channels().min(3)]);
+    }
+
+}
+
+// Flipping every bit maps each channel to max - value, whatever the depth.
+pub fn invert(pixels: &mut [u8]) {
+    for byte in pixels.iter_mut() {
+        *byte = !*byte;
+    }
+}
+
+fn shade<T: Float>(point: Complex<T>, pixel_size: f64, config: Config) -> Option<f64> {
+
+    match config.coloring {
+        Coloring::EscapeTime => config.fractal.escape_time(point, &config)
+                                      .map(|time| linear_shade(time, config)),
+        Coloring::Smooth => config.fractal.escape_time_smooth(point, &config)
+                                  .map(|time| smooth_shade(time, config)),
+        Coloring::Distance => config.fractal.escape_time_distance(point, &config)
+                                    .map(|distance| (distance / pixel_size).clamp(0.0, 1.0).powf(0.25)),
+        Coloring::OrbitTrap(trap) => config.fractal.escape_time_orbit_trap(point, &config, trap)
+                                           .map(|distance| 1.0 - distance.clamp(0.0, 1.0)),
+        Coloring::Stripe(density) => config.fractal.escape_time_stripe(point, &config, density)
+                                           .map(|(time, stripe)| 0.5 * smooth_shade(time, config) + 0.25 * (stripe + 1.0)),
+        Coloring::Lighting(light) => config.fractal.escape_time_normal(point, &config)
+                                           .map(|normal| light.brightness(normal))
+    }
+
+}
+
+fn linear_shade(time: usize, config: Config) -> f64 {
+    if config.log_scale {
+        return log_shade(time as f64, config);
+    }
+    let max = (1usize << config.depth) - 1;
+    (max - time * max / config.limit) as f64 / max as f64
+}
+
+fn smooth_shade(time: f64, config: Config) -> f64 {
+    if config.log_scale {
+        return log_shade(time, config);
+    }
+    1.0 - time.clamp(0.0, config.limit as f64) / config.limit as f64
+}
+
+fn log_shade(time: f64, config: Config) -> f64 {
+    1.0 - time.clamp(0.0, config.limit as f64).ln_1p() / (config.limit as f64).ln_1p()
+}
+
+pub fn julia_frame_constant(start: Complex<f64>, end: Complex<f64>, frame: usize, frames: usize) -> Complex<f64> {
+
+    if frames < 2 {
+        return start;
+    }
+
+    let t = frame as f64 / (frames - 1) as f64;
+    start * (1.0 - t) + end * t
+
+}
+
+// Shrinks the view around the center by `zoom` per frame. Scaling both
+// corners about the center keeps it on the same pixel in every frame.
+pub fn zoom_frame_bounds(upper_left: Complex<f64>,
+                         lower_right: Complex<f64>,
+                         center: Complex<f64>,
+                         zoom: f64,
+                         frame: usize)
+   -> (Complex<f64>, Complex<f64>)
+{
+
+    let scale = zoom.powi(frame as i32).recip();
+    (center + (upper_left - center) * scale, center + (lower_right - center) * scale)
+
+}
+
+// Each tenfold magnification adds another `base` iterations.
+pub fn zoom_frame_limit(base: usize, zoom: f64, frame: usize) -> usize {
+    let depth = (frame as f64 * zoom.log10()).max(0.0);
+    (base as f64 * (1.0 + depth)).round() as usize
+}
+
+// An iteration limit for a view `width` units wide, adding `per_decade`
+// iterations to `base` for each tenfold narrowing below a width of 1.
+pub fn auto_iterations(width: f64, base: usize, per_decade: f64) -> usize {
+    let depth = (-width.log10()).max(0.0);
+    (base as f64 + per_decade * depth).round() as usize
+}
+
+// The bit depth that keeps every escape count of a limit distinct : 8 bits
+// have levels enough for 255 iterations, and higher limits get 16 bits.
+pub fn auto_depth(limit: usize) -> u8 {
+    if limit > 255 { 16 } else { 8 }
+}
+
+pub fn frame_path(directory: &str, prefix: &str, frame: usize) -> PathBuf {
+    Path::new(directory).join(format!("{}_{:04}.png", prefix, frame))
+}
+
+#[test]
+fn test_pixel_to_point() {
+    assert_eq!(pixel_to_point((100, 100),
+                              (50, 50),
+                              Complex::<f64> { re: -1.0, im: 1.0},
+                              Complex::<f64> { re: 1.0, im: -1.0}),
+               Complex::<f64> { re: 0.0, im: 0.0});
+
+    // Rows are measured from the middle of the view, which puts rows 61 and
+    // 80 of this view an ULP away from measuring them from the top edge.
+    let (upper_left, lower_right) = (Complex { re: -0.8, im: 0.2 }, Complex { re: -0.7, im: 0.125 });
+    for (pixel, point) in [((0, 0), Complex { re: -0.8, im: 0.2 }),
+                           ((17, 41), Complex { re: -0.7943333333333333, im: 0.18041401273885352 }),
+                           ((150, 61), Complex { re: -0.75, im: 0.17085987261146499 }),
+                           ((150, 80), Complex { re: -0.75, im: 0.16178343949044585 }),
+                           ((299, 156), Complex { re: -0.7003333333333333, im: 0.12547770700636943 })] {
+        assert_eq!(pixel_to_point((300, 157), pixel, upper_left, lower_right), point);
+    }
+}
+
+#[test]
+fn test_fit_aspect() {
+    let upper_left = Complex { re: -2.0, im: 1.0 };
+    let lower_right = Complex { re: 1.0, im: -2.0 };
+
+    assert_eq!(fit_aspect((1920, 1080), upper_left, lower_right), Complex { re: 1.0, im: 1.0 - 3.0 * 1080.0 / 1920.0 });
+    assert_eq!(fit_aspect((100, 100), upper_left, lower_right), lower_right);
+
+    assert_eq!(aspect_distortion((100, 100), upper_left, lower_right), 1.0);
+    assert!((aspect_distortion((1920, 1080), upper_left, lower_right) - 1080.0 / 1920.0).abs() < 1e-12);
+    assert!((aspect_distortion((1920, 1080), upper_left, fit_aspect((1920, 1080), upper_left, lower_right)) - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_view_from_center() {
+    assert_eq!(view_from_center((200, 100), Complex { re: -0.5, im: 0.0 }, 1.0),
+               (Complex { re: -2.5, im: 1.0 }, Complex { re: 1.5, im: -1.0 }));
+    assert_eq!(view_from_center((100, 100), Complex { re: 1.0, im: 1.0 }, 4.0),
+               (Complex { re: 0.5, im: 1.5 }, Complex { re: 1.5, im: 0.5 }));
+}
+
+#[test]
+fn test_point_to_pixel() {
+    let bounds = (300, 200);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -0.8 };
+
+    assert_eq!(point_to_pixel((100, 100),
+                              Complex { re: 0.0, im: 0.0 },
+                              Complex { re: -1.0, im: 1.0 },
+                              Complex { re: 1.0, im: -1.0 }),
+               (50.0, 50.0));
+
+    for &pixel in &[(0, 0), (299, 199), (150, 100), (17, 183)] {
+        let (x, y) = point_to_pixel(bounds, pixel_to_point(bounds, pixel, upper_left, lower_right), upper_left, lower_right);
+        assert!((x - pixel.0 as f64).abs() < 1e-9 && (y - pixel.1 as f64).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_parse_pair() {
+    assert_eq!(parse_pair::<i32>("-10x10",    'x'), Some((-10, 10)));
+    assert_eq!(parse_pair::<i32>("800,600f",  ','), None);
+    assert_eq!(parse_pair::<i32>("1920/1080", '/'), Some((1920, 1080)));
+    assert_eq!(parse_pair::<i32>("10.6*-34",  '*'), None);
+    assert_eq!(parse_pair::<String>("abc*jhu",  '*'), Some(("abc".to_string(), "jhu".to_string())))
+}
+
+#[test]
+#[allow(clippy::approx_constant)]
+fn test_parse_complex() {
+    assert_eq!(parse_complex("3.14,1").unwrap(), Complex { re: 3.14, im: 1.0 });
+    assert_eq!(parse_complex("-12/4").unwrap(), Complex { re: -3.0, im: 0.0 });
+}
+
+#[test]
+fn test_parse_complex_notation() {
+    assert_eq!(parse_complex("-7.5e-3,1e2").unwrap(), Complex { re: -7.5e-3, im: 100.0 });
+    assert_eq!(parse_complex("1E-5,-2.5E+3").unwrap(), Complex { re: 1e-5, im: -2500.0 });
+    assert_eq!(parse_complex("1/3,-2/3").unwrap(), Complex { re: 1.0 / 3.0, im: -2.0 / 3.0 });
+    assert_eq!(parse_complex("-1/4+3/4i").unwrap(), Complex { re: -0.25, im: 0.75 });
+    assert_eq!(parse_complex("1e-2/4,0.5").unwrap(), Complex { re: 0.0025, im: 0.5 });
+    assert_eq!(parse_complex("1/2,1e-3").unwrap(), Complex { re: 0.5, im: 1e-3 });
+    assert_eq!(parse_complex("3/4").unwrap(), Complex { re: 0.75, im: 0.0 });
+    assert!(parse_complex("1/,2").is_err());
+    assert!(parse_complex("/2,1").is_err());
+    assert!(parse_complex("1,1/").is_err());
+    assert!(parse_complex("1/2/3,1").is_err());
+    assert!(parse_complex("1/0,1").is_err());
+    assert!(parse_complex("1/+i").is_err());
+}
+
+#[test]
+fn test_parse_bounds() {
+    assert_eq!(parse_bounds("1920x1080").unwrap(), (1920, 1080));
+    assert!(matches!(parse_bounds("1920by1080"), Err(RenderError::ParseBounds(value)) if value == "1920by1080"));
+    assert!(matches!(parse_complex("1.5+"), Err(RenderError::ParseComplex(value)) if value == "1.5+"));
+}
+
+#[test]
+fn test_parse_complex_algebraic() {
+    assert_eq!(parse_complex("-0.7+0.4i").unwrap(), Complex { re: -0.7, im: 0.4 });
+    assert_eq!(parse_complex("1.5-0.3i").unwrap(), Complex { re: 1.5, im: -0.3 });
+    assert_eq!(parse_complex("1e-3+2e-2i").unwrap(), Complex { re: 1e-3, im: 2e-2 });
+    assert_eq!(parse_complex("1-i").unwrap(), Complex { re: 1.0, im: -1.0 });
+    assert_eq!(parse_complex("2i").unwrap(), Complex { re: 0.0, im: 2.0 });
+    assert_eq!(parse_complex("-i").unwrap(), Complex { re: 0.0, im: -1.0 });
+    assert_eq!(parse_complex("1.5").unwrap(), Complex { re: 1.5, im: 0.0 });
+    assert!(parse_complex("1.5+").is_err());
+    assert!(parse_complex("1.5+xi").is_err());
+    assert!(parse_complex("a+bi").is_err());
+    assert!(parse_complex("").is_err());
+}
+
+#[test]
+fn test_newton() {
+    let roots = [Complex { re: 1.0, im: 0.0 },
+                 Complex { re: -0.5, im: 3f64.sqrt() / 2.0 },
+                 Complex { re: -0.5, im: -(3f64.sqrt()) / 2.0 }];
+
+    for (index, root) in roots.iter().enumerate() {
+        let near = *root * 1.1 + Complex { re: 0.02, im: -0.01 };
+        let (converged, time) = newton(near, 50).unwrap();
+        assert_eq!(converged, index);
+        assert!(time > 0 && time < 10);
+        assert_eq!(newton(*root, 50), Some((index, 0)));
+        assert_eq!(newton::<f32>(cast(near), 50).map(|(root, _)| root), Some(index));
+    }
+
+    assert_eq!(newton(Complex { re: 0.0, im: 0.0 }, 50), None);
+    assert_eq!(newton(Complex { re: 100.0, im: 0.0 }, 50).map(|(root, _)| root), Some(0));
+    assert_eq!(newton(Complex { re: 100.0, im: 0.0 }, 2), None);
+    assert_eq!(Fractal::Newton.escape_time(roots[2] * 0.9, &Config::default()), newton(roots[2] * 0.9, 255).map(|(_, time)| time));
+}
+
+#[test]
+fn test_julia_escape_time() {
+    let c = Complex { re: 0.0, im: 0.0 };
+    assert_eq!(julia_escape_time(Complex { re: 0.0, im: 0.0 }, c, 255), None);
+    assert_eq!(julia_escape_time(Complex { re: 0.5, im: -0.5 }, c, 255), None);
+    assert_eq!(julia_escape_time(Complex { re: 0.0, im: 0.99 }, c, 255), None);
+    assert!(julia_escape_time(Complex { re: 1.01, im: 0.0 }, c, 255).is_some());
+    assert!(julia_escape_time(Complex { re: -0.8, im: 0.8 }, c, 255).is_some());
+}
+
+#[test]
+fn test_escape_time_smooth() {
+    assert_eq!(escape_time_smooth(Complex { re: 0.0, im: 0.0 }, 255), None);
+    assert_eq!(escape_time_smooth(Complex { re: -0.5, im: 0.5 }, 255), None);
+
+    for c in &[Complex { re: 0.5, im: 0.5 }, Complex { re: -1.0, im: 0.5 }, Complex { re: 2.0, im: 2.0 }] {
+        let time = escape_time(*c, 255).unwrap() as f64;
+        let smooth = escape_time_smooth(*c, 255).unwrap();
+        assert!(smooth > time - 1.0 && smooth <= time + 1.0);
+    }
+}
+
+#[test]
+fn test_smooth_time_power() {
+    let z = Complex { re: 3.0, im: 4.0 };
+    let log_log = 5f64.ln().ln();
+
+    assert_eq!(smooth_time(7, z, 2), 7.0 + 1.0 - log_log / 2f64.ln());
+    assert_eq!(smooth_time(7, z, 3), 7.0 + 1.0 - log_log / 3f64.ln());
+
+    // Once |z| is large a cubic step takes it from r to about r^3, which
+    // leaves the smooth count unchanged when normalized by log(3) only.
+    let (large, cubed) = (Complex { re: 1e3, im: 0.0 }, Complex { re: 1e9, im: 0.0 });
+    assert!((smooth_time(5, cubed, 3) - smooth_time(4, large, 3)).abs() < 1e-12);
+    assert!((smooth_time(5, cubed, 2) - smooth_time(4, large, 2)).abs() > 0.5);
+
+    let point = Complex { re: 0.6, im: 0.8 };
+    let config = Config { power: 3, coloring: Coloring::Smooth, ..Config::default() };
+    let (time, z) = escape(Complex { re: 0.0, im: 0.0 }, point, config.limit, 3, 4.0, false).unwrap();
+    assert_eq!(Fractal::Mandelbrot.escape_time_smooth(point, &config), Some(smooth_time(time, z, 3)));
+    assert_eq!(multibrot_escape_time_smooth(Complex { re: 0.0, im: 0.0 }, point, 255, 3), Some(smooth_time(time, z, 3)));
+}
+
+#[test]
+fn test_smooth_bias() {
+    // Fractional counts along the real axis, which cross many escape count
+    // boundaries on the way to the cusp at 0.25. Their logarithm follows a
+    // gentle curve, apart from the kinks where the count steps.
+    let roughness = |smooth_bias: usize| {
+        let config = Config { coloring: Coloring::Smooth, smooth_bias, ..Config::default() };
+        let times: Vec<f64> = (0..400).map(|i| Complex { re: 0.26 + i as f64 * 0.001, im: 0.0 })
+                                      .map(|point| Fractal::Mandelbrot.escape_time_smooth(point, &config).unwrap())
+                                      .map(f64::ln)
+                                      .collect();
+        times.windows(3).fold(0.0, |sum, window| sum + (window[0] - 2.0 * window[1] + window[2]).abs())
+    };
+    let (plain, biased) = (roughness(0), roughness(2));
+    assert!(biased < plain / 2.0, "{} {}", plain, biased);
+
+    // Far past the bailout, extra steps change the smooth count very little.
+    let config = Config { coloring: Coloring::Smooth, bailout: 1e3, ..Config::default() };
+    let point = Complex { re: -0.75, im: 0.2 };
+    let biased = Fractal::Mandelbrot.escape_time_smooth(point, &Config { smooth_bias: 2, ..config }).unwrap();
+    assert!((biased - Fractal::Mandelbrot.escape_time_smooth(point, &config).unwrap()).abs() < 1e-3);
+    assert_eq!(Fractal::Mandelbrot.escape_time(point, &Config { smooth_bias: 2, ..config }), Fractal::Mandelbrot.escape_time(point, &config));
+
+    // Steps that would overflow are skipped rather than giving infinities.
+    assert!(biased_smooth_time(3, Complex { re: 1e30f32, im: 0.0 }, Complex { re: 0.0, im: 0.0 }, 2, false, 4).is_finite());
+}
+
+#[test]
+fn test_render_region() {
+    let bounds = (30, 20);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.0 }, Complex { re: 1.0, im: -1.0 });
+    let config = Config { palette: Palette::Fire, ..Config::default() };
+
+    let mut expected = vec![0; bounds.0 * bounds.1 * 3];
+    render_parallel(&mut expected, bounds, upper_left, lower_right, config);
+    let mut buffer = vec![0; bounds.0 * bounds.1 * 3];
+    render_region(&mut buffer, bounds, upper_left, lower_right, config).unwrap();
+    assert_eq!(buffer, expected);
+
+    // A buffer sized for grayscale is too short for RGB, and is not written.
+    let mut short = vec![7; bounds.0 * bounds.1];
+    let error = render_region(&mut short, bounds, upper_left, lower_right, config).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid buffer : 600 bytes, expected 1800 for 30x20 pixels of 3 bytes");
+    assert!(short.iter().all(|byte| *byte == 7));
+    assert!(render_region(&mut buffer, (usize::MAX, 2), upper_left, lower_right, config).is_err());
+}
+
+#[test]
+fn test_auto_depth() {
+    let bounds = (12, 8);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.0 }, Complex { re: 1.0, im: -1.0 });
+
+    for (limit, depth) in [(200, 8), (255, 8), (256, 16), (2000, 16)] {
+        assert_eq!(auto_depth(limit), depth);
+        let config = Config { limit, depth: auto_depth(limit), ..Config::default() };
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        render_region(&mut pixels, bounds, upper_left, lower_right, config).unwrap();
+        assert_eq!((pixels.len(), config.color_type()), (bounds.0 * bounds.1 * depth as usize / 8, ColorType::Gray(depth)));
+    }
+}
+
+#[test]
+fn test_render_masked() {
+    let bounds = (40, 30);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+
+    // A disc in the middle, and a few stray pixels along the edges.
+    let mask: Vec<bool> = (0..bounds.0 * bounds.1).map(|index| (index % bounds.0, index / bounds.0))
+                                                  .map(|(x, y)| (x as f64 - 20.0).hypot(y as f64 - 15.0) < 10.0 || (x + y) % 13 == 0)
+                                                  .collect();
+
+    for config in [Config::default(), Config { palette: Palette::Ultra, coloring: Coloring::Smooth, mirror: true, ..Config::default() }] {
+        let mut full = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        render(&mut full, bounds, upper_left, lower_right, config);
+
+        let mut masked = vec![77; full.len()];
+        render_masked(&mut masked, bounds, &mask, upper_left, lower_right, config);
+
+        let channels = config.bytes_per_pixel();
+        for (index, inside) in mask.iter().enumerate() {
+            let pixel = &masked[index * channels..][..channels];
+            if *inside {
+                assert_eq!(pixel, &full[index * channels..][..channels]);
+            } else {
+                assert!(pixel.iter().all(|byte| *byte == 77));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_render_parallel_cancellable() {
+    let bounds = (64, 60);
+    let (upper_left, lower_right) = (Complex { re: -0.75, im: 0.12 }, Complex { re: -0.73, im: 0.105 });
+    let config = Config { limit: 500, palette: Palette::Fire, supersample: 2, ..Config::default() };
+    let row_length = bounds.0 * config.bytes_per_pixel();
+
+    let cancel = AtomicBool::new(false);
+    let mut pixels = vec![1; row_length * 2];
+    assert_eq!(render_parallel_cancellable(&mut pixels, (bounds.0, 2), upper_left, lower_right, config, &cancel), 2);
+
+    // Cancelled once the 10th row is done, on a single thread so the rows
+    // are taken in order.
+    let mut pixels = vec![1; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let rendered = pool.install(|| render_cancellable_with(&mut pixels, bounds, upper_left, lower_right, config, &cancel, |rendered| {
+        if rendered == 10 {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }));
+    assert_eq!(rendered, 10);
+
+    // The rendered rows are whole and match a full render, and the rest are
+    // untouched.
+    for (row, line) in pixels.chunks(row_length).enumerate() {
+        if row < rendered {
+            let mut expected = vec![0; row_length];
+            render_tile(&mut expected, bounds, (0, row), (bounds.0, 1), upper_left, lower_right, config);
+            assert_eq!(line, &expected[..], "row {}", row);
+        } else {
+            assert!(line.iter().all(|byte| *byte == 1), "row {}", row);
+        }
+    }
+    assert_eq!(render_parallel_cancellable(&mut pixels, bounds, upper_left, lower_right, config, &cancel), 0);
+}
+
+#[test]
+fn test_render_dither() {
+    // Far outside the set, where the smooth shade only rises by a few levels
+    // across the view.
+    let bounds = (64, 64);
+    let (upper_left, lower_right) = (Complex { re: 1.0, im: 0.52 }, Complex { re: 1.02, im: 0.5 });
+    let unique = |config: Config| {
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        render(&mut pixels, bounds, upper_left, lower_right, config);
+        pixels.sort_unstable();
+        pixels.dedup();
+        pixels.len()
+    };
+
+    for palette in [Palette::Grayscale, Palette::Ultra] {
+        let config = Config { coloring: Coloring::Smooth, palette, ..Config::default() };
+        let (plain, dithered) = (unique(config), unique(Config { dither: true, ..config }));
+        assert!(dithered > plain, "{:?} : {} {}", palette, plain, dithered);
+    }
+
+    // Dithering replaces rounding, so it moves no channel by a whole level.
+    let config = Config { coloring: Coloring::Smooth, palette: Palette::Fire, supersample: 2, ..Config::default() };
+    let (mut plain, mut dithered) = (vec![0; bounds.0 * bounds.1 * 3], vec![0; bounds.0 * bounds.1 * 3]);
+    render(&mut plain, bounds, upper_left, lower_right, config);
+    render(&mut dithered, bounds, upper_left, lower_right, Config { dither: true, ..config });
+    assert!(plain.iter().zip(&dithered).all(|(plain, dithered)| plain.abs_diff(*dithered) <= 1));
+    assert_ne!(plain, dithered);
+}
+
+#[test]
+fn test_supersample_threshold() {
+    let bounds = (8, 8);
+    let config = Config { supersample: 8, ..Config::default() };
+    let taken = |upper_left: Complex<f64>, lower_right: Complex<f64>, config: Config| {
+        (0..bounds.1).flat_map(|row| (0..bounds.0).map(move |column| (column, row)))
+                     .map(|pixel| supersample(bounds, pixel, upper_left, lower_right, 0.0, config).1)
+                     .sum::<usize>()
+    };
+
+    // Inside the cardioid every sample agrees, while across the seahorse
+    // valley they keep disagreeing.
+    let flat = (Complex { re: -0.2, im: 0.1 }, Complex { re: 0.0, im: -0.1 });
+    let boundary = (Complex { re: -0.76, im: 0.14 }, Complex { re: -0.73, im: 0.12 });
+
+    assert_eq!(taken(flat.0, flat.1, config), 64 * 64);
+    assert_eq!(taken(boundary.0, boundary.1, config), 64 * 64);
+
+    let config = Config { supersample_threshold: 2.0, ..config };
+    let (flat, boundary) = (taken(flat.0, flat.1, config), taken(boundary.0, boundary.1, config));
+    assert_eq!(flat, 64 * MIN_SUPERSAMPLES);
+    assert!(boundary > 4 * flat, "{} {}", flat, boundary);
+}
+
+#[test]
+fn test_iterate() {
+    let bounds = (40, 30);
+    let (upper_left, lower_right) = (Complex { re: -2.2, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let origin = Complex { re: 0.0, im: 0.0 };
+
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            for limit in [1, 2, 20, 255] {
+                for (c, z0) in [(point, origin), (Complex { re: -0.8, im: 0.156 }, point)] {
+                    let iterated = iterate(c, z0, limit, 2.0);
+                    assert_eq!(iterated.count.is_some(), iterated.z.norm_sqr() > 4.0, "{} {} {}", c, z0, limit);
+                    assert!(iterated.steps <= limit);
+                }
+                if !in_cardioid_or_bulb(point) {
+                    assert_eq!(iterate(point, origin, limit, 2.0).count, escape_time(point, limit));
+                }
+            }
+        }
+    }
+
+    // The derivative is the one the distance estimate divides by.
+    let c = Complex { re: -0.75, im: 0.2 };
+    let iterated = iterate(c, origin, 255, 2.0);
+    let norm = iterated.z.norm();
+    assert_eq!(Some(norm * norm.ln() / iterated.dz.norm()), escape_time_distance(c, 255));
+}
+
+#[test]
+fn test_render_parallel() {
+    let bounds = (37, 23);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    for config in &[Config::default(), Config { coloring: Coloring::Smooth, ..Config::default() }] {
+        let mut serial = vec![0; bounds.0 * bounds.1];
+        let mut parallel = vec![0; bounds.0 * bounds.1];
+        render(&mut serial, bounds, upper_left, lower_right, *config);
+        render_parallel(&mut parallel, bounds, upper_left, lower_right, *config);
+        assert_eq!(serial, parallel);
+    }
+}
+
+#[test]
+fn test_render_hilbert() {
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+
+    let configs = [Config::default(),
+                   Config { coloring: Coloring::Smooth, palette: Palette::Ultra, depth: 16, ..Config::default() },
+                   Config { mirror: true, supersample: 2, ..Config::default() },
+                   Config { mirror: true, palette: Palette::Fire, ..Config::default() }];
+
+    for bounds in [(61, 45), (64, 48), (200, 9), (70, 90)] {
+        for config in configs {
+            let mut expected = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+            render(&mut expected, bounds, upper_left, lower_right, config);
+
+            let mut pixels = vec![0; expected.len()];
+            render_hilbert(&mut pixels, bounds, upper_left, lower_right, config);
+            assert_eq!(pixels, expected);
+        }
+    }
+}
+
+#[test]
+fn test_render_tiles() {
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+
+    let configs = [Config::default(),
+                   Config { coloring: Coloring::Smooth, palette: Palette::Ultra, depth: 16, ..Config::default() },
+                   Config { mirror: true, palette: Palette::Fire, ..Config::default() },
+                   Config { supersample: 2, dither: true, palette: Palette::Ocean, ..Config::default() }];
+
+    for bounds in [(61, 45), (64, 64), (200, 9), (7, 100)] {
+        for config in configs {
+            let mut banded = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+            render_parallel(&mut banded, bounds, upper_left, lower_right, config);
+
+            let mut tiled = vec![0; banded.len()];
+            render_tiles(&mut tiled, bounds, upper_left, lower_right, config);
+            assert_eq!(tiled, banded, "{:?} {:?}", bounds, config);
+        }
+    }
+}
+
+#[test]
+fn test_render_progressive() {
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+
+    let configs = [Config::default(),
+                   Config { coloring: Coloring::Smooth, palette: Palette::Ultra, supersample: 2, ..Config::default() },
+                   Config { mirror: true, palette: Palette::Fire, ..Config::default() }];
+
+    for bounds in [(61, 45), (64, 48), (5, 3)] {
+        for config in configs {
+            let mut expected = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+            render_parallel(&mut expected, bounds, upper_left, lower_right, config);
+
+            let mut pixels = vec![0; expected.len()];
+            let mut passes = Vec::new();
+            render_progressive(&mut pixels, bounds, upper_left, lower_right, config, |step, pixels| passes.push((step, pixels.to_vec())));
+
+            assert_eq!(passes.iter().map(|(step, _)| *step).collect::<Vec<_>>(), PROGRESSIVE_STEPS);
+            assert_eq!(passes.last().unwrap().1, expected);
+            assert_eq!(pixels, expected);
+
+            // The first pass already has the top left pixel of every 8x8 block,
+            // except in rows mirrored from the ones above the axis.
+            if config.mirror {
+                continue;
+            }
+            let bytes_per_pixel = config.bytes_per_pixel();
+            let coarse = &passes[0].1;
+            for (row, column) in (0..bounds.1).step_by(8).flat_map(|row| (0..bounds.0).step_by(8).map(move |column| (row, column))) {
+                let index = (row * bounds.0 + column) * bytes_per_pixel;
+                assert_eq!(coarse[index..][..bytes_per_pixel], expected[index..][..bytes_per_pixel]);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_render_parallel_with_profile() {
+    let bounds = (120, 90);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+    let config = Config { limit: 2000, ..Config::default() };
+
+    let mut expected = vec![0; bounds.0 * bounds.1];
+    render_parallel(&mut expected, bounds, upper_left, lower_right, config);
+
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    let profile = render_parallel_with_profile(&mut pixels, bounds, upper_left, lower_right, config, Duration::from_micros(50));
+    assert_eq!(pixels, expected);
+    assert_eq!(profile.samples.len(), bounds.1);
+    assert_eq!(profile.interval, Duration::from_micros(50));
+}
+
+#[test]
+fn test_render_parallel_with_stats() {
+    let bounds = (31, 17);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    let mut expected = vec![0; bounds.0 * bounds.1];
+    render(&mut expected, bounds, upper_left, lower_right, Config::default());
+
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    let stats = render_parallel_with_stats(&mut pixels, bounds, upper_left, lower_right, Config::default());
+    assert_eq!(stats.timings.len(), bounds.1);
+    assert!(stats.min() <= stats.mean() && stats.mean() <= stats.max());
+    assert_eq!(pixels, expected);
+}
+
+#[test]
+fn test_render_thread_count() {
+    let bounds = (41, 29);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    let mut single = vec![0; bounds.0 * bounds.1];
+    render(&mut single, bounds, upper_left, lower_right, Config::default());
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    let mut multi = vec![0; bounds.0 * bounds.1];
+    pool.install(|| render_parallel(&mut multi, bounds, upper_left, lower_right, Config::default()));
+
+    assert_eq!(single, multi);
+}
+
+#[test]
+fn test_render_parallel_row_coverage() {
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    for &threads in &[3, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        for &height in &[1, 7, 8, 9, 135, 1080] {
+            let bounds = (3, height);
+            let mut serial = vec![0; bounds.0 * bounds.1];
+            let mut parallel = vec![0; bounds.0 * bounds.1];
+            render(&mut serial, bounds, upper_left, lower_right, Config::default());
+            pool.install(|| render_parallel(&mut parallel, bounds, upper_left, lower_right, Config::default()));
+            assert_eq!(serial, parallel, "{} rows on {} threads", height, threads);
+        }
+    }
+}
+
+#[test]
+fn test_render_depth_16() {
+    let bounds = (24, 16);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    for coloring in &[Coloring::EscapeTime, Coloring::Smooth] {
+        let config = Config { limit: 1000, coloring: *coloring, ..Config::default() };
+        let deep = Config { depth: 16, ..config };
+        assert_eq!(deep.bytes_per_pixel(), 2);
+        assert_eq!(deep.color_type(), ColorType::Gray(16));
+
+        let mut narrow = vec![0; bounds.0 * bounds.1];
+        let mut wide = vec![0; bounds.0 * bounds.1 * 2];
+        render(&mut narrow, bounds, upper_left, lower_right, config);
+        render(&mut wide, bounds, upper_left, lower_right, deep);
+
+        let wide: Vec<u16> = wide.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        for (&byte, &value) in narrow.iter().zip(&wide) {
+            assert!((value as f64 / 257.0 - byte as f64).abs() <= 1.0);
+        }
+
+        let mut distinct = wide.clone();
+        distinct.sort();
+        distinct.dedup();
+        let mut distinct_narrow = narrow.clone();
+        distinct_narrow.sort();
+        distinct_narrow.dedup();
+        assert!(distinct.len() >= distinct_narrow.len());
+    }
+}
+
+#[test]
+fn test_invert() {
+    let bounds = (24, 16);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    for config in &[Config::default(),
+                    Config { coloring: Coloring::Smooth, palette: Palette::Ultra, ..Config::default() },
+                    Config { depth: 16, ..Config::default() }] {
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        let mut inverted = pixels.clone();
+        render(&mut pixels, bounds, upper_left, lower_right, *config);
+        render(&mut inverted, bounds, upper_left, lower_right, Config { invert: true, ..*config });
+
+        assert!(pixels.iter().zip(&inverted).all(|(pixel, inverted)| *pixel == 255 - *inverted));
+
+        invert(&mut inverted);
+        assert_eq!(inverted, pixels);
+        invert(&mut inverted);
+        invert(&mut inverted);
+        assert_eq!(inverted, pixels);
+    }
+}
+
+#[test]
+fn test_interior() {
+    let bounds = (9, 9);
+    let upper_left = Complex { re: -2.0, im: 2.0 };
+    let lower_right = Complex { re: 2.0, im: -2.0 };
+    let origin = 4 * bounds.0 + 4;
+
+    for (config, expected) in [(Config { interior: parse_color("255").unwrap(), ..Config::default() }, vec![255]),
+                               (Config { interior: parse_color("ff8000").unwrap(), palette: Palette::Fire, ..Config::default() }, vec![255, 128, 0]),
+                               (Config { interior: parse_color("ff8000").unwrap(), depth: 16, coloring: Coloring::Smooth, ..Config::default() }, vec![255, 255])] {
+        let size = config.bytes_per_pixel();
+        let mut pixels = vec![0; bounds.0 * bounds.1 * size];
+        render(&mut pixels, bounds, upper_left, lower_right, config);
+        assert_eq!(&pixels[origin * size..(origin + 1) * size], &expected[..]);
+    }
+
+    assert_eq!(parse_color("00ff7f"), Some([0, 255, 127]));
+    assert_eq!(parse_color("12"), Some([12; 3]));
+    assert_eq!(parse_color("256"), None);
+    assert_eq!(parse_color("00ff7g"), None);
+}
+
+#[test]
+fn test_transparent_interior() {
+    let bounds = (9, 9);
+    let upper_left = Complex { re: -2.0, im: 2.0 };
+    let lower_right = Complex { re: 2.0, im: -2.0 };
+    let (origin, corner) = (4 * bounds.0 + 4, 0);
+
+    for config in [Config { transparent: true, ..Config::default() },
+                   Config { transparent: true, palette: Palette::Fire, invert: true, ..Config::default() }] {
+        assert_eq!(config.color_type(), ColorType::RGBA(8));
+
+        let mut pixels = vec![0; bounds.0 * bounds.1 * 4];
+        render(&mut pixels, bounds, upper_left, lower_right, config);
+        assert_eq!(pixels[origin * 4 + 3], 0);
+        assert_eq!(pixels[corner * 4 + 3], 255);
+
+        // Colors are those of the opaque render.
+        let opaque_config = Config { transparent: false, ..config };
+        let channels = opaque_config.channels();
+        let mut opaque = vec![0; bounds.0 * bounds.1 * channels];
+        render(&mut opaque, bounds, upper_left, lower_right, opaque_config);
+        assert_eq!(&pixels[corner * 4..corner * 4 + channels], &opaque[corner * channels..(corner + 1) * channels]);
+    }
+}
+
+#[test]
+fn test_render_palette() {
+    let bounds = (8, 8);
+    let upper_left = Complex { re: -2.0, im: 2.0 };
+    let lower_right = Complex { re: 2.0, im: -2.0 };
+
+    let mut gray = vec![0; bounds.0 * bounds.1];
+    render(&mut gray, bounds, upper_left, lower_right, Config::default());
+
+    let config = Config { palette: Palette::Fire, ..Config::default() };
+    let mut rgb = vec![0; bounds.0 * bounds.1 * 3];
+    render(&mut rgb, bounds, upper_left, lower_right, config);
+
+    for (value, color) in gray.iter().zip(rgb.chunks(3)) {
+        let expected = if *value == 0 { [0, 0, 0] } else { Palette::Fire.color(*value as f64 / 255.0) };
+        assert_eq!(color, expected);
+    }
+}
+
+#[test]
+fn test_render_parallel_with_progress() {
+    let bounds = (16, 12);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+
+    let mut expected = vec![0; bounds.0 * bounds.1];
+    render(&mut expected, bounds, upper_left, lower_right, Config::default());
+
+    let progress = Progress::new(bounds.1);
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    render_parallel_with_progress(&mut pixels, bounds, upper_left, lower_right, Config::default(), &progress);
+
+    assert_eq!(progress.completed(), bounds.1);
+    assert_eq!(pixels, expected);
+}
+
+#[test]
+fn test_render_supersample() {
+    let bounds = (32, 32);
+    let upper_left = Complex { re: -0.80, im: 0.20 };
+    let lower_right = Complex { re: -0.70, im: 0.10 };
+
+    let roughness = |pixels: &[u8]| -> f64 {
+        let differences: Vec<f64> = pixels.chunks(bounds.0)
+                                          .flat_map(|line| line.windows(2).map(|pair| pair[0] as f64 - pair[1] as f64))
+                                          .collect();
+        let mean = differences.iter().sum::<f64>() / differences.len() as f64;
+        differences.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / differences.len() as f64
+    };
+
+    let mut single = vec![0; bounds.0 * bounds.1];
+    render(&mut single, bounds, upper_left, lower_right, Config::default());
+
+    let mut supersampled = vec![0; bounds.0 * bounds.1];
+    render(&mut supersampled, bounds, upper_left, lower_right, Config { supersample: 2, ..Config::default() });
+
+    assert!(roughness(&supersampled) < roughness(&single));
+}
+
+#[test]
+fn test_in_cardioid_or_bulb() {
+    assert!(in_cardioid_or_bulb(Complex { re: -0.5, im: 0.0 }));
+    assert!(in_cardioid_or_bulb(Complex { re: 0.0, im: 0.0 }));
+    assert!(in_cardioid_or_bulb(Complex { re: -1.0, im: 0.1 }));
+    assert!(!in_cardioid_or_bulb(Complex { re: 0.5, im: 0.0 }));
+    assert!(!in_cardioid_or_bulb(Complex { re: -1.3, im: 0.0 }));
+    assert!(!in_cardioid_or_bulb(Complex { re: -0.75, im: 0.1 }));
+
+    assert_eq!(escape_time(Complex { re: -0.5, im: 0.0 }, usize::MAX), None);
+    assert_eq!(escape_time_smooth(Complex { re: -1.0, im: 0.0 }, usize::MAX), None);
+}
+
+#[test]
+fn test_periodicity_checking() {
+    let naive = |c: Complex<f64>, limit: usize| -> Option<usize> {
+        let mut z = Complex { re: 0.0, im: 0.0 };
+        for i in 0..limit {
+            if z.norm_sqr() > 4.0 {
+                return Some(i);
+            }
+            z = z * z + c;
+        }
+        None
+    };
+
+    let bounds = (64, 48);
+    let upper_left = Complex { re: -1.9, im: 1.1 };
+    let lower_right = Complex { re: 0.5, im: -1.1 };
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+            let c = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            assert_eq!(julia_escape_time(Complex { re: 0.0, im: 0.0 }, c, 1000), naive(c, 1000));
+        }
+    }
+
+    assert_eq!(julia_escape_time(Complex { re: 0.0, im: 0.0 }, Complex { re: -0.1, im: 0.8 }, usize::MAX), None);
+}
+
+#[test]
+fn test_single_precision() {
+    for c in &[Complex { re: 0.0, im: 0.0 }, Complex { re: -0.1, im: 0.8 }] {
+        assert_eq!(escape_time::<f64>(*c, 255), None);
+        assert_eq!(escape_time::<f32>(cast(*c), 255), None);
+    }
+    assert_eq!(escape_time::<f32>(Complex { re: 1.0, im: 1.0 }, 255), escape_time::<f64>(Complex { re: 1.0, im: 1.0 }, 255));
+
+    let bounds = (16, 16);
+    let upper_left = Complex { re: -2.0, im: 2.0 };
+    let lower_right = Complex { re: 2.0, im: -2.0 };
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    render(&mut pixels, bounds, upper_left, lower_right, Config { precision: Precision::Single, ..Config::default() });
+    assert_eq!(pixels[8 * bounds.0 + 8], 0);
+}
+
+#[test]
+fn test_julia_frame_constant() {
+    let start = Complex { re: -0.8, im: 0.0 };
+    let end = Complex { re: 0.2, im: 1.0 };
+    assert_eq!(julia_frame_constant(start, end, 0, 11), start);
+    assert_eq!(julia_frame_constant(start, end, 10, 11), end);
+    assert!((julia_frame_constant(start, end, 5, 11) - Complex { re: -0.3, im: 0.5 }).norm() < 1e-12);
+    assert_eq!(julia_frame_constant(start, end, 0, 1), start);
+}
+
+#[test]
+fn test_zoom_frame_bounds() {
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+    let center = Complex { re: -0.5, im: 0.6 };
+    let bounds = (300, 240);
+
+    let (ul, lr) = zoom_frame_bounds(upper_left, lower_right, center, 2.0, 0);
+    assert!((ul - upper_left).norm() < 1e-12 && (lr - lower_right).norm() < 1e-12);
+
+    let (ul, lr) = zoom_frame_bounds(upper_left, lower_right, center, 2.0, 3);
+    assert!(((lr.re - ul.re) - 3.0 / 8.0).abs() < 1e-12);
+    assert!(((ul.im - lr.im) - 2.4 / 8.0).abs() < 1e-12);
+
+    // The center maps back to the same pixel in the zoomed frame.
+    let pixel = (150, 60);
+    assert!((pixel_to_point(bounds, pixel, upper_left, lower_right) - center).norm() < 1e-12);
+    assert!((pixel_to_point(bounds, pixel, ul, lr) - center).norm() < 1e-12);
+}
+
+#[test]
+fn test_zoom_frame_limit() {
+    assert_eq!(zoom_frame_limit(100, 2.0, 0), 100);
+    assert_eq!(zoom_frame_limit(100, 10.0, 1), 200);
+    assert_eq!(zoom_frame_limit(100, 10.0, 3), 400);
+    assert!(zoom_frame_limit(100, 1.5, 10) > zoom_frame_limit(100, 1.5, 9));
+}
+
+#[test]
+fn test_auto_iterations() {
+    assert_eq!(auto_iterations(4.0, 255, 100.0), 255);
+    assert_eq!(auto_iterations(1e-3, 255, 100.0), 555);
+
+    let limits: Vec<usize> = (0..20).map(|step| auto_iterations(3.0 * 0.5f64.powi(step), 100, 250.0)).collect();
+    assert!(limits.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(limits[19] > limits[10]);
+}
+
+#[test]
+fn test_frame_path() {
+    assert_eq!(frame_path("frames", "frame", 7), Path::new("frames/frame_0007.png"));
+    assert_eq!(frame_path(".", "julia", 1234), Path::new("./julia_1234.png"));
+}
+
+#[test]
+fn test_multibrot_symmetry() {
+    let bounds = (64, 64);
+    let upper_left = Complex { re: -2.0, im: 2.0 };
+    let lower_right = Complex { re: 2.0, im: -2.0 };
+
+    let is_point_symmetric = |power: u32| -> bool {
+        let mut pixels = vec![0; bounds.0 * bounds.1];
+        render(&mut pixels, bounds, upper_left, lower_right, Config { power, ..Config::default() });
+        (1..bounds.1).all(|row| (1..bounds.0).all(|column| {
+            pixels[row * bounds.0 + column] == pixels[(bounds.1 - row) * bounds.0 + bounds.0 - column]
+        }))
+    };
+
+    assert!(is_point_symmetric(3));
+    assert!(!is_point_symmetric(2));
+
+    let c = Complex { re: -0.3, im: 0.4 };
+    assert_eq!(multibrot_escape_time(Complex { re: 0.0, im: 0.0 }, c, 255, 2), escape_time(c, 255));
+}
+
+#[test]
+fn test_burning_ship_escape_time() {
+    assert_eq!(burning_ship_escape_time(Complex { re: -1.75, im: -0.02 }, 255, 2), None);
+    assert_eq!(burning_ship_escape_time(Complex { re: 0.0, im: 0.0 }, 255, 2), None);
+    assert!(burning_ship_escape_time(Complex { re: 1.0, im: 1.0 }, 255, 2).is_some());
+
+    let c = Complex { re: -0.2, im: 0.7 };
+    assert_eq!(Fractal::BurningShip.escape_time(c, &Config::default()), burning_ship_escape_time(c, 255, 2));
+    assert_ne!(burning_ship_escape_time(c, 255, 2), escape_time(c, 255));
+}
+
+#[test]
+fn test_validate_bounds() {
+    let upper_left = Complex { re: -1.0, im: 1.0 };
+    let lower_right = Complex { re: 1.0, im: -1.0 };
+
+    assert!(validate_bounds((100, 100), upper_left, lower_right).is_ok());
+    assert!(validate_bounds((0, 100), upper_left, lower_right).is_err());
+    assert!(validate_bounds((100, 0), upper_left, lower_right).is_err());
+    assert!(validate_bounds((100, 100), lower_right, upper_left).is_err());
+    assert!(validate_bounds((100, 100), Complex { re: 1.0, im: 1.0 }, Complex { re: -1.0, im: -1.0 }).is_err());
+    assert!(validate_bounds((100, 100), Complex { re: -1.0, im: -1.0 }, Complex { re: 1.0, im: 1.0 }).is_err());
+    assert!(validate_bounds((100, 100), Complex { re: 1.0, im: 1.0 }, Complex { re: 1.0, im: -1.0 }).is_err());
+    assert!(validate_bounds((100, 100), Complex { re: -1.0, im: 1.0 }, Complex { re: 1.0, im: 1.0 }).is_err());
+}
+
+#[test]
+fn test_render_histogram() {
+    let bounds = (96, 64);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+    let config = Config { limit: 2000, ..Config::default() };
+
+    let distinct = |pixels: &[u8]| pixels.iter().collect::<std::collections::HashSet<_>>().len();
+
+    let mut linear = vec![0; bounds.0 * bounds.1];
+    render(&mut linear, bounds, upper_left, lower_right, config);
+
+    let mut equalized = vec![0; bounds.0 * bounds.1];
+    render_histogram(&mut equalized, bounds, upper_left, lower_right, config);
+
+    assert!(distinct(&equalized) > distinct(&linear));
+}
+
+#[test]
+fn test_render_iterations_map() {
+    let config = Config { limit: 500, ..Config::default() };
+    let mandelbrot = Fractal::Mandelbrot;
+
+    // Points in the main cardioid or bulb take no steps at all, points caught
+    // in a cycle stop well short of the limit and quick escapes take a few.
+    assert_eq!(mandelbrot.executed_iterations(Complex { re: 0.0, im: 0.0 }, &config), 0);
+    assert_eq!(mandelbrot.executed_iterations(Complex { re: -1.0, im: 0.0 }, &config), 0);
+    assert!(mandelbrot.executed_iterations(Complex { re: -0.1225, im: 0.7449 }, &config) < config.limit / 2);
+    assert_eq!(mandelbrot.executed_iterations(Complex { re: 0.4, im: 0.1 }, &config), escape_time(Complex { re: 0.4, im: 0.1 }, 500).unwrap());
+    assert!(mandelbrot.executed_iterations(Complex { re: 0.0, im: 0.0 }, &Config { power: 3, ..config }) > 0);
+
+    let bounds = (100, 80);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+    render_iterations_map(&mut pixels, bounds, upper_left, lower_right, config);
+
+    let heat = |pixel: &[u8]| pixel.iter().map(|channel| *channel as usize).sum::<usize>();
+    let at = |point: Complex<f64>| {
+        let (x, y) = point_to_pixel(bounds, point, upper_left, lower_right);
+        heat(&pixels[(y as usize * bounds.0 + x as usize) * 3..][..3])
+    };
+
+    // The cardioid is darker than the exterior, while the hottest pixels lie
+    // on the boundary between them.
+    let interior = at(Complex { re: -0.2, im: 0.0 });
+    assert_eq!(interior, heat(&Palette::Inferno.color(0.0)));
+    assert!(at(Complex { re: 0.9, im: 0.0 }) > interior);
+
+    let hottest = pixels.chunks(3).enumerate().max_by_key(|(_, pixel)| heat(pixel)).unwrap().0;
+    let point = pixel_to_point(bounds, (hottest % bounds.0, hottest / bounds.0), upper_left, lower_right);
+    assert!(heat(&pixels[hottest * 3..][..3]) > 4 * interior.max(1));
+    assert!(escape_time(point, 500).is_none_or(|time| time > 50));
+}
+
+#[test]
+fn test_render_cycle() {
+    let bounds = (64, 48);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let config = Config { palette: Palette::Ultra, ..Config::default() };
+
+    let mut counts = vec![None; bounds.0 * bounds.1];
+    render_counts(&mut counts, bounds, upper_left, lower_right, config);
+
+    let cycle = |phase: f64| {
+        let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+        render_cycle(&mut pixels, &counts, config, phase);
+        pixels
+    };
+
+    // Without a phase, the counts color exactly as a direct render.
+    let mut direct = vec![0; bounds.0 * bounds.1 * 3];
+    render(&mut direct, bounds, upper_left, lower_right, config);
+    assert_eq!(cycle(0.0), direct);
+
+    assert_ne!(cycle(0.25), cycle(0.5));
+    assert_eq!(cycle(0.25), cycle(1.25));
+
+    // Points in the set keep the interior color whatever the phase.
+    let interior = counts.iter().position(|count| count.is_none()).unwrap();
+    assert_eq!(cycle(0.5)[interior * 3..interior * 3 + 3], [0, 0, 0]);
+}
+
+#[test]
+fn test_histogram_equalize() {
+    let shades = histogram_equalize(&[Some(1), Some(1), Some(3), None], 4);
+    assert_eq!(shades, vec![Some(1.0), Some(1.0), Some(1.0 - 2.0 / 3.0), None]);
+}
+
+#[test]
+fn test_bailout() {
+    let default = Config::default();
+    let large = Config { bailout: 100.0, ..Config::default() };
+
+    for c in &[Complex { re: 0.5, im: 0.5 }, Complex { re: -1.0, im: 0.4 }, Complex { re: -0.75, im: 0.1 }, Complex { re: 2.0, im: 2.0 }] {
+        let time = Fractal::Mandelbrot.escape_time(*c, &default).unwrap();
+        assert_eq!(Some(time), escape_time(*c, 255));
+        assert!(Fractal::Mandelbrot.escape_time(*c, &large).unwrap() >= time);
+    }
+    assert_eq!(Fractal::Mandelbrot.escape_time(Complex { re: -0.1, im: 0.8 }, &large), None);
+}
+
+#[test]
+fn test_escape_time_distance() {
+    assert_eq!(escape_time_distance(Complex { re: 0.0, im: 0.0 }, 255), None);
+    assert_eq!(escape_time_distance(Complex { re: -0.1, im: 0.8 }, 255), None);
+
+    let near = escape_time_distance(Complex { re: 0.26, im: 0.0 }, 1000).unwrap();
+    let far = escape_time_distance(Complex { re: 1.0, im: 1.0 }, 1000).unwrap();
+    assert!(near > 0.0 && near < 0.05);
+    assert!(far > near);
+
+    let config = Config::default();
+    assert_eq!(Fractal::Mandelbrot.escape_time_distance(Complex { re: 0.26, im: 0.0 }, &Config { limit: 1000, ..config }), Some(near));
+    assert!(Fractal::Julia(Complex { re: 0.0, im: 0.0 }).escape_time_distance(Complex { re: 1.01, im: 0.0 }, &config).unwrap() < 0.05);
+}
+
+#[test]
+fn test_escape_time_orbit_trap() {
+    assert_eq!(escape_time_orbit_trap(Complex { re: 0.0, im: 0.0 }, 255, Trap::Point), None);
+    assert_eq!(escape_time_orbit_trap(Complex { re: -0.1, im: 0.8 }, 255, Trap::Point), None);
+
+    let c = Complex { re: 0.4, im: 0.1 };
+    let time = escape_time(c, 255).unwrap();
+
+    // The trap distance is the running minimum over the orbit, so it never
+    // increases from one iterate to the next and matches a direct walk.
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    let mut min_distance = f64::INFINITY;
+    for _ in 0..time {
+        z = z * z + c;
+        let next = min_distance.min(z.norm());
+        assert!(next <= min_distance);
+        min_distance = next;
+    }
+
+    assert_eq!(escape_time_orbit_trap(c, 255, Trap::Point), Some(min_distance));
+    assert_eq!(escape_time_orbit_trap(c, 1000, Trap::Point), Some(min_distance));
+    assert_eq!(escape_time_orbit_trap(c, time, Trap::Point), None);
+    assert!(escape_time_orbit_trap(c, 255, Trap::Line).unwrap() <= 0.1);
+
+    assert_eq!("circle".parse::<Trap>(), Ok(Trap::Circle));
+    assert!("square".parse::<Trap>().is_err());
+}
+
+#[test]
+fn test_lighting() {
+    let config = Config::default();
+    let mandelbrot = Fractal::Mandelbrot;
+    assert_eq!(mandelbrot.escape_time_normal(Complex { re: -0.2, im: 0.1 }, &config), None);
+    assert_eq!(Fractal::Newton.escape_time_normal(Complex { re: 2.0, im: 1.0 }, &config), None);
+
+    // Far from the set the surface slopes straight away from it.
+    let normal = mandelbrot.escape_time_normal(Complex { re: 10.0, im: 0.0 }, &config).unwrap();
+    assert!((normal - Complex { re: 1.0, im: 0.0 }).norm() < 1e-9);
+    assert!((Light { azimuth: 0.0, elevation: 0.0 }.brightness(normal) - 0.5f64.sqrt()).abs() < 1e-9);
+    assert!((Light { azimuth: 0.0, elevation: 90.0 }.brightness(normal) - 0.5f64.sqrt()).abs() < 1e-9);
+
+    // Turning the light around swaps lit and shaded slopes along the boundary.
+    let light = Light { azimuth: 30.0, elevation: 45.0 };
+    let opposite = Light { azimuth: 210.0, ..light };
+    let bounds = (60, 45);
+    let (upper_left, lower_right) = (Complex { re: -0.78, im: 0.17 }, Complex { re: -0.72, im: 0.12 });
+    let mut boundary = 0;
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            let lit = shade(point, 0.001, Config { coloring: Coloring::Lighting(light), ..config });
+            let turned = shade(point, 0.001, Config { coloring: Coloring::Lighting(opposite), ..config });
+            assert_eq!(lit.is_some(), turned.is_some());
+            if let (Some(lit), Some(turned)) = (lit, turned) {
+                assert!((lit + turned - 1.0).abs() < 1e-9, "{} {} at {}", lit, turned, point);
+                boundary += 1;
+            }
+        }
+    }
+    assert!(boundary > 100);
+
+    assert_eq!("120,30".parse(), Ok(Light { azimuth: 120.0, elevation: 30.0 }));
+    assert!("120".parse::<Light>().is_err());
+    assert!("0,95".parse::<Light>().is_err());
+}
+
+#[test]
+fn test_escape_time_stripe() {
+    let config = Config::default();
+    let mandelbrot = Fractal::Mandelbrot;
+    assert_eq!(mandelbrot.escape_time_stripe(Complex { re: 0.0, im: 0.0 }, &config, 5.0), None);
+    assert_eq!(mandelbrot.escape_time_stripe(Complex { re: -0.1, im: 0.8 }, &config, 5.0), None);
+    assert_eq!(mandelbrot.escape_time_stripe(Complex { re: -1.0, im: 0.0 }, &config, 5.0), None);
+    assert_eq!(Fractal::Newton.escape_time_stripe(Complex { re: 2.0, im: 1.0 }, &config, 5.0), None);
+
+    // The smooth count matches smooth coloring, and the average of sines
+    // stays within their range over a whole view.
+    let c = Complex { re: 0.4, im: 0.1 };
+    let (time, _) = mandelbrot.escape_time_stripe(c, &config, 5.0).unwrap();
+    assert_eq!(Some(time), mandelbrot.escape_time_smooth(c, &config));
+
+    let bounds = (80, 60);
+    let (upper_left, lower_right) = (Complex { re: -2.2, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let mut stripes = Vec::new();
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            stripes.extend(mandelbrot.escape_time_stripe(point, &config, 5.0).map(|(_, stripe)| stripe));
+        }
+    }
+    assert!(stripes.iter().all(|stripe| (-1.0..=1.0).contains(stripe)));
+    assert!(stripes.iter().any(|stripe| *stripe < -0.1) && stripes.iter().any(|stripe| *stripe > 0.1));
+
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    render(&mut pixels, bounds, upper_left, lower_right, Config { coloring: Coloring::Stripe(5.0), ..config });
+    assert_eq!(pixels[pixels.len() / 2 + bounds.0 / 3], 0);
+    assert!(pixels.iter().any(|pixel| *pixel > 0));
+}
+
+#[test]
+fn test_render_deep() {
+    // A view 1e-16 wide on c = i, where neighbouring pixels are closer than
+    // f64 can tell apart but the escape times still vary.
+    let bounds = (16, 16);
+    let center = Complex { re: 0.0, im: 1.0 };
+    let zoom = 4e16;
+    let config = Config { limit: 1000, ..Config::default() };
+    let distinct = |pixels: &[u8]| {
+        let mut values = pixels.to_vec();
+        values.sort_unstable();
+        values.dedup();
+        values.len()
+    };
+
+    let (upper_left, lower_right) = view_from_center(bounds, center, zoom);
+    assert_eq!(upper_left.im, lower_right.im);
+    let mut shallow = vec![0; bounds.0 * bounds.1];
+    render(&mut shallow, bounds, upper_left, lower_right, config);
+
+    let mut deep = vec![0; bounds.0 * bounds.1];
+    render_deep(&mut deep, bounds, center, zoom, config);
+    assert_eq!(distinct(&shallow), 1);
+    assert!(distinct(&deep) > 4);
+
+    // The same view at f64-friendly scales matches the ordinary renderer.
+    let mut expected = vec![0; bounds.0 * bounds.1];
+    let mut actual = vec![0; bounds.0 * bounds.1];
+    let (upper_left, lower_right) = view_from_center(bounds, center, 4e6);
+    render(&mut expected, bounds, upper_left, lower_right, config);
+    render_deep(&mut actual, bounds, center, 4e6, config);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_render_crop() {
+    let bounds = (40, 30);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+    let (origin, size) = parse_crop("7,5,20,13").unwrap();
+
+    for config in [Config::default(), Config { coloring: Coloring::Smooth, palette: Palette::Ultra, ..Config::default() }] {
+        let bytes_per_pixel = config.bytes_per_pixel();
+
+        let mut full = vec![0; bounds.0 * bounds.1 * bytes_per_pixel];
+        render(&mut full, bounds, upper_left, lower_right, config);
+
+        let mut cropped = vec![17; full.len()];
+        render_crop(&mut cropped, bounds, origin, size, upper_left, lower_right, config);
+
+        for (row, (expected, actual)) in full.chunks(bounds.0 * bytes_per_pixel).zip(cropped.chunks(bounds.0 * bytes_per_pixel)).enumerate() {
+            for (column, (expected, actual)) in expected.chunks(bytes_per_pixel).zip(actual.chunks(bytes_per_pixel)).enumerate() {
+                let inside = (origin.0..origin.0 + size.0).contains(&column) && (origin.1..origin.1 + size.1).contains(&row);
+                if inside {
+                    assert_eq!(actual, expected);
+                } else {
+                    assert!(actual.iter().all(|byte| *byte == 17));
+                }
+            }
+        }
+    }
+
+    assert_eq!(parse_crop("0,0,1,1"), Some(((0, 0), (1, 1))));
+    assert_eq!(parse_crop("1,2,3"), None);
+    assert_eq!(parse_crop("1,2,3,x"), None);
+}
+
+#[test]
+fn test_render_precision_diff() {
+    let bounds = (64, 48);
+    let config = Config { limit: 500, ..Config::default() };
+    let mismatched = |upper_left, lower_right| {
+        let mut pixels = vec![0; bounds.0 * bounds.1];
+        render_precision_diff(&mut pixels, bounds, upper_left, lower_right, config);
+        pixels.iter().filter(|pixel| **pixel != 0).count()
+    };
+
+    let shallow = mismatched(Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let (upper_left, lower_right) = view_from_center(bounds, Complex { re: -0.743643887037151, im: 0.13182590420533 }, 1e5);
+    let deep = mismatched(upper_left, lower_right);
+
+    assert!(shallow * 20 < bounds.0 * bounds.1);
+    assert!(deep > shallow * 4);
+}
+
+#[test]
+fn test_render_adaptive() {
+    let bounds = (64, 48);
+    let upper_left = Complex { re: -2.2, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+    let config = Config { supersample: 3, coloring: Coloring::Smooth, ..Config::default() };
+
+    let mut uniform = vec![0; bounds.0 * bounds.1];
+    render(&mut uniform, bounds, upper_left, lower_right, config);
+
+    let mut single = vec![0; bounds.0 * bounds.1];
+    render(&mut single, bounds, upper_left, lower_right, Config { supersample: 1, ..config });
+
+    let mut adaptive = vec![0; bounds.0 * bounds.1];
+    let refined = render_adaptive(&mut adaptive, bounds, upper_left, lower_right, config, 4);
+    assert!(refined > 0 && refined * 3 < bounds.0 * bounds.1);
+
+    // Every pixel is either refined exactly like uniform supersampling or
+    // left at its single sample.
+    let matching = adaptive.iter().zip(&uniform).filter(|(adaptive, uniform)| adaptive == uniform).count();
+    assert!(adaptive.iter().zip(&uniform).zip(&single).all(|((adaptive, uniform), single)| adaptive == uniform || adaptive == single));
+    assert!(matching >= refined);
+
+    // The largest errors of the single sample render are on the edges, which
+    // adaptive sampling fixes.
+    let error = |pixels: &[u8]| pixels.iter().zip(&uniform).map(|(pixel, uniform)| (*pixel as i32 - *uniform as i32).abs()).max().unwrap();
+    assert!(error(&adaptive) < error(&single));
+}
+
+#[test]
+fn test_mirror() {
+    let upper_left = Complex { re: -2.2, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+
+    for bounds in [(64, 48), (65, 31)] {
+        for config in [Config::default(),
+                       Config { coloring: Coloring::Smooth, palette: Palette::Ultra, ..Config::default() },
+                       Config { fractal: Fractal::Julia(Complex { re: -0.75, im: 0.0 }), ..Config::default() }] {
+            let mut full = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+            render(&mut full, bounds, upper_left, lower_right, config);
+
+            let mirrored = Config { mirror: true, ..config };
+            assert!(mirror_rows(bounds, upper_left, lower_right, mirrored) < bounds.1);
+
+            let mut serial = vec![0; full.len()];
+            render(&mut serial, bounds, upper_left, lower_right, mirrored);
+            assert!(serial == full);
+
+            let mut parallel = vec![0; full.len()];
+            render_parallel(&mut parallel, bounds, upper_left, lower_right, mirrored);
+            assert!(parallel == full);
+        }
+    }
+
+    let config = Config { mirror: true, ..Config::default() };
+    assert_eq!(mirror_rows((64, 48), upper_left, Complex { re: 1.0, im: -1.0 }, config), 48);
+    assert_eq!(mirror_rows((64, 48), upper_left, lower_right, Config { fractal: Fractal::BurningShip, ..config }), 48);
+    assert_eq!(mirror_rows((64, 48), upper_left, lower_right, Config { supersample: 2, ..config }), 48);
+
+    let bounds = (64, 48);
+    let dithered = Config { coloring: Coloring::Smooth, palette: Palette::Ultra, dither: true, ..Config::default() };
+    let mut full = vec![0; bounds.0 * bounds.1 * dithered.bytes_per_pixel()];
+    render(&mut full, bounds, upper_left, lower_right, dithered);
+
+    let mirrored = Config { mirror: true, ..dithered };
+    assert_eq!(mirror_rows(bounds, upper_left, lower_right, mirrored), bounds.1);
+    let mut serial = vec![0; full.len()];
+    render(&mut serial, bounds, upper_left, lower_right, mirrored);
+    assert!(serial == full);
+    let mut parallel = vec![0; full.len()];
+    render_parallel(&mut parallel, bounds, upper_left, lower_right, mirrored);
+    assert!(parallel == full);
+}
+
+#[test]
+fn test_gamma() {
+    let linear = Config::default();
+    let corrected = Config { gamma: 2.2, ..linear };
+
+    assert_eq!(shade_color(Some(0.5), linear)[0], 128.0);
+    assert!(shade_color(Some(0.5), corrected)[0] > 180.0);
+    assert_eq!(shade_color(Some(0.0), corrected)[0], 0.0);
+    assert_eq!(shade_color(Some(1.0), corrected)[0], 255.0);
+    assert_eq!(shade_color(None, Config { interior: [40; 3], ..corrected })[0], 40.0);
+
+    let palette = Config { palette: Palette::Ocean, ..linear };
+    let lifted = shade_color(Some(0.5), Config { gamma: 2.2, ..palette });
+    for (channel, plain) in lifted.iter().zip(shade_color(Some(0.5), palette)).take(3) {
+        assert!(*channel >= plain);
+    }
+}
+
+#[test]
+fn test_orbit() {
+    let config = Config::default();
+
+    // 0, 1, 2, 5 : escapes after three steps.
+    let orbit = Fractal::Mandelbrot.orbit(Complex { re: 1.0, im: 0.0 }, &config);
+    assert_eq!(orbit, vec![Complex { re: 1.0, im: 0.0 }, Complex { re: 2.0, im: 0.0 }, Complex { re: 5.0, im: 0.0 }]);
+
+    let c = Complex { re: 0.38, im: 0.3 };
+    let orbit = Fractal::Mandelbrot.orbit(c, &config);
+    assert_eq!(orbit.len(), 19);
+    assert_eq!(Some(orbit.len()), escape_time(c, config.limit));
+    assert!(orbit.last().unwrap().norm_sqr() > 4.0);
+    assert!(orbit[..orbit.len() - 1].iter().all(|z| z.norm_sqr() <= 4.0));
+
+    assert_eq!(Fractal::Mandelbrot.orbit(Complex { re: -1.0, im: 0.0 }, &config).len(), config.limit);
+    assert_eq!(Fractal::Julia(c).orbit(Complex { re: 3.0, im: 0.0 }, &config), vec![]);
+}
+
+#[test]
+fn test_log_scale() {
+    let bounds = (120, 80);
+    let (upper_left, lower_right) = (Complex { re: -2.2, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+
+    // The levels of the points outside the set spread further apart, rather
+    // than most of them sharing the few brightest levels.
+    let spread = |config: Config| {
+        let mut pixels = vec![0; bounds.0 * bounds.1];
+        render(&mut pixels, bounds, upper_left, lower_right, config);
+        let levels: Vec<f64> = pixels.iter().filter(|pixel| **pixel > 0).map(|pixel| *pixel as f64).collect();
+        let mean = levels.iter().sum::<f64>() / levels.len() as f64;
+        (levels.iter().map(|level| (level - mean).powi(2)).sum::<f64>() / levels.len() as f64).sqrt()
+    };
+
+    let linear = spread(Config::default());
+    let log = spread(Config { log_scale: true, ..Config::default() });
+    assert!(log > 2.0 * linear, "log spread {} is not well above linear {}", log, linear);
+
+    let config = Config { log_scale: true, ..Config::default() };
+    assert_eq!(linear_shade(0, config), 1.0);
+    assert_eq!(linear_shade(config.limit, config), 0.0);
+    assert!(linear_shade(31, config) < 0.5);
+    assert_eq!(smooth_shade(15.0, config), linear_shade(15, config));
+}
@@ -1,154 +1,1677 @@
+use mandelbrot::{ChunkOrder, Coloring, Config, Fractal, Light, Progress, parse_bounds, parse_color, parse_complex, parse_crop, render_adaptive, render_crop, render_masked, render_parallel_cancellable, render_region, render_precision_diff, render_counts, render_parallel, render_parallel_with_progress, render_parallel_with_stats, render_parallel_with_profile, render_progressive, render_hilbert, render_tiles, render_histogram, render_cycle, render_iterations_map, validate_bounds, write_counts, write_image, write_image_with_metadata, write_raw16};
+use mandelbrot::{area_bounds, estimate_area, auto_depth, load_mask, auto_iterations, autofocus, make_seamless, parse_manifest, compare, load_baseline, antibuddhabrot_density, buddhabrot_density, nebulabrot_density, render_density, render_nebula, Checkpoint, Gradient, IterationSchedule, MappedBuffer, Palette, Precision, RenderError, render_deep, render_perturbation, find_preset, aspect_distortion, JsonConfig, fit_aspect, frame_path, julia_frame_constant, view_from_center, write_streamed_png, write_tiled_png, zoom_frame_bounds, zoom_frame_limit, RenderConfig};
+use image::ColorType;
 use num::Complex;
+use mandelbrot::grid::{draw_grid, gray_to_rgb};
+use mandelbrot::resample::lanczos_resize;
+use mandelbrot::terminal::{apply_key, ascii_preview, parse_keys, preview, preview_bounds, Key};
+use std::borrow::Cow;
 use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
 use std::str::FromStr;
-use image::ColorType;
-use image::png::PNGEncoder;
-use std::fs::File;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 fn main() {
-    
-    let args: Vec<String> = env::args().collect();
 
-    if args.len() != 5 {
-        eprintln!("Usage : {} mandelbrot.png 1920x1080 -1,1 1,-1", args[0]);
+    if let Err(error) = run() {
+        eprintln!("Error : {}", error);
         std::process::exit(1);
     }
-    
-    let bounds = parse_pair::<usize>(&args[2], 'x').expect("Error while parsing bounds");
-    let upper_left = parse_complex(&args[3]).expect("Error while parsing first complex number");
-    let lower_right = parse_complex(&args[4]).expect("Error while parsing second complex number");
 
-    let mut pixels = vec![0; bounds.0 * bounds.1];
+}
 
-    let threads = 8;
-    let rows_per_band = bounds.1 / threads + 1;
+fn run() -> Result<(), Box<dyn Error>> {
 
-    {
+    let mut args: Vec<String> = env::args().collect();
 
-        let bands: Vec<&mut [u8]> = pixels.chunks_mut(rows_per_band * bounds.0).collect();
+    if take_switch(&mut args, "--help") || take_switch(&mut args, "-h") {
+        print!("{}", usage(&args[0]));
+        return Ok(());
+    }
+    if take_switch(&mut args, "--version") || take_switch(&mut args, "-V") {
+        println!("mandelbrot {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    if let Some(manifest) = take_flag(&mut args, "--batch") {
+        let jobs = match take_flag(&mut args, "--jobs") {
+            None => 1,
+            Some(jobs) => parse_arg::<usize>(&jobs, "--jobs")?.max(1)
+        };
+        let strict = take_switch(&mut args, "--strict");
+        return run_batch(&manifest, &args[1..], jobs, strict);
+    }
 
-        crossbeam::scope(|spawner| {
-            for (i, band) in bands.into_iter().enumerate() {
-                let top = rows_per_band * i;
-                let height = band.len() / bounds.0;
-                let band_bounds = (bounds.0, height);
-                let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
-                let band_lower_right = pixel_to_point(bounds, (bounds.0, top + height), upper_left, lower_right);
+    let options = parse(args)?;
+    validate(&options)?;
+    execute(options)
 
-                spawner.spawn(move |_| {
-                    render(band, band_bounds, band_upper_left, band_lower_right);
-                });
+}
+
+// Everything given on the command line for a single render, taken out of the
+// arguments but not yet checked against each other.
+struct Options {
+    // The program name followed by the positional arguments.
+    args: Vec<String>,
+    logger: Logger,
+    file: Option<RenderConfig>,
+    from_image: Option<String>,
+    to_json: bool,
+    config: Config,
+    depth: bool,
+    depth_auto: bool,
+    adaptive: Option<usize>,
+    perturbation: bool,
+    precision_diff: bool,
+    buddhabrot: bool,
+    antibuddhabrot: bool,
+    nebulabrot: Option<[usize; 3]>,
+    nebula_samples: Option<[usize; 3]>,
+    seed_orbit: Option<Complex<f64>>,
+    sample_count: Option<usize>,
+    samples: usize,
+    seed: u64,
+    raw: Option<String>,
+    raw16: bool,
+    checkpoint: Option<String>,
+    resume: Option<String>,
+    crop: Option<((usize, usize), (usize, usize))>,
+    mask: Option<String>,
+    fill: u8,
+    keep_aspect: bool,
+    scale: usize,
+    grid: Option<f64>,
+    seamless: Option<usize>,
+    baseline: Option<String>,
+    threshold: usize,
+    max_memory: usize,
+    iterations_map: Option<String>,
+    mmap: bool,
+    histogram: bool,
+    progress: bool,
+    stats: bool,
+    profile: bool,
+    save_partial: bool,
+    chunk_order: ChunkOrder,
+    dry_run: bool,
+    ascii: bool,
+    interactive: bool,
+    animation: Option<(Complex<f64>, Complex<f64>, usize)>,
+    zoom_animation: Option<(Complex<f64>, f64, usize)>,
+    palette_cycle: Option<usize>,
+    center: Option<Complex<f64>>,
+    zoom: Option<f64>,
+    region_autofocus: bool,
+    candidates: usize,
+    auto: bool,
+    per_decade: f64,
+    base_iterations: usize,
+    schedule: Option<IterationSchedule>,
+    prefix: String,
+    tile_size: Option<usize>,
+    serve: Option<String>,
+    cache_tiles: usize,
+    threads: usize
+}
+
+impl Options {
+
+    // An output and bounds with both corners.
+    fn positional(&self) -> bool {
+        self.args.len() == 5 && self.center.is_none() && self.zoom.is_none()
+    }
 
+    // An output and bounds around --center or a preset.
+    fn centered(&self) -> bool {
+        self.args.len() == 3 && self.center.is_some()
+    }
+
+    // Views framed in double-double precision straight from the center.
+    fn deep(&self) -> bool {
+        self.config.precision == Precision::DoubleDouble || self.perturbation
+    }
+
+    fn modes(&self) -> Vec<Mode> {
+        [(self.animation.is_some(), Mode::JuliaAnimation),
+         (self.zoom_animation.is_some(), Mode::ZoomAnimation),
+         (self.palette_cycle.is_some(), Mode::PaletteCycle),
+         (self.crop.is_some(), Mode::Crop),
+         (self.mask.is_some(), Mode::Mask),
+         (self.adaptive.is_some(), Mode::Adaptive),
+         (self.histogram, Mode::Histogram),
+         (self.progress, Mode::Progress),
+         (self.stats, Mode::Stats),
+         (self.profile, Mode::Profile),
+         (self.chunk_order != ChunkOrder::Rows, Mode::ChunkOrder),
+         (self.save_partial, Mode::SavePartial),
+         (self.scale > 1, Mode::Scale),
+         (self.tile_size.is_some(), Mode::TileSize),
+         (self.checkpoint.is_some(), Mode::Checkpoint),
+         (self.resume.is_some(), Mode::Resume),
+         (self.raw.is_some(), Mode::Raw),
+         (self.raw16, Mode::Raw16),
+         (self.iterations_map.is_some(), Mode::IterationsMap),
+         (self.precision_diff, Mode::PrecisionDiff),
+         (self.buddhabrot, Mode::Buddhabrot),
+         (self.antibuddhabrot, Mode::Antibuddhabrot),
+         (self.nebulabrot.is_some(), Mode::Nebulabrot),
+         (self.seamless.is_some(), Mode::Seamless),
+         (self.grid.is_some(), Mode::Grid),
+         (self.baseline.is_some(), Mode::Compare),
+         (self.interactive, Mode::Interactive),
+         (self.dry_run, Mode::DryRun),
+         (self.to_json, Mode::ToJson),
+         (self.config.dither, Mode::Dither),
+         (self.auto, Mode::AutoIterations),
+         (self.depth, Mode::Depth),
+         (self.depth_auto, Mode::ColorDepthAuto),
+         (self.perturbation, Mode::Perturbation),
+         (self.config.precision == Precision::DoubleDouble, Mode::DeepPrecision)]
+            .iter()
+            .filter(|(active, _)| *active)
+            .map(|(_, mode)| *mode)
+            .collect()
+    }
+
+}
+
+// The flags that change what is rendered or written, checked against each
+// other through `CONFLICTS`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    JuliaAnimation,
+    ZoomAnimation,
+    PaletteCycle,
+    Crop,
+    Mask,
+    Adaptive,
+    Histogram,
+    Progress,
+    Stats,
+    Profile,
+    ChunkOrder,
+    SavePartial,
+    Scale,
+    TileSize,
+    Checkpoint,
+    Resume,
+    Raw,
+    Raw16,
+    IterationsMap,
+    PrecisionDiff,
+    Buddhabrot,
+    Antibuddhabrot,
+    Nebulabrot,
+    Seamless,
+    Grid,
+    Compare,
+    Interactive,
+    DryRun,
+    ToJson,
+    Dither,
+    AutoIterations,
+    Depth,
+    ColorDepthAuto,
+    Perturbation,
+    DeepPrecision
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mode::JuliaAnimation => "--julia-animation",
+            Mode::ZoomAnimation => "--zoom-animation",
+            Mode::PaletteCycle => "--palette-cycle",
+            Mode::Crop => "--crop",
+            Mode::Mask => "--mask",
+            Mode::Adaptive => "--antialias-adaptive",
+            Mode::Histogram => "--histogram",
+            Mode::Progress => "--progress",
+            Mode::Stats => "--stats",
+            Mode::Profile => "--profile",
+            Mode::ChunkOrder => "--chunk-order hilbert or tiles",
+            Mode::SavePartial => "--save-partial",
+            Mode::Scale => "--scale above 1",
+            Mode::TileSize => "--tile-size",
+            Mode::Checkpoint => "--checkpoint",
+            Mode::Resume => "--resume",
+            Mode::Raw => "--raw",
+            Mode::Raw16 => "--format raw16",
+            Mode::IterationsMap => "--iterations-map",
+            Mode::PrecisionDiff => "--precision-diff",
+            Mode::Buddhabrot => "--buddhabrot",
+            Mode::Antibuddhabrot => "--antibuddhabrot",
+            Mode::Nebulabrot => "--nebulabrot",
+            Mode::Seamless => "--seamless-tile",
+            Mode::Grid => "--grid",
+            Mode::Compare => "--compare",
+            Mode::Interactive => "--interactive",
+            Mode::DryRun => "--dry-run",
+            Mode::ToJson => "--to-json",
+            Mode::Dither => "--dither",
+            Mode::AutoIterations => "--auto-iterations",
+            Mode::Depth => "--depth",
+            Mode::ColorDepthAuto => "--color-depth-auto",
+            Mode::Perturbation => "--perturbation",
+            Mode::DeepPrecision => "--precision above 53 bits"
+        })
+    }
+}
+
+// Animations, each writing a directory of frames.
+const FRAMES: &[Mode] = &[Mode::JuliaAnimation, Mode::ZoomAnimation, Mode::PaletteCycle];
+
+// Outputs written by their own path instead of the colored render.
+const OUTPUTS: &[Mode] = &[Mode::Raw16, Mode::PaletteCycle, Mode::PrecisionDiff, Mode::Buddhabrot, Mode::Antibuddhabrot, Mode::Nebulabrot,
+                           Mode::TileSize, Mode::Checkpoint, Mode::Resume];
+
+// Ways of rendering the colored image, of which only one can run.
+const RENDERERS: &[Mode] = &[Mode::Crop, Mode::Mask, Mode::Adaptive, Mode::Histogram, Mode::Progress, Mode::Stats, Mode::Profile,
+                             Mode::ChunkOrder, Mode::SavePartial];
+
+const DEEP: &[Mode] = &[Mode::Perturbation, Mode::DeepPrecision];
+
+// Pairs of mode lists where no mode of the first can be combined with any
+// mode of the second, in either order.
+const CONFLICTS: &[(&[Mode], &[Mode])] = &[
+    (FRAMES, FRAMES),
+    (OUTPUTS, OUTPUTS),
+    (RENDERERS, RENDERERS),
+    (OUTPUTS, RENDERERS),
+    (OUTPUTS, &[Mode::JuliaAnimation, Mode::ZoomAnimation, Mode::Scale, Mode::Seamless, Mode::Grid, Mode::Compare]),
+    (FRAMES, &[Mode::SavePartial, Mode::Seamless, Mode::Grid, Mode::Compare, Mode::Raw, Mode::IterationsMap, Mode::Interactive]),
+    (&[Mode::Scale], &[Mode::Crop, Mode::Mask]),
+    (&[Mode::Interactive], &[Mode::DryRun, Mode::Raw, Mode::Raw16, Mode::IterationsMap, Mode::PrecisionDiff, Mode::Mask, Mode::SavePartial]),
+    (&[Mode::Dither], &[Mode::Histogram, Mode::PaletteCycle, Mode::Perturbation]),
+    (&[Mode::AutoIterations], &[Mode::ZoomAnimation]),
+    (&[Mode::Depth], &[Mode::ColorDepthAuto]),
+    (DEEP, FRAMES),
+    (DEEP, OUTPUTS),
+    (DEEP, RENDERERS),
+    (DEEP, &[Mode::Scale, Mode::Seamless, Mode::Grid, Mode::Compare, Mode::Raw, Mode::IterationsMap, Mode::Interactive, Mode::ToJson])
+];
+
+// The first pair of active modes that cannot be combined, in the order given.
+fn conflict(modes: &[Mode]) -> Option<(Mode, Mode)> {
+    let conflicting = |first: Mode, second: Mode| CONFLICTS.iter().any(|(left, right)| {
+        (left.contains(&first) && right.contains(&second)) || (left.contains(&second) && right.contains(&first))
+    });
+    modes.iter()
+         .enumerate()
+         .flat_map(|(index, first)| modes[index + 1..].iter().map(move |second| (*first, *second)))
+         .find(|(first, second)| conflicting(*first, *second))
+}
+
+// Takes every flag out of the arguments, leaving the positional ones.
+fn parse(mut args: Vec<String>) -> Result<Options, Box<dyn Error>> {
+
+    let verbose = take_switch(&mut args, "--verbose");
+    let quiet = take_switch(&mut args, "--quiet");
+    let logger = Logger {
+        level: match (quiet, verbose) {
+            (true, _) => Level::Error,
+            (false, true) => Level::Debug,
+            (false, false) => env::var("RUST_LOG").ok().and_then(|level| level.parse().ok()).unwrap_or(Level::Warn)
+        }
+    };
+
+    let file = match take_flag(&mut args, "--config") {
+        None => None,
+        Some(path) => Some(RenderConfig::load(&path)?)
+    };
+
+    // The view of an earlier render, printed on its own or rendered again
+    // into the given output.
+    let from_image = take_flag(&mut args, "--bounds-from-image");
+
+    // A whole config as JSON, which the other flags still override.
+    let json = match take_flag(&mut args, "--from-json") {
+        None => None,
+        Some(path) => Some(JsonConfig::load(&path)?)
+    };
+    if json.is_some() && (file.is_some() || from_image.is_some()) {
+        return Err("--from-json cannot be combined with --config or --bounds-from-image".into());
+    }
+    let to_json = take_switch(&mut args, "--to-json");
+
+    let file = match (&from_image, file) {
+        (None, file) => file,
+        (Some(_), Some(_)) => return Err("--bounds-from-image and --config cannot be combined".into()),
+        (Some(path), None) => {
+            let mut file = RenderConfig::from_image(path)?;
+            match args.len() {
+                1 => {},
+                2 => file.output = Some(args.remove(1)),
+                _ => return Err("--bounds-from-image takes an output and no bounds or corners".into())
             }
-        }).unwrap();
+            Some(file)
+        }
+    };
+
+    let (file, mut config) = match json {
+        None => (file, Config::default()),
+        Some(json) => (Some(RenderConfig { output: json.output, bounds: json.bounds, upper_left: json.upper_left, lower_right: json.lower_right, ..RenderConfig::default() }), json.config)
+    };
+    if let Some(file) = &file {
+        config.limit = file.iterations.unwrap_or(config.limit);
+        config.palette = file.palette.unwrap_or(config.palette);
+    }
+    let preset = match take_flag(&mut args, "--preset") {
+        None => None,
+        Some(name) => Some(find_preset(&name)?)
+    };
+    if let Some(preset) = preset {
+        config.limit = preset.iterations;
+    }
+    if let Some(limit) = take_flag(&mut args, "--iterations") {
+        config.limit = parse_arg(&limit, "--iterations")?;
+    }
+    if let Some(c) = take_flag(&mut args, "--julia") {
+        config.fractal = Fractal::Julia(parse_complex_arg(&c, "--julia")?);
+    }
+    if take_switch(&mut args, "--burning-ship") {
+        config.fractal = Fractal::BurningShip;
+    }
+    if take_switch(&mut args, "--newton") {
+        config.fractal = Fractal::Newton;
+    }
+    if take_switch(&mut args, "--smooth") {
+        config.coloring = Coloring::Smooth;
+    }
+    if take_switch(&mut args, "--distance") {
+        config.coloring = Coloring::Distance;
+    }
+    if let Some(trap) = take_flag(&mut args, "--trap") {
+        config.coloring = Coloring::OrbitTrap(trap.parse()?);
+    }
+    if let Some(density) = take_flag(&mut args, "--stripe") {
+        config.coloring = Coloring::Stripe(parse_arg(&density, "--stripe")?);
+    }
+    if take_switch(&mut args, "--normal-map") {
+        config.coloring = Coloring::Lighting(Light::default());
+    }
+    if let Some(light) = take_flag(&mut args, "--lighting") {
+        config.coloring = Coloring::Lighting(light.parse()?);
+    }
+    if let Some(palette) = take_flag(&mut args, "--palette") {
+        config.palette = palette.parse()?;
+    }
+    if let Some(name) = take_flag(&mut args, "--colormap") {
+        if !Palette::COLORMAPS.contains(&name.as_str()) {
+            return Err(format!("Unknown colormap {}, expected one of {}", name, Palette::COLORMAPS.join(", ")).into());
+        }
+        config.palette = name.parse()?;
+    }
+    if let Some(path) = take_flag(&mut args, "--gradient") {
+        config.palette = Palette::Gradient(Gradient::load(&path)?);
+    }
+    if let Some(samples) = take_flag(&mut args, "--supersample") {
+        config.supersample = parse_arg(&samples, "--supersample")?;
+    }
+    if let Some(threshold) = take_flag(&mut args, "--supersample-threshold") {
+        config.supersample_threshold = parse_arg(&threshold, "--supersample-threshold")?;
+    }
+    if let Some(sampling) = take_flag(&mut args, "--sampling") {
+        config.sampling = sampling.parse()?;
+    }
+    if take_switch(&mut args, "--f32") {
+        config.precision = Precision::Single;
+    }
+    if let Some(bits) = take_flag(&mut args, "--precision") {
+        config.precision = Precision::from_bits(parse_arg(&bits, "--precision")?)?;
+    }
+    if let Some(power) = take_flag(&mut args, "--power") {
+        config.power = parse_arg(&power, "--power")?;
+    }
+    if let Some(bailout) = take_flag(&mut args, "--bailout") {
+        config.bailout = parse_arg(&bailout, "--bailout")?;
+    }
+    if let Some(bias) = take_flag(&mut args, "--bailout-smooth-bias") {
+        config.smooth_bias = parse_arg(&bias, "--bailout-smooth-bias")?;
+    }
+    let depth = take_flag(&mut args, "--depth");
+    if let Some(depth) = &depth {
+        config.depth = parse_arg(depth, "--depth")?;
+    }
+    let depth_auto = take_switch(&mut args, "--color-depth-auto");
+    let adaptive = match take_flag(&mut args, "--antialias-adaptive") {
+        None => None,
+        Some(threshold) => Some(parse_arg::<usize>(&threshold, "--antialias-adaptive")?)
+    };
+    let perturbation = take_switch(&mut args, "--perturbation");
+    let precision_diff = take_switch(&mut args, "--precision-diff");
+    let buddhabrot = take_switch(&mut args, "--buddhabrot");
+    let antibuddhabrot = take_switch(&mut args, "--antibuddhabrot");
+    let nebulabrot = match take_flag(&mut args, "--nebulabrot") {
+        None => None,
+        Some(limits) => Some(parse_channels(&limits, "--nebulabrot")?)
+    };
+    let nebula_samples = match take_flag(&mut args, "--nebula-samples") {
+        None => None,
+        Some(samples) => Some(parse_channels(&samples, "--nebula-samples")?)
+    };
+    let seed_orbit = match take_flag(&mut args, "--seed-orbit") {
+        None => None,
+        Some(point) => Some(parse_complex_arg(&point, "--seed-orbit")?)
+    };
+    let sample_count = match take_flag(&mut args, "--sample-count") {
+        None => None,
+        Some(count) => Some(parse_arg::<usize>(&count, "--sample-count")?)
+    };
+    let samples = match take_flag(&mut args, "--samples") {
+        None => 1_000_000,
+        Some(samples) => parse_arg::<usize>(&samples, "--samples")?
+    };
+    let seed = match take_flag(&mut args, "--seed") {
+        None => None,
+        Some(seed) => Some(parse_arg::<u64>(&seed, "--seed")?)
+    };
+    if let Some(seed) = seed {
+        config.sampling = config.sampling.with_seed(seed);
+    }
+    let seed = seed.unwrap_or(0);
+    let raw = take_flag(&mut args, "--raw");
+    let raw16 = match take_flag(&mut args, "--format").as_deref() {
+        None => false,
+        Some("raw16") => true,
+        Some(format) => return Err(format!("Unknown --format {}, expected raw16", format).into())
+    };
+    let checkpoint = take_flag(&mut args, "--checkpoint");
+    let resume = take_flag(&mut args, "--resume");
+    config.invert |= take_switch(&mut args, "--invert");
+    config.transparent |= take_switch(&mut args, "--transparent-interior");
+    config.mirror |= take_switch(&mut args, "--mirror");
+    config.log_scale |= take_switch(&mut args, "--log-scale");
+    config.dither |= take_switch(&mut args, "--dither");
+    if let Some(gamma) = take_flag(&mut args, "--gamma") {
+        config.gamma = parse_arg(&gamma, "--gamma")?;
+    }
+    if let Some(interior) = take_flag(&mut args, "--interior") {
+        config.interior = parse_color(&interior).ok_or_else(|| format!("Invalid --interior color '{}', expected RRGGBB like ffffff or a gray byte like 255", interior))?;
+    }
+    let crop = match take_flag(&mut args, "--crop") {
+        None => None,
+        Some(crop) => Some(parse_crop(&crop).ok_or_else(|| format!("Invalid --crop value '{}', expected x,y,width,height like 100,50,320,240", crop))?)
+    };
+    let mask = take_flag(&mut args, "--mask");
+    let fill = match take_flag(&mut args, "--fill") {
+        None => 0,
+        Some(fill) => parse_arg::<u8>(&fill, "--fill")?
+    };
+    let keep_aspect = take_switch(&mut args, "--keep-aspect");
+    let scale = match take_flag(&mut args, "--scale") {
+        None => 1,
+        Some(scale) => parse_arg::<usize>(&scale, "--scale")?
+    };
+    let grid = match take_flag(&mut args, "--grid") {
+        None => None,
+        Some(spacing) => Some(parse_arg::<f64>(&spacing, "--grid")?)
+    };
+    let seamless = match take_flag(&mut args, "--seamless-tile") {
+        None => None,
+        Some(blend) => Some(parse_arg::<usize>(&blend, "--seamless-tile")?)
+    };
+    let baseline = take_flag(&mut args, "--compare");
+    let threshold = match take_flag(&mut args, "--threshold") {
+        None => 0,
+        Some(pixels) => parse_arg::<usize>(&pixels, "--threshold")?
+    };
+    let max_memory = match take_flag(&mut args, "--max-memory") {
+        None => DEFAULT_MAX_MEMORY,
+        Some(megabytes) => parse_arg::<usize>(&megabytes, "--max-memory")?
+    };
+    let iterations_map = take_flag(&mut args, "--iterations-map");
+    let mmap = take_switch(&mut args, "--mmap");
+    let histogram = take_switch(&mut args, "--histogram");
+    let progress = take_switch(&mut args, "--progress");
+    let stats = take_switch(&mut args, "--stats");
+    let profile = take_switch(&mut args, "--profile");
+    let save_partial = take_switch(&mut args, "--save-partial");
+    let chunk_order = match take_flag(&mut args, "--chunk-order") {
+        None => ChunkOrder::Rows,
+        Some(order) => order.parse()?
+    };
+    let dry_run = take_switch(&mut args, "--dry-run");
+    let ascii = take_switch(&mut args, "--preview");
+    let interactive = take_switch(&mut args, "--interactive");
+    let animation = match take_flag_values(&mut args, "--julia-animation", 3) {
+        None => None,
+        Some(values) if values.len() == 3 => Some((parse_complex_arg(&values[0], "--julia-animation start")?,
+                                                   parse_complex_arg(&values[1], "--julia-animation end")?,
+                                                   parse_arg::<usize>(&values[2], "--julia-animation frames")?)),
+        Some(_) => return Err("--julia-animation expects a start constant, an end constant and a frame count".into())
+    };
+    let zoom_animation = match take_flag_values(&mut args, "--zoom-animation", 3) {
+        None => None,
+        Some(values) if values.len() == 3 => Some((parse_complex_arg(&values[0], "--zoom-animation center")?,
+                                                   parse_arg::<f64>(&values[1], "--zoom-animation factor")?,
+                                                   parse_arg::<usize>(&values[2], "--zoom-animation frames")?)),
+        Some(_) => return Err("--zoom-animation expects a center, a zoom factor per frame and a frame count".into())
+    };
+    let palette_cycle = match take_flag(&mut args, "--palette-cycle") {
+        None => None,
+        Some(frames) => Some(parse_arg::<usize>(&frames, "--palette-cycle")?)
+    };
+    let center = match take_flag(&mut args, "--center") {
+        None => preset.map(|preset| preset.center),
+        Some(center) => Some(parse_complex_arg(&center, "--center")?)
+    };
+    let zoom = match take_flag(&mut args, "--zoom") {
+        None => preset.map(|preset| preset.zoom),
+        Some(zoom) => Some(parse_arg::<f64>(&zoom, "--zoom")?)
+    };
+    let region_autofocus = take_switch(&mut args, "--region-autofocus");
+    let candidates = match take_flag(&mut args, "--candidates") {
+        None => 64,
+        Some(count) => parse_arg::<usize>(&count, "--candidates")?
+    };
+    let auto = take_switch(&mut args, "--auto-iterations");
+    let per_decade = match take_flag(&mut args, "--iterations-per-decade") {
+        None => 100.0,
+        Some(count) => parse_arg::<f64>(&count, "--iterations-per-decade")?
+    };
+    let base_iterations = match take_flag(&mut args, "--base-iterations") {
+        None => config.limit,
+        Some(limit) => parse_arg(&limit, "--base-iterations")?
+    };
+    let schedule = match take_flag(&mut args, "--iterations-per-frame-schedule") {
+        None => None,
+        Some(schedule) => Some(schedule.parse::<IterationSchedule>()?)
+    };
+    let prefix = take_flag(&mut args, "--frame-prefix").unwrap_or_else(|| "frame".to_string());
+    let tile_size = match take_flag(&mut args, "--tile-size") {
+        None => None,
+        Some(size) => Some(parse_arg::<usize>(&size, "--tile-size")?)
+    };
+    let serve = take_flag(&mut args, "--serve");
+    let cache_tiles = match take_flag(&mut args, "--cache-tiles") {
+        None => 256,
+        Some(count) => parse_arg::<usize>(&count, "--cache-tiles")?
+    };
+    let threads = match take_flag(&mut args, "--threads") {
+        None => std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+        Some(threads) => parse_arg::<usize>(&threads, "--threads")?
+    };
 
+    // Whatever flags are left were not recognized. Negative coordinates start
+    // with a single dash, so only double dashes are checked.
+    if let Some(flag) = args.iter().skip(1).find(|arg| arg.starts_with("--")) {
+        return Err(format!("Unknown flag {}, see --help", flag).into());
     }
 
-    write_image(&args[1], &pixels, bounds).expect("Error while writing image");
+    Ok(Options {
+        args, logger, file, from_image, to_json, config, depth: depth.is_some(), depth_auto, adaptive, perturbation, precision_diff,
+        buddhabrot, antibuddhabrot, nebulabrot, nebula_samples, seed_orbit, sample_count, samples, seed, raw, raw16, checkpoint,
+        resume, crop, mask, fill, keep_aspect, scale, grid, seamless, baseline, threshold, max_memory, iterations_map, mmap,
+        histogram, progress, stats, profile, save_partial, chunk_order, dry_run, ascii, interactive, animation, zoom_animation,
+        palette_cycle, center, zoom, region_autofocus, candidates, auto, per_decade, base_iterations, schedule, prefix, tile_size,
+        serve, cache_tiles, threads
+    })
 
 }
 
-fn escape_time(c: Complex<f64>, limit: usize) -> Option<usize> {
-    let mut z = Complex { re: 0.0, im: 0.0 };
-    for i in 0..limit {
-        if z.norm_sqr() > 4.0 {
-            return Some(i);
+// Checks the flags against each other and their values on their own, before
+// anything is rendered or written. What depends on the resolved view is left
+// to `execute`.
+fn validate(options: &Options) -> Result<(), Box<dyn Error>> {
+
+    let config = &options.config;
+
+    if let Some((first, second)) = conflict(&options.modes()) {
+        return Err(format!("{} cannot be combined with {}", first, second).into());
+    }
+
+    if options.nebula_samples.is_some() && options.nebulabrot.is_none() {
+        return Err("--nebula-samples only applies to --nebulabrot".into());
+    }
+    if options.schedule.is_some() && options.zoom_animation.is_none() {
+        return Err("--iterations-per-frame-schedule only applies to --zoom-animation".into());
+    }
+    if !(config.gamma.is_finite() && config.gamma > 0.0) {
+        return Err(format!("--gamma must be positive, got {}", config.gamma).into());
+    }
+    if !(options.per_decade.is_finite() && options.per_decade >= 0.0) {
+        return Err(format!("--iterations-per-decade must not be negative, got {}", options.per_decade).into());
+    }
+    if let Some((_, factor, _)) = options.zoom_animation {
+        if factor.is_nan() || factor <= 0.0 {
+            return Err(format!("--zoom-animation factor must be positive, got {}", factor).into());
+        }
+    }
+    if options.palette_cycle == Some(0) {
+        return Err("--palette-cycle needs at least one frame".into());
+    }
+    if options.adaptive.is_some() && config.supersample < 2 {
+        return Err("--antialias-adaptive needs --supersample 2 or more for the refined pixels".into());
+    }
+    if let Some(spacing) = options.grid {
+        if !(spacing.is_finite() && spacing > 0.0) {
+            return Err(format!("--grid spacing must be positive, got {}", spacing).into());
+        }
+    }
+    if (options.buddhabrot || options.antibuddhabrot || options.nebulabrot.is_some()) && config.fractal != Fractal::Mandelbrot {
+        return Err("--buddhabrot, --antibuddhabrot and --nebulabrot only support the Mandelbrot set".into());
+    }
+
+    // Printing an orbit or estimating the area takes no output or bounds.
+    if options.seed_orbit.is_some() {
+        if options.args.len() != 1 || config.fractal == Fractal::Newton {
+            return Err("--seed-orbit takes no output or bounds and does not support --newton".into());
+        }
+        return Ok(());
+    }
+    if let Some(count) = options.sample_count {
+        if options.args.len() != 1 || count == 0 || config.limit == 0 || config.fractal == Fractal::Newton {
+            return Err("--sample-count needs a positive count, takes no output or bounds and does not support --newton".into());
+        }
+        return Ok(());
+    }
+
+    let described = options.positional() || options.centered() || (options.args.len() == 1 && (options.file.is_some() || options.serve.is_some()));
+    if !described || config.limit == 0 || config.supersample == 0 || config.supersample_threshold.is_nan() || config.supersample_threshold < 0.0 || config.power < 2 || config.bailout.is_nan() || config.bailout < 2.0 || options.tile_size == Some(0) || options.scale == 0 || options.threads == 0 || options.base_iterations == 0 || (config.depth != 8 && config.depth != 16) {
+        eprint!("{}", usage(&options.args[0]));
+        std::process::exit(1);
+    }
+
+    // Tiles are framed by their own coordinates, so serving takes no output
+    // or bounds.
+    if options.serve.is_some() {
+        if options.args.len() != 1 || options.cache_tiles == 0 {
+            return Err("--serve takes no output or bounds, tiles are requested as /z/x/y.png, and --cache-tiles must be positive".into());
         }
-        z = z * z + c;
+        return Ok(());
     }
-    None
+
+    if let Some(zoom) = options.zoom {
+        if zoom.is_nan() || zoom <= 0.0 {
+            return Err(format!("--zoom must be positive, got {}", zoom).into());
+        }
+    }
+    if options.deep() && !options.centered() {
+        return Err("--perturbation and --precision above 53 bits only support a single image framed with --center or --preset".into());
+    }
+
+    Ok(())
+
 }
 
-fn parse_pair<T: FromStr>(s: &str, separator: char) -> Option<(T, T)> {
-    
-    match s.find(separator) {
+// Renders and writes what the validated options ask for.
+fn execute(options: Options) -> Result<(), Box<dyn Error>> {
+
+    let positional = options.positional();
+    let centered = options.centered();
+    let deep = options.deep();
+    let Options {
+        args, logger, file, from_image, to_json, mut config, depth: _, depth_auto, adaptive, perturbation, precision_diff,
+        buddhabrot, antibuddhabrot, nebulabrot, nebula_samples, seed_orbit, sample_count, samples, seed, raw, raw16, checkpoint,
+        resume, crop, mask, fill, keep_aspect, scale, grid, seamless, baseline, threshold, max_memory, iterations_map, mmap,
+        histogram, progress, stats, profile, save_partial, chunk_order, dry_run, ascii, interactive, animation, zoom_animation,
+        palette_cycle, center, zoom, region_autofocus, candidates, auto, per_decade, base_iterations, schedule, prefix, tile_size,
+        serve, cache_tiles, threads
+    } = options;
+
+    // The variants share the output path of the Buddhabrot.
+    let buddhabrot = buddhabrot || antibuddhabrot || nebulabrot.is_some();
+
+    if let (Some(_), Some(file), 1) = (&from_image, &file, args.len()) {
+        print!("{}", file);
+        return Ok(());
+    }
+
+    // The orbit of a single point is printed as CSV, starting from its initial
+    // value : 0 for c in the Mandelbrot set, the point itself for Julia sets.
+    if let Some(point) = seed_orbit {
+        let start = match config.fractal {
+            Fractal::Julia(_) => point,
+            _ => Complex { re: 0.0, im: 0.0 }
+        };
+        println!("step,re,im,norm_sqr");
+        for (step, z) in std::iter::once(start).chain(config.fractal.orbit(point, &config)).enumerate() {
+            println!("{},{},{},{}", step, z.re, z.im, z.norm_sqr());
+        }
+        return Ok(());
+    }
+
+    // The set's area is estimated over a box holding all of it.
+    if let Some(count) = sample_count {
+        let (upper_left, lower_right) = area_bounds(&config);
+        let start = Instant::now();
+        let estimate = estimate_area(upper_left, lower_right, config, count, seed);
+        logger.log(Level::Debug, format_args!("Sampled {} to {} in {:.3?}", upper_left, lower_right, start.elapsed()));
+        println!("Area : {}", estimate);
+        return Ok(());
+    }
+
+    if let Some(address) = serve {
+        return serve_tiles(&address, config, cache_tiles, &logger);
+    }
+
+    let zoom = zoom.unwrap_or(1.0);
+
+    // Deep views are framed in double-double precision straight from the
+    // center, since their corners may not be representable as f64.
+    if deep {
+
+        let (output, bounds) = (&args[1], parse_bounds(&args[2])?);
+        if auto {
+            config.limit = auto_iterations(4.0 / zoom, config.limit, per_decade);
+        }
+        if depth_auto {
+            config.depth = auto_depth(config.limit);
+        }
+        logger.log(Level::Info, format_args!("Rendering {:?} into {} at {}x{}", config.fractal, output, bounds.0, bounds.1));
+        logger.log(Level::Info, format_args!("Center {} at zoom {:e} in double-double precision", center.unwrap(), zoom));
+
+        if dry_run {
+            print!("{}", plan(bounds, &config, &[config.limit]));
+            return Ok(());
+        }
+
+        check_memory(bounds, config.bytes_per_pixel(), max_memory)?;
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()?;
+
+        let start = Instant::now();
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        if perturbation {
+            let glitches = render_perturbation(&mut pixels, bounds, center.unwrap(), zoom, config);
+            logger.log(Level::Debug, format_args!("Rendered in {:.3?}, recomputing {} glitched pixels", start.elapsed(), glitches));
+        } else {
+            render_deep(&mut pixels, bounds, center.unwrap(), zoom, config);
+            logger.log(Level::Debug, format_args!("Rendered in {:.3?}", start.elapsed()));
+        }
+
+        write_image(output, &pixels, bounds, config.color_type())
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+
+        return Ok(());
+
+    }
+
+    let (output, bounds, upper_left, lower_right) = if centered {
+        let bounds = parse_bounds(&args[2])?;
+        let (upper_left, lower_right) = view_from_center(bounds, center.unwrap(), zoom);
+        (args[1].clone(), bounds, upper_left, lower_right)
+    } else if positional {
+        (args[1].clone(),
+         parse_bounds(&args[2])?,
+         parse_complex_arg(&args[3], "upper left corner")?,
+         parse_complex_arg(&args[4], "lower right corner")?)
+    } else {
+        let file = file.unwrap_or_default();
+        (file.output.ok_or("Config is missing output")?,
+         file.bounds.ok_or("Config is missing bounds")?,
+         file.upper_left.ok_or("Config is missing upper_left")?,
+         file.lower_right.ok_or("Config is missing lower_right")?)
+    };
+
+    let lower_right = if keep_aspect { fit_aspect(bounds, upper_left, lower_right) } else { lower_right };
+
+    validate_bounds(bounds, upper_left, lower_right)?;
+
+    // The given view is only where candidate windows are searched for.
+    let (upper_left, lower_right) = if region_autofocus {
+        let start = Instant::now();
+        let view = autofocus(bounds, upper_left, lower_right, config, candidates, seed);
+        logger.log(Level::Debug, format_args!("Scored {} candidate regions in {:.3?}", candidates, start.elapsed()));
+        view
+    } else {
+        (upper_left, lower_right)
+    };
+
+    // The limit given with --iterations is the base for the widest views.
+    if auto {
+        config.limit = auto_iterations(lower_right.re - upper_left.re, config.limit, per_decade);
+    }
+    // Picked once the limit is final, so deeper views get the finer levels.
+    if depth_auto {
+        config.depth = auto_depth(config.limit);
+    }
+
+    // The config as resolved so far, which renders the same image when read
+    // back with --from-json and the same mode flags.
+    if to_json {
+        let json = JsonConfig { output: Some(output), bounds: Some(bounds), upper_left: Some(upper_left), lower_right: Some(lower_right), config };
+        println!("{}", json);
+        return Ok(());
+    }
+
+    let distortion = aspect_distortion(bounds, upper_left, lower_right);
+    if (distortion - 1.0).abs() > 0.01 {
+        logger.log(Level::Warn, format_args!("the region is stretched by {:.3} horizontally to fit {}x{}, use --keep-aspect to avoid it", distortion, bounds.0, bounds.1));
+    }
+
+    logger.log(Level::Info, format_args!("Rendering {:?} into {} at {}x{}", config.fractal, output, bounds.0, bounds.1));
+    logger.log(Level::Info, format_args!("Upper left {}, lower right {}", upper_left, lower_right));
+    logger.log(Level::Info, format_args!("{} iterations on {} threads", config.limit, threads));
+
+    // Each frame of a zoom animation has its own limit, from the schedule when
+    // one is given, running from --base-iterations up to --iterations.
+    let zoom_limits = match (zoom_animation, &schedule) {
+        (Some((_, factor, frames)), None) => (0..frames).map(|frame| zoom_frame_limit(base_iterations, factor, frame)).collect(),
+        (Some((_, _, frames)), Some(schedule)) => schedule.limits(base_iterations, config.limit, frames)?,
+        (None, _) => Vec::new()
+    };
+
+    let frames = animation.is_some() || zoom_animation.is_some() || palette_cycle.is_some();
+    let lowercase = output.to_ascii_lowercase();
+    if config.transparent && !frames && !lowercase.ends_with(".png") && !lowercase.ends_with(".webp") {
+        return Err("--transparent-interior needs a .png or .webp output".into());
+    }
+    if tile_size.is_some() && !lowercase.ends_with(".png") {
+        return Err("--tile-size only supports writing a .png image".into());
+    }
+    if interactive && output == "-" {
+        return Err("--interactive only supports exploring a single image written to a file".into());
+    }
+    if (grid.is_some() || baseline.is_some()) && config.depth != 8 {
+        return Err("--grid and --compare only support 8 bit images".into());
+    }
+
+    if let Some((origin, size)) = crop {
+        if origin.0 + size.0 > bounds.0 || origin.1 + size.1 > bounds.1 {
+            return Err(format!("--crop window {}x{} at {},{} does not fit in {}x{}", size.0, size.1, origin.0, origin.1, bounds.0, bounds.1).into());
+        }
+    }
+
+    let mask = match mask {
         None => None,
-        Some(index) => {
-            match (T::from_str(&s[..index]), T::from_str(&s[index + 1..])) {
-                (Ok(x), Ok(y)) => Some((x, y)),
-                _ => None
+        Some(filename) => Some(load_mask(&filename, bounds)?)
+    };
+
+    // Rendered at `scale` times the output size, then filtered down to it.
+    let render_bounds = (bounds.0 * scale, bounds.1 * scale);
+
+    // A thumbnail of the framing, kept off stdout where the image may go.
+    if ascii {
+        eprint!("{}", ascii_preview((80, 40), bounds, upper_left, lower_right, config));
+    }
+
+    if dry_run {
+        let limits: Vec<usize> = match (animation, zoom_animation) {
+            (Some((_, _, frames)), _) => vec![config.limit; frames],
+            (_, Some(_)) => zoom_limits.clone(),
+            _ => vec![config.limit]
+        };
+        print!("{}", plan(render_bounds, &config, &limits));
+        return Ok(());
+    }
+
+    // A plain .png render is encoded as it goes rather than held whole, unless
+    // its lower half is to be mirrored from the whole upper half, it is to be
+    // resized, overlaid with a grid or compared, or it is explicitly to be held
+    // in a mapped file.
+    let streamed = !mmap && !save_partial && grid.is_none() && seamless.is_none() && baseline.is_none() && !config.mirror && scale == 1 && animation.is_none() && zoom_animation.is_none() && crop.is_none() && mask.is_none() && adaptive.is_none() && !histogram && !progress && !stats && !profile && chunk_order == ChunkOrder::Rows && output.to_ascii_lowercase().ends_with(".png");
+
+    // Only the buffers held whole in memory count against --max-memory, not
+    // streamed images, tiles or mapped files.
+    let held = !raw16 && (palette_cycle.is_some() || precision_diff || buddhabrot
+                          || (tile_size.is_none() && (checkpoint.is_some() || resume.is_some() || (!streamed && !mmap))));
+    let counts = raw.is_some() || raw16 || palette_cycle.is_some() || histogram || buddhabrot;
+    let bytes_per_pixel = if held { config.bytes_per_pixel() } else { 0 } + if counts { std::mem::size_of::<Option<usize>>() } else { 0 };
+    check_memory(render_bounds, bytes_per_pixel, max_memory)?;
+
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()?;
+
+    let (upper_left, lower_right) = if interactive {
+        match explore(bounds, upper_left, lower_right, config)? {
+            Some(view) => view,
+            None => return Ok(())
+        }
+    } else {
+        (upper_left, lower_right)
+    };
+    if interactive {
+        logger.log(Level::Info, format_args!("Writing upper left {}, lower right {}", upper_left, lower_right));
+    }
+
+    // Where escape-time rendering spends its steps, written next to the image.
+    if let Some(path) = &iterations_map {
+        check_memory(bounds, 3, max_memory)?;
+        let start = Instant::now();
+        let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+        render_iterations_map(&mut pixels, bounds, upper_left, lower_right, config);
+        logger.log(Level::Debug, format_args!("Mapped executed iterations in {:.3?}", start.elapsed()));
+
+        write_image(path, &pixels, bounds, ColorType::RGB(8))
+            .map_err(|error| format!("Could not write {} : {}", path, error))?;
+    }
+
+    if let Some(raw) = &raw {
+
+        let start = Instant::now();
+        let mut counts = vec![None; bounds.0 * bounds.1];
+        render_counts(&mut counts, bounds, upper_left, lower_right, config);
+        logger.log(Level::Debug, format_args!("Counted escapes in {:.3?}", start.elapsed()));
+
+        write_counts(raw, &counts, bounds, config.limit)
+            .map_err(|error| format!("Could not write {} : {}", raw, error))?;
+
+    }
+
+    // The output holds the escape counts instead of an image.
+    if raw16 {
+
+        let start = Instant::now();
+        let mut counts = vec![None; bounds.0 * bounds.1];
+        render_counts(&mut counts, bounds, upper_left, lower_right, config);
+        logger.log(Level::Debug, format_args!("Counted escapes in {:.3?}", start.elapsed()));
+
+        write_raw16(&output, &counts, config.limit)
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+
+        return Ok(());
+
+    }
+
+    // The counts are computed once, then colored with the palette shifted a
+    // little further on each frame.
+    if let Some(frames) = palette_cycle {
+
+        let start = Instant::now();
+        let mut counts = vec![None; bounds.0 * bounds.1];
+        render_counts(&mut counts, bounds, upper_left, lower_right, config);
+        logger.log(Level::Debug, format_args!("Counted escapes in {:.3?}", start.elapsed()));
+
+        fs::create_dir_all(&output).map_err(|error| format!("Could not create frame directory {} : {}", output, error))?;
+
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        for frame in 0..frames {
+            render_cycle(&mut pixels, &counts, config, frame as f64 / frames as f64);
+
+            let path = frame_path(&output, &prefix, frame);
+            write_image(&path.to_string_lossy(), &pixels, bounds, config.color_type())
+                .map_err(|error| format!("Could not write {} : {}", path.display(), error))?;
+        }
+
+        return Ok(());
+
+    }
+
+    if precision_diff {
+
+        let start = Instant::now();
+        let mut pixels = vec![0; bounds.0 * bounds.1];
+        render_precision_diff(&mut pixels, bounds, upper_left, lower_right, config);
+        logger.log(Level::Debug, format_args!("Compared f32 and f64 in {:.3?}", start.elapsed()));
+
+        write_image(&output, &pixels, bounds, ColorType::Gray(8))
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+
+        return Ok(());
+
+    }
+
+    if buddhabrot {
+
+        let start = Instant::now();
+        let (pixels, color_type, traced) = if let Some(limits) = nebulabrot {
+            let samples = nebula_samples.unwrap_or([samples; 3]);
+            let densities = nebulabrot_density(bounds, upper_left, lower_right, config, [0, 1, 2].map(|channel| (limits[channel], samples[channel])), seed);
+            let mut pixels = vec![0; bounds.0 * bounds.1 * 3];
+            render_nebula(&mut pixels, &densities);
+            (pixels, ColorType::RGB(8), samples.iter().sum())
+        } else {
+            let density = if antibuddhabrot {
+                antibuddhabrot_density(bounds, upper_left, lower_right, config, samples, seed)
+            } else {
+                buddhabrot_density(bounds, upper_left, lower_right, config, samples, seed)
+            };
+            let mut pixels = vec![0; bounds.0 * bounds.1];
+            render_density(&mut pixels, &density);
+            (pixels, ColorType::Gray(8), samples)
+        };
+        logger.log(Level::Debug, format_args!("Traced {} samples in {:.3?}", traced, start.elapsed()));
+
+        write_image(&output, &pixels, bounds, color_type)
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+
+        return Ok(());
+
+    }
+
+    if let Some(tile_size) = tile_size {
+
+        let start = Instant::now();
+        write_tiled_png(&output, bounds, upper_left, lower_right, config, tile_size)
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+        logger.log(Level::Debug, format_args!("Rendered and wrote {} tiles in {:.3?}", tile_size, start.elapsed()));
+
+        return Ok(());
+
+    }
+
+    if let Some(path) = resume.as_ref().or(checkpoint.as_ref()) {
+
+        let mut state = Checkpoint::new(bounds, upper_left, lower_right, config);
+        if resume.is_some() {
+            state.resume(path)?;
+            logger.log(Level::Info, format_args!("Resuming {} with {} of {} rows done", path, state.completed_rows(), bounds.1));
+        }
+
+        let start = Instant::now();
+        while !state.is_complete() {
+            state.render_rows((bounds.1 / 20).max(1));
+            state.save(path).map_err(|error| format!("Could not write checkpoint {} : {}", path, error))?;
+            logger.log(Level::Debug, format_args!("Checkpointed {} of {} rows after {:.3?}", state.completed_rows(), bounds.1, start.elapsed()));
+        }
+
+        write_image(&output, state.pixels(), bounds, config.color_type())
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+        fs::remove_file(path).map_err(|error| format!("Could not remove checkpoint {} : {}", path, error))?;
+
+        return Ok(());
+
+    }
+
+    // Enough to render the same view again with --bounds-from-image.
+    let metadata = RenderConfig {
+        output: None,
+        bounds: Some(bounds),
+        upper_left: Some(upper_left),
+        lower_right: Some(lower_right),
+        iterations: Some(config.limit),
+        palette: Some(config.palette)
+    }.to_string();
+
+    if streamed {
+
+        let start = Instant::now();
+        write_streamed_png(&output, bounds, upper_left, lower_right, config, Some(&metadata))
+            .map_err(|error| format!("Could not write {} : {}", output, error))?;
+        logger.log(Level::Debug, format_args!("Rendered and wrote {} in {:.3?}", output, start.elapsed()));
+
+        return Ok(());
+
+    }
+
+    // With --mmap the pixels live in a scratch file next to the output, which
+    // the OS pages instead of the whole image being allocated at once.
+    let len = render_bounds.0 * render_bounds.1 * config.bytes_per_pixel();
+    let (mut mapped, mut owned);
+    let pixels: &mut [u8] = if mmap {
+        let path = format!("{}.pixels", output.trim_end_matches('/'));
+        mapped = MappedBuffer::create(Path::new(&path), len).map_err(|error| format!("Could not map {} : {}", path, error))?;
+        if fill != 0 {
+            mapped.fill(fill);
+        }
+        &mut mapped
+    } else {
+        owned = vec![fill; len];
+        &mut owned
+    };
+
+    if save_partial {
+        catch_interrupt();
+    }
+
+    let render_into = |pixels: &mut [u8], upper_left: Complex<f64>, lower_right: Complex<f64>, config: Config| -> Result<_, RenderError> {
+        let start = Instant::now();
+        let mut row_stats = None;
+        if let Some((origin, size)) = crop {
+            render_crop(pixels, render_bounds, origin, size, upper_left, lower_right, config);
+        } else if let Some(mask) = &mask {
+            render_masked(pixels, render_bounds, mask, upper_left, lower_right, config);
+        } else if let Some(threshold) = adaptive {
+            let refined = render_adaptive(pixels, render_bounds, upper_left, lower_right, config, threshold);
+            logger.log(Level::Debug, format_args!("Supersampled {} of {} pixels", refined, render_bounds.0 * render_bounds.1));
+        } else if histogram {
+            render_histogram(pixels, render_bounds, upper_left, lower_right, config);
+        } else if progress {
+            render_parallel_with_progress(pixels, render_bounds, upper_left, lower_right, config, &Progress::new(render_bounds.1));
+        } else if stats {
+            row_stats = Some(render_parallel_with_stats(pixels, render_bounds, upper_left, lower_right, config));
+        } else if profile {
+            let profile = render_parallel_with_profile(pixels, render_bounds, upper_left, lower_right, config, PROFILE_INTERVAL);
+            eprint!("Profile : {}", profile);
+        } else if chunk_order == ChunkOrder::Hilbert {
+            render_hilbert(pixels, render_bounds, upper_left, lower_right, config);
+        } else if chunk_order == ChunkOrder::Tiles {
+            render_tiles(pixels, render_bounds, upper_left, lower_right, config);
+        } else if save_partial {
+            let rendered = render_parallel_cancellable(pixels, render_bounds, upper_left, lower_right, config, &INTERRUPTED);
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                logger.log(Level::Warn, format_args!("interrupted after rendering {} rows, writing them to {}", rendered, output));
+            }
+        } else if threads == 1 {
+            render_region(pixels, render_bounds, upper_left, lower_right, config)?;
+        } else {
+            render_parallel(pixels, render_bounds, upper_left, lower_right, config);
+        }
+        logger.log(Level::Debug, format_args!("Rendered {} to {} in {:.3?}", upper_left, lower_right, start.elapsed()));
+        Ok(row_stats)
+    };
+
+    if let Some((start, end, frames)) = animation {
+
+        fs::create_dir_all(&output).map_err(|error| format!("Could not create frame directory {} : {}", output, error))?;
+
+        for frame in 0..frames {
+            let frame_config = Config { fractal: Fractal::Julia(julia_frame_constant(start, end, frame, frames)), ..config };
+            let row_stats = render_into(pixels, upper_left, lower_right, frame_config)?;
+
+            let path = frame_path(&output, &prefix, frame);
+            write_image(&path.to_string_lossy(), &resize(pixels, render_bounds, bounds, &config), bounds, config.color_type())
+                .map_err(|error| format!("Could not write {} : {}", path.display(), error))?;
+            if let Some(row_stats) = row_stats {
+                eprintln!("{} : {}", path.display(), row_stats);
+            }
+        }
+
+        return Ok(());
+
+    }
+
+    if let Some((center, factor, _)) = zoom_animation {
+
+        fs::create_dir_all(&output).map_err(|error| format!("Could not create frame directory {} : {}", output, error))?;
+
+        for (frame, &limit) in zoom_limits.iter().enumerate() {
+            let (frame_upper_left, frame_lower_right) = zoom_frame_bounds(upper_left, lower_right, center, factor, frame);
+            let frame_config = Config { limit, ..config };
+            let row_stats = render_into(pixels, frame_upper_left, frame_lower_right, frame_config)?;
+
+            let path = frame_path(&output, &prefix, frame);
+            write_image(&path.to_string_lossy(), &resize(pixels, render_bounds, bounds, &config), bounds, config.color_type())
+                .map_err(|error| format!("Could not write {} : {}", path.display(), error))?;
+            if let Some(row_stats) = row_stats {
+                eprintln!("{} : {}", path.display(), row_stats);
             }
         }
+
+        return Ok(());
+
+    }
+
+    let row_stats = render_into(pixels, upper_left, lower_right, config)?;
+    let mut image = resize(pixels, render_bounds, bounds, &config);
+    let mut color = config.color_type();
+
+    if let Some(blend) = seamless {
+        make_seamless(image.to_mut(), bounds, config.channels(), config.depth, blend);
     }
 
+    // The grid is drawn at the output size, in color over grayscale images.
+    if let Some(spacing) = grid {
+        let channels = if config.channels() == 1 { 3 } else { config.channels() };
+        let mut overlaid = if config.channels() == 1 { gray_to_rgb(&image) } else { image.into_owned() };
+        draw_grid(&mut overlaid, bounds, channels, upper_left, lower_right, spacing);
+        if config.channels() == 1 {
+            color = ColorType::RGB(8);
+        }
+        image = Cow::Owned(overlaid);
+    }
+
+    write_image_with_metadata(&output, &image, bounds, color, &metadata)
+        .map_err(|error| format!("Could not write {} : {}", output, error))?;
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        return Err(format!("Render interrupted, {} only holds the rows rendered before it", output).into());
+    }
+    if let Some(row_stats) = row_stats {
+        eprintln!("{} : {}", output, row_stats);
+    }
+
+    // The render is still written, so a failing comparison can be inspected.
+    if let Some(baseline) = baseline {
+        let channels = if grid.is_some() { config.channels().max(3) } else { config.channels() };
+        let difference = compare(&image, &load_baseline(&baseline, bounds, channels)?, channels);
+        println!("{} : {}", baseline, difference);
+        if difference.pixels > threshold {
+            return Err(format!("{} differs from {} in more than {} pixels", output, baseline, threshold).into());
+        }
+    }
+
+    Ok(())
+
 }
 
-fn parse_complex(s: &str) -> Option<Complex<f64>> {
-    match parse_pair(s, ',') {
-        None => None,
-        Some((re, im)) => Some(Complex { re, im })
+// Renders every job of a manifest by running this program on it, with the
+// remaining flags shared by all jobs, `jobs` renders at a time. Malformed lines
+// abort the batch before anything is rendered when `strict`, and are skipped
+// otherwise.
+fn run_batch(manifest: &str, flags: &[String], jobs: usize, strict: bool) -> Result<(), Box<dyn Error>> {
+
+    let contents = fs::read_to_string(manifest).map_err(|error| format!("Could not read batch {} : {}", manifest, error))?;
+    let program = env::current_exe()?;
+
+    let mut commands = Vec::new();
+    for (_, job) in parse_manifest(&contents) {
+        let job = match job {
+            Ok(job) => job,
+            Err(message) if strict => return Err(format!("Invalid batch {} : {}", manifest, message).into()),
+            Err(message) => {
+                if !flags.iter().any(|flag| flag == "--quiet") {
+                    eprintln!("Warn : {} in {}, skipping it", message, manifest);
+                }
+                continue;
+            }
+        };
+
+        let (bounds, upper_left, lower_right) = (job.bounds.unwrap(), job.upper_left.unwrap(), job.lower_right.unwrap());
+        let mut args = vec![job.output.clone().unwrap(),
+                            format!("{}x{}", bounds.0, bounds.1),
+                            format!("{},{}", upper_left.re, upper_left.im),
+                            format!("{},{}", lower_right.re, lower_right.im)];
+        let mut flags = flags.to_vec();
+
+        // A job's own settings replace the shared ones.
+        if let Some(iterations) = job.iterations {
+            take_flag(&mut flags, "--iterations");
+            args.extend(["--iterations".to_string(), iterations.to_string()]);
+        }
+        if let Some(palette) = job.palette {
+            take_flag(&mut flags, "--palette");
+            let name = Palette::NAMES.iter().find(|name| name.parse() == Ok(palette)).unwrap();
+            args.extend(["--palette".to_string(), name.to_string()]);
+        }
+
+        args.extend(flags);
+        commands.push((job.output.unwrap(), args));
+    }
+
+    let mut failed = 0;
+    for chunk in commands.chunks(jobs) {
+        let children: Vec<_> = chunk.iter()
+                                    .map(|(output, args)| (output, Command::new(&program).args(args).spawn()))
+                                    .collect();
+        for (output, child) in children {
+            match child.and_then(|mut child| child.wait()) {
+                Ok(status) if status.success() => {},
+                Ok(_) => failed += 1,
+                Err(error) => {
+                    eprintln!("Error : could not render {} : {}", output, error);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} jobs in {} failed", failed, commands.len(), manifest).into());
     }
+
+    Ok(())
+
 }
 
-fn pixel_to_point(bounds: (usize, usize),
-                  pixel: (usize, usize),
-                  upper_left: Complex<f64>,
-                  lower_right: Complex<f64>) 
-   -> Complex<f64> 
-{
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug
+}
 
-    let (width, height) = (lower_right.re - upper_left.re, upper_left.im - lower_right.im);
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            _ => Err(format!("Unknown log level {}", s))
+        }
+    }
+}
+
+struct Logger {
+    level: Level
+}
 
-    Complex::<f64> { re: upper_left.re + pixel.0 as f64 * width / bounds.0 as f64,
-                     im: upper_left.im - pixel.1 as f64 * height / bounds.1 as f64 }
+impl Logger {
+    fn enabled(&self, level: Level) -> bool {
+        level <= self.level
+    }
 
+    fn log(&self, level: Level, message: fmt::Arguments) {
+        if self.enabled(level) {
+            eprintln!("{:?} : {}", level, message);
+        }
+    }
 }
 
-fn render(pixels: &mut [u8],
-          bounds: (usize, usize),
-          upper_left: Complex<f64>,
-          lower_right: Complex<f64>)
+// Previews the view in the terminal until a key quits, panning and zooming as
+// keys arrive. Returns the final view if it should be written.
+fn explore(bounds: (usize, usize),
+           mut upper_left: Complex<f64>,
+           mut lower_right: Complex<f64>,
+           config: Config)
+   -> io::Result<Option<(Complex<f64>, Complex<f64>)>>
 {
 
-    assert!(pixels.len() == bounds.0 * bounds.1);
+    let config = Config { supersample: 1, depth: 8, ..config };
+    let saved = stty(&["-g"]);
+    // Without isig, Ctrl-C arrives as a byte that quits like q, rather than
+    // killing the process before the terminal is restored.
+    stty(&["-icanon", "-echo", "-isig", "min", "1"]);
+
+    // The terminal is restored even when drawing or reading fails.
+    let mut stdout = io::stdout();
+    let view = (|| {
 
-    for row in 0..bounds.1 {
-        for column in 0..bounds.0 {
+        let mut input = [0; 64];
+        write!(stdout, "\x1b[2J\x1b[?25l")?;
 
-            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+        loop {
 
-            pixels[row * bounds.0 + column] = 
-                match escape_time(point, 255) {
-                    None => 0,
-                    Some(time) => 255 - time as u8
-                };
+            let preview_size = preview_bounds(terminal_size(), bounds);
+            let mut pixels = vec![0; preview_size.0 * preview_size.1 * config.bytes_per_pixel()];
+
+            // Each pass is drawn as it completes, so the view sharpens in place.
+            let mut drawn = Ok(());
+            render_progressive(&mut pixels, preview_size, upper_left, lower_right, config, |_, pixels| {
+                if drawn.is_ok() {
+                    drawn = write!(stdout, "\x1b[H{}{} to {} : arrows or hjkl pan, + and - zoom, w writes, q quits\x1b[K",
+                                   preview(pixels, preview_size, config.channels()), upper_left, lower_right)
+                            .and_then(|_| stdout.flush());
+                }
+            });
+            drawn?;
+
+            let read = io::stdin().read(&mut input)?;
+            let keys = if read == 0 { vec![Key::Quit] } else { parse_keys(&input[..read]) };
+
+            for key in keys {
+                match key {
+                    Key::Write => return Ok(Some((upper_left, lower_right))),
+                    Key::Quit => return Ok(None),
+                    _ => (upper_left, lower_right) = apply_key(key, upper_left, lower_right)
+                }
+            }
 
         }
+
+    })();
+
+    let restored = write!(stdout, "\x1b[0m\x1b[?25h\r\n");
+    if let Some(saved) = saved {
+        stty(&[saved.trim()]);
     }
+    restored?;
+
+    view
 
 }
 
-fn write_image(filename: &str, pixels: &[u8], bounds: (usize, usize)) -> Result<(), std::io::Error> {
-    
-    let output = File::create(filename)?;
+// Set by the first Ctrl-C once `catch_interrupt` is called.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
-    let encoder = PNGEncoder::new(output);
-    encoder.encode(pixels, bounds.0 as u32, bounds.1 as u32, ColorType::Gray(8))?;
+// Turns the first Ctrl-C into a request to stop rendering, so the rows done
+// so far can still be written. The default action is restored on it, so a
+// second Ctrl-C exits at once.
+#[cfg(unix)]
+fn catch_interrupt() {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::Relaxed);
+        unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+    }
+    unsafe { libc::signal(libc::SIGINT, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+}
 
-    Ok(())
+#[cfg(not(unix))]
+fn catch_interrupt() {}
+
+// Runs stty on the terminal, returning its output, or None when stdin is not
+// a terminal.
+fn stty(args: &[&str]) -> Option<String> {
+
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+
+}
+
+// Columns and lines of the terminal, falling back to 80x24.
+fn terminal_size() -> (usize, usize) {
+
+    let size = stty(&["size"]).and_then(|size| {
+        let mut parts = size.split_whitespace().map(|part| part.parse::<usize>().ok());
+        let (lines, columns) = (parts.next()??, parts.next()??);
+        Some((columns, lines))
+    });
+
+    size.filter(|size| size.0 > 0 && size.1 > 0).unwrap_or((80, 24))
+
+}
+
+// A leading colon like :8080 listens on every interface.
+#[cfg(feature = "tile-server")]
+fn serve_tiles(address: &str, config: Config, cache_tiles: usize, logger: &Logger) -> Result<(), Box<dyn Error>> {
+
+    let address = match address.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => address.to_string()
+    };
+
+    logger.log(Level::Info, format_args!("Serving {:?} tiles on http://{}/z/x/y.png", config.fractal, address));
+    Ok(mandelbrot::server::TileServer::new(config, cache_tiles).serve(&address)?)
+
+}
+
+#[cfg(not(feature = "tile-server"))]
+fn serve_tiles(_: &str, _: Config, _: usize, _: &Logger) -> Result<(), Box<dyn Error>> {
+    Err("--serve needs the tile-server feature, build with --features tile-server".into())
+}
+
+fn usage(program: &str) -> String {
+
+    let forms = [
+        "mandelbrot.png 1920x1080 -1,1 1,-1 [--iterations 255] [--auto-iterations [--iterations-per-decade 100]] [--region-autofocus [--candidates 64] [--seed 0]] [--julia re,im] [--burning-ship] [--newton] [--smooth [--bailout-smooth-bias 2]] [--distance] [--trap point|line|circle] [--stripe 5] [--normal-map [--lighting 45,45]] [--palette grayscale|fire|ocean|ultra] [--colormap viridis|magma|inferno|plasma] [--gradient file.grad] [--supersample 1 [--sampling grid|random|sobol [--seed 0]] [--supersample-threshold 0]] [--scale 1] [--mirror] [--antialias-adaptive threshold] [--f32] [--precision 53] [--perturbation] [--power 2] [--bailout 2] [--depth 8|16 | --color-depth-auto] [--gamma 1] [--log-scale] [--dither] [--invert] [--interior RRGGBB] [--transparent-interior] [--histogram] [--mmap] [--max-memory 4096] [--tile-size 512] [--threads N] [--chunk-order rows|hilbert|tiles] [--keep-aspect] [--grid 0.5] [--seamless-tile 32] [--crop x,y,w,h | --mask region.png] [--fill 0] [--raw counts.npy|counts.csv] [--iterations-map heat.png] [--format raw16] [--precision-diff] [--compare baseline.png [--threshold 0]] [--buddhabrot | --antibuddhabrot | --nebulabrot 5000,500,50 [--nebula-samples 1000000,1000000,1000000]] [--samples 1000000] [--seed 0] [--checkpoint render.ckpt | --resume render.ckpt] [--progress] [--stats] [--profile] [--save-partial] [--preview] [--to-json] [--dry-run] [--interactive] [--verbose|--quiet]",
+        "mandelbrot.png 1920x1080 --center -0.5,0 [--zoom 1] [flags]",
+        "mandelbrot.png 1920x1080 --preset seahorse-valley|elephant-valley|triple-spiral|mini-mandelbrot [flags]",
+        "--config render.toml [flags]",
+        "--from-json render.json|- [--to-json] [flags]",
+        "--bounds-from-image earlier.png [mandelbrot.png] [flags]",
+        "--batch jobs.txt [--jobs 1] [--strict] [flags]",
+        "--serve :8080 [--cache-tiles 256] [flags]",
+        "--sample-count 1000000 [--seed 0] [--julia re,im] [--burning-ship] [--iterations 255] [--power 2] [--bailout 2]",
+        "--seed-orbit re,im [--julia re,im] [--burning-ship] [--iterations 255] [--power 2] [--bailout 2]",
+        "frames/ 1920x1080 -1,1 1,-1 --julia-animation start end frames [--frame-prefix frame]",
+        "frames/ 1920x1080 -2,1.2 1,-1.2 --palette-cycle frames [--frame-prefix frame]",
+        "frames/ 1920x1080 -2,1.2 1,-1.2 --zoom-animation center factor frames [--base-iterations 255 [--iterations-per-frame-schedule linear|geometric|0:255,100:2000]] [--frame-prefix frame]",
+    ];
+
+    forms.iter()
+         .enumerate()
+         .map(|(index, form)| format!("{} {} {}\n", if index == 0 { "Usage :" } else { "       " }, program, form))
+         .collect()
+
+}
+
+fn resize<'a>(pixels: &'a [u8], render_bounds: (usize, usize), bounds: (usize, usize), config: &Config) -> Cow<'a, [u8]> {
+    if render_bounds == bounds {
+        Cow::Borrowed(pixels)
+    } else {
+        Cow::Owned(lanczos_resize(pixels, render_bounds, bounds, config.channels(), config.depth))
+    }
+}
+
+// How often --profile notes the row each thread is on.
+const PROFILE_INTERVAL: Duration = Duration::from_millis(1);
+
+// Megabytes of buffers a render may allocate unless --max-memory says otherwise.
+const DEFAULT_MAX_MEMORY: usize = 4096;
+
+// Refuses buffers of `bytes_per_pixel` for every pixel above `max_memory`
+// megabytes, before they are allocated.
+fn check_memory(bounds: (usize, usize), bytes_per_pixel: usize, max_memory: usize) -> Result<(), String> {
+
+    let megabytes = bounds.0.checked_mul(bounds.1)
+                            .and_then(|pixels| pixels.checked_mul(bytes_per_pixel))
+                            .map(|bytes| bytes.div_ceil(1 << 20));
+
+    match megabytes {
+        Some(megabytes) if megabytes <= max_memory => Ok(()),
+        _ => Err(format!("Rendering {}x{} needs {} of memory, above the --max-memory limit of {} MB. Check the bounds, raise --max-memory or write a .png with --tile-size to render it in tiles",
+                         bounds.0, bounds.1, megabytes.map_or("more than the address space".to_string(), |megabytes| format!("{} MB", megabytes)), max_memory))
+    }
+
+}
+
+// What a render would cost, given the iteration limit of each frame. The
+// iteration budget is an upper bound, reached only if no point escapes.
+fn plan(bounds: (usize, usize), config: &Config, limits: &[usize]) -> String {
+
+    let pixels = bounds.0 * bounds.1;
+    let samples = config.supersample * config.supersample;
+    let iterations: u128 = limits.iter().map(|limit| (pixels * samples) as u128 * *limit as u128).sum();
 
+    format!("Frames : {}\nPixels : {} per frame ({}x{})\nBuffer : {} bytes\nIterations : at most {}\n",
+            limits.len(), pixels, bounds.0, bounds.1, pixels * config.bytes_per_pixel(), iterations)
+
+}
+
+fn parse_arg<T: FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Could not parse {} value '{}'", name, value))
+}
+
+fn parse_complex_arg(value: &str, name: &str) -> Result<Complex<f64>, String> {
+    parse_complex(value).map_err(|_| format!("Could not parse {} '{}', expected a complex number like -0.5,1 or -0.5+1i", name, value))
+}
+
+// Three comma separated positive values, for the red, green and blue channels.
+fn parse_channels(value: &str, name: &str) -> Result<[usize; 3], String> {
+    let values: Vec<usize> = value.split(',').map(|part| parse_arg(part, name)).collect::<Result<_, _>>()?;
+    match values[..] {
+        [red, green, blue] if red > 0 && green > 0 && blue > 0 => Ok([red, green, blue]),
+        _ => Err(format!("{} expects three positive values like 5000,500,50, got '{}'", name, value))
+    }
+}
+
+fn take_switch(args: &mut Vec<String>, flag: &str) -> bool {
+
+    match args.iter().position(|arg| arg == flag) {
+        None => false,
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+    }
+
+}
+
+fn take_flag_values(args: &mut Vec<String>, flag: &str, count: usize) -> Option<Vec<String>> {
+
+    let index = args.iter().position(|arg| arg == flag)?;
+    let end = (index + 1 + count).min(args.len());
+
+    let values: Vec<String> = args.drain(index..end).skip(1).collect();
+    Some(values)
+
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Option<String> {
+
+    let index = args.iter().position(|arg| arg == flag)?;
+    if index + 1 >= args.len() {
+        args.remove(index);
+        return Some(String::new());
+    }
+
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Some(value)
+
+}
+
+#[test]
+fn test_take_flag() {
+    let mut args: Vec<String> = ["prog", "--iterations", "1000", "out.png"].iter().map(|s| s.to_string()).collect();
+    assert_eq!(take_flag(&mut args, "--iterations"), Some("1000".to_string()));
+    assert_eq!(args, vec!["prog".to_string(), "out.png".to_string()]);
+    assert_eq!(take_flag(&mut args, "--iterations"), None);
+}
+
+#[test]
+fn test_take_switch() {
+    let mut args: Vec<String> = ["prog", "--smooth", "out.png"].iter().map(|s| s.to_string()).collect();
+    assert!(take_switch(&mut args, "--smooth"));
+    assert_eq!(args, vec!["prog".to_string(), "out.png".to_string()]);
+    assert!(!take_switch(&mut args, "--smooth"));
+}
+
+#[test]
+fn test_take_flag_values() {
+    let mut args: Vec<String> = ["prog", "--julia-animation", "0,0", "1,1", "10", "out"].iter().map(|s| s.to_string()).collect();
+    assert_eq!(take_flag_values(&mut args, "--julia-animation", 3),
+               Some(vec!["0,0".to_string(), "1,1".to_string(), "10".to_string()]));
+    assert_eq!(args, vec!["prog".to_string(), "out".to_string()]);
+    assert_eq!(take_flag_values(&mut args, "--julia-animation", 3), None);
+}
+
+#[test]
+fn test_parse_arg() {
+    assert_eq!(parse_arg::<usize>("1000", "--iterations"), Ok(1000));
+    assert_eq!(parse_arg::<usize>("many", "--iterations"), Err("Could not parse --iterations value 'many'".to_string()));
+    assert_eq!(parse_complex_arg("-0.5,1", "--julia"), Ok(Complex { re: -0.5, im: 1.0 }));
+    assert!(parse_complex_arg("-0.5+", "--julia").unwrap_err().contains("--julia"));
+}
+
+#[test]
+fn test_logger_level() {
+    assert_eq!("info".parse::<Level>(), Ok(Level::Info));
+    assert_eq!("DEBUG".parse::<Level>(), Ok(Level::Debug));
+    assert!("trace".parse::<Level>().is_err());
+
+    let logger = Logger { level: Level::Warn };
+    assert!(logger.enabled(Level::Error) && logger.enabled(Level::Warn));
+    assert!(!logger.enabled(Level::Info) && !logger.enabled(Level::Debug));
+    assert!(!Logger { level: Level::Error }.enabled(Level::Warn));
 }
 
 #[test]
-fn test_pixel_to_point() {
-    assert_eq!(pixel_to_point((100, 100),
-                              (50, 50),
-                              Complex::<f64> { re: -1.0, im: 1.0},
-                              Complex::<f64> { re: 1.0, im: -1.0}),
-               Complex::<f64> { re: 0.0, im: 0.0});
+fn test_conflict() {
+    assert_eq!(conflict(&[]), None);
+    assert_eq!(conflict(&[Mode::Interactive, Mode::Raw16]), Some((Mode::Interactive, Mode::Raw16)));
+    assert_eq!(conflict(&[Mode::Raw16, Mode::Interactive]), Some((Mode::Raw16, Mode::Interactive)));
+    assert_eq!(conflict(&[Mode::Interactive, Mode::Raw]), Some((Mode::Interactive, Mode::Raw)));
+    assert_eq!(conflict(&[Mode::Progress, Mode::Crop]), Some((Mode::Progress, Mode::Crop)));
+    assert_eq!(conflict(&[Mode::Stats, Mode::Dither, Mode::Profile]), Some((Mode::Stats, Mode::Profile)));
+    assert_eq!(conflict(&[Mode::JuliaAnimation, Mode::PaletteCycle]), Some((Mode::JuliaAnimation, Mode::PaletteCycle)));
+    assert_eq!(conflict(&[Mode::Checkpoint, Mode::Resume]), Some((Mode::Checkpoint, Mode::Resume)));
+    assert_eq!(conflict(&[Mode::Perturbation, Mode::Progress]), Some((Mode::Perturbation, Mode::Progress)));
+    assert_eq!(conflict(&[Mode::Depth, Mode::ColorDepthAuto]), Some((Mode::Depth, Mode::ColorDepthAuto)));
+
+    assert_eq!(conflict(&[Mode::Raw, Mode::Raw16, Mode::IterationsMap]), None);
+    assert_eq!(conflict(&[Mode::Progress, Mode::JuliaAnimation, Mode::Scale, Mode::Dither]), None);
+    assert_eq!(conflict(&[Mode::Grid, Mode::Compare, Mode::Seamless, Mode::Interactive]), None);
+    assert_eq!(conflict(&[Mode::Perturbation, Mode::DeepPrecision, Mode::AutoIterations, Mode::ColorDepthAuto]), None);
+
+    // Only one of each group runs, so every pair within a group conflicts.
+    for group in [FRAMES, OUTPUTS, RENDERERS] {
+        for (index, first) in group.iter().enumerate() {
+            for second in &group[index + 1..] {
+                assert!(conflict(&[*first, *second]).is_some(), "{} and {}", first, second);
+            }
+        }
+    }
 }
 
 #[test]
-fn test_parse_pair() {
-    assert_eq!(parse_pair::<i32>("-10x10",    'x'), Some((-10, 10)));
-    assert_eq!(parse_pair::<i32>("800,600f",  ','), None);
-    assert_eq!(parse_pair::<i32>("1920/1080", '/'), Some((1920, 1080)));
-    assert_eq!(parse_pair::<i32>("10.6*-34",  '*'), None);
-    assert_eq!(parse_pair::<String>("abc*jhu",  '*'), Some(("abc".to_string(), "jhu".to_string())))
+fn test_check_memory() {
+    assert!(check_memory((1920, 1080), 3, DEFAULT_MAX_MEMORY).is_ok());
+    assert!(check_memory((192000, 108000), 0, 1).is_ok());
+    assert!(check_memory((1024, 1024), 2, 2).is_ok());
+
+    let error = check_memory((192000, 108000), 1, DEFAULT_MAX_MEMORY).unwrap_err();
+    assert!(error.starts_with("Rendering 192000x108000 needs 19776 MB of memory, above the --max-memory limit of 4096 MB"));
+    assert!(error.contains("--tile-size"));
+    assert!(check_memory((1024, 1024), 3, 2).is_err());
+    assert!(check_memory((usize::MAX, 2), 1, usize::MAX).unwrap_err().contains("more than the address space"));
 }
 
 #[test]
-fn test_parse_complex() {
-    assert_eq!(parse_complex("3.14,1"), Some(Complex { re: 3.14, im: 1.0 }));
-    assert_eq!(parse_complex("-12/4"), None);
-}
\ No newline at end of file
+fn test_plan() {
+    let config = Config { limit: 100, supersample: 2, palette: Palette::Fire, ..Config::default() };
+    assert_eq!(plan((40, 30), &config, &[100, 200]),
+               "Frames : 2\nPixels : 1200 per frame (40x30)\nBuffer : 3600 bytes\nIterations : at most 1440000\n");
+}
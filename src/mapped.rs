@@ -0,0 +1,79 @@
+use memmap2::MmapMut;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+// A pixel buffer backed by a scratch file rather than by memory, so the OS
+// pages rows in and out as they are rendered and encoded. The file is removed
+// when the buffer is dropped.
+pub struct MappedBuffer {
+    path: PathBuf,
+    map: MmapMut
+}
+
+impl MappedBuffer {
+
+    pub fn create(path: &Path, len: usize) -> Result<Self, io::Error> {
+
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len(len as u64)?;
+
+        // Safety : the file was just created for this buffer alone, and
+        // nothing else resizes or writes it while it is mapped.
+        let map = unsafe { MmapMut::map_mut(&file) };
+
+        match map {
+            Ok(map) => Ok(MappedBuffer { path: path.to_path_buf(), map }),
+            Err(error) => {
+                let _ = fs::remove_file(path);
+                Err(error)
+            }
+        }
+
+    }
+
+}
+
+impl Deref for MappedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl DerefMut for MappedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.map
+    }
+}
+
+impl Drop for MappedBuffer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[test]
+fn test_mapped_buffer() {
+    use crate::{Config, Palette, render_parallel};
+    use num::Complex;
+
+    let bounds = (97, 61);
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+    let config = Config { palette: Palette::Fire, ..Config::default() };
+    let len = bounds.0 * bounds.1 * config.bytes_per_pixel();
+
+    let mut pixels = vec![0; len];
+    render_parallel(&mut pixels, bounds, upper_left, lower_right, config);
+
+    let path = std::env::temp_dir().join("mandelbrot_test_mapped_buffer.pixels");
+    let mut mapped = MappedBuffer::create(&path, len).unwrap();
+    assert!(mapped.iter().all(|byte| *byte == 0));
+    render_parallel(&mut mapped, bounds, upper_left, lower_right, config);
+    assert!(*mapped == *pixels);
+
+    drop(mapped);
+    assert!(!path.exists());
+}
@@ -0,0 +1,33 @@
+use crate::RenderError;
+use image::GenericImage;
+use std::io;
+
+// Loads a mask image as one flag per pixel, set where the image is not black.
+// Its size must match the output exactly, since the mask is not resampled.
+pub fn load_mask(filename: &str, bounds: (usize, usize)) -> Result<Vec<bool>, RenderError> {
+
+    let invalid = |message: String| RenderError::Io(io::Error::new(io::ErrorKind::InvalidData, message));
+
+    let image = image::open(filename).map_err(|error| invalid(format!("Could not read mask {} : {}", filename, error)))?;
+    let (width, height) = image.dimensions();
+    if (width as usize, height as usize) != bounds {
+        return Err(invalid(format!("Mask {} is {}x{}, expected {}x{} like the output", filename, width, height, bounds.0, bounds.1)));
+    }
+
+    Ok(image.to_luma().into_raw().into_iter().map(|level| level != 0).collect())
+
+}
+
+#[test]
+fn test_load_mask() {
+    use crate::write_image;
+    use image::ColorType;
+
+    let pixels = [0, 255, 1, 0, 0, 128];
+    let path = std::env::temp_dir().join("mandelbrot_test_load_mask.png");
+    write_image(path.to_str().unwrap(), &pixels, (3, 2), ColorType::Gray(8)).unwrap();
+
+    assert_eq!(load_mask(path.to_str().unwrap(), (3, 2)).unwrap(), vec![false, true, true, false, false, true]);
+    assert_eq!(load_mask(path.to_str().unwrap(), (2, 3)).unwrap_err().to_string(),
+               format!("Mask {} is 3x2, expected 2x3 like the output", path.display()));
+}
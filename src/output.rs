@@ -0,0 +1,562 @@
+use crate::RenderError;
+use crate::webp::encode_webp;
+use deflate::Compression;
+use deflate::write::ZlibEncoder;
+use image::ColorType;
+use image::jpeg::JPEGEncoder;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn write_image(filename: &str, pixels: &[u8], bounds: (usize, usize), color: ColorType) -> Result<(), RenderError> {
+
+    let extension = Path::new(filename).extension()
+                                       .and_then(|extension| extension.to_str())
+                                       .map(|extension| extension.to_ascii_lowercase());
+
+    if filename == "-" {
+        return Ok(write_ppm(filename, pixels, bounds, color)?);
+    }
+
+    match extension.as_deref() {
+        Some("png") => encode_png(File::create(filename)?, pixels, bounds, color)?,
+        Some("jpg") | Some("jpeg") => {
+            let mut output = File::create(filename)?;
+            let mut encoder = JPEGEncoder::new(&mut output);
+            encoder.encode(pixels, bounds.0 as u32, bounds.1 as u32, color)?;
+        },
+        Some("pgm") | Some("ppm") => write_ppm(filename, pixels, bounds, color)?,
+        Some("webp") => {
+            let mut output = io::BufWriter::new(File::create(filename)?);
+            encode_webp(&mut output, pixels, bounds, color)?;
+            output.flush()?;
+        },
+        _ => return Err(RenderError::Io(io::Error::new(io::ErrorKind::InvalidInput,
+                                                       format!("Unsupported output format for {}, expected .png, .jpg, .jpeg, .pgm, .ppm or .webp", filename))))
+    }
+
+    Ok(())
+
+}
+
+// Keyword of the PNG tEXt chunk holding the parameters of a render.
+pub const METADATA_KEYWORD: &str = "mandelbrot";
+
+// Like `write_image`, with `metadata` kept in a tEXt chunk right after the
+// header of PNG files. Other formats are written without it.
+pub fn write_image_with_metadata(filename: &str, pixels: &[u8], bounds: (usize, usize), color: ColorType, metadata: &str) -> Result<(), RenderError> {
+
+    let png = Path::new(filename).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+    if !png {
+        return write_image(filename, pixels, bounds, color);
+    }
+
+    let output = io::BufWriter::new(File::create(filename)?);
+    encode_png_with_text(output, pixels, bounds, color, Some((METADATA_KEYWORD, metadata)))?;
+
+    Ok(())
+
+}
+
+// The text of the first tEXt chunk with the given keyword in a PNG file.
+pub fn read_png_text(bytes: &[u8], keyword: &str) -> Option<String> {
+
+    let mut chunks = bytes.strip_prefix(&PNG_SIGNATURE)?;
+
+    while chunks.len() >= 12 {
+        let length = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+        let data = chunks.get(8..8 + length)?;
+        if &chunks[4..8] == b"tEXt" {
+            if let Some(text) = data.strip_prefix(keyword.as_bytes()).and_then(|rest| rest.strip_prefix(&[0])) {
+                // tEXt is Latin-1, whose code points are its bytes.
+                return Some(text.iter().map(|byte| *byte as char).collect());
+            }
+        }
+        chunks = chunks.get(12 + length..)?;
+    }
+
+    None
+
+}
+
+pub fn encode_png<W: Write>(output: W, pixels: &[u8], bounds: (usize, usize), color: ColorType) -> Result<(), std::io::Error> {
+    encode_png_with_text(output, pixels, bounds, color, None)
+}
+
+// Whole images go through the same writer as streamed ones, so both give the
+// same bytes however many IDAT chunks the image data takes.
+fn encode_png_with_text<W: Write>(output: W,
+                                  pixels: &[u8],
+                                  bounds: (usize, usize),
+                                  color: ColorType,
+                                  text: Option<(&str, &str)>)
+   -> Result<(), std::io::Error>
+{
+
+    let mut writer = PngStreamWriter::new(output, bounds, color, text)?;
+    writer.write_rows(pixels)?;
+    writer.finish()?;
+
+    Ok(())
+
+}
+
+// Compressed image data goes out in IDAT chunks of this size as it is
+// produced, so neither the pixels nor their compressed stream are held whole.
+const IDAT_SIZE: usize = 1 << 16;
+
+// Writes the bytes it is given as IDAT chunks of `IDAT_SIZE` bytes, and the
+// rest as a last chunk when finished.
+struct IdatWriter<W: Write> {
+    output: W,
+    buffer: Vec<u8>
+}
+
+impl<W: Write> IdatWriter<W> {
+
+    fn finish(mut self) -> Result<W, std::io::Error> {
+        write_chunk(&mut self.output, b"IDAT", &self.buffer)?;
+        Ok(self.output)
+    }
+
+}
+
+impl<W: Write> Write for IdatWriter<W> {
+
+    fn write(&mut self, bytes: &[u8]) -> Result<usize, std::io::Error> {
+
+        let taken = bytes.len().min(IDAT_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&bytes[..taken]);
+        if self.buffer.len() == IDAT_SIZE {
+            write_chunk(&mut self.output, b"IDAT", &self.buffer)?;
+            self.buffer.clear();
+        }
+
+        Ok(taken)
+
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.output.flush()
+    }
+
+}
+
+pub struct PngStreamWriter<W: Write> {
+    zlib: ZlibEncoder<IdatWriter<W>>,
+    bytes_per_pixel: usize,
+    row_length: usize,
+    filtered: Vec<u8>
+}
+
+impl<W: Write> PngStreamWriter<W> {
+
+    // The header is written at once, followed by a tEXt chunk when given a
+    // keyword and its text, since none may come between the image data chunks.
+    pub fn new(mut output: W, bounds: (usize, usize), color: ColorType, text: Option<(&str, &str)>) -> Result<Self, std::io::Error> {
+
+        let (color_type, bit_depth): (png::ColorType, png::BitDepth) = color.into();
+        let bytes_per_pixel = match color {
+            ColorType::Gray(bits) | ColorType::Palette(bits) => bits as usize,
+            ColorType::GrayA(bits) => 2 * bits as usize,
+            ColorType::RGB(bits) => 3 * bits as usize,
+            ColorType::RGBA(bits) => 4 * bits as usize
+        }.div_ceil(8);
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(bounds.0 as u32).to_be_bytes());
+        header.extend_from_slice(&(bounds.1 as u32).to_be_bytes());
+        header.extend_from_slice(&[bit_depth as u8, color_type as u8, 0, 0, 0]);
+
+        output.write_all(&PNG_SIGNATURE)?;
+        write_chunk(&mut output, b"IHDR", &header)?;
+        if let Some((keyword, text)) = text {
+            write_text_chunk(&mut output, keyword, text)?;
+        }
+
+        Ok(PngStreamWriter {
+            zlib: ZlibEncoder::new(IdatWriter { output, buffer: Vec::with_capacity(IDAT_SIZE) }, Compression::Fast),
+            bytes_per_pixel,
+            row_length: bounds.0 * bytes_per_pixel,
+            filtered: vec![0; bounds.0 * bytes_per_pixel]
+        })
+
+    }
+
+    pub fn write_rows(&mut self, rows: &[u8]) -> Result<(), std::io::Error> {
+
+        assert!(self.row_length > 0 && rows.len().is_multiple_of(self.row_length));
+
+        // Rows are filtered and compressed exactly as PNGEncoder does, so an
+        // image whose data fits in one IDAT chunk gets the same bytes from it.
+        for line in rows.chunks(self.row_length) {
+            for (index, byte) in self.filtered.iter_mut().enumerate() {
+                *byte = if index < self.bytes_per_pixel {
+                    line[index]
+                } else {
+                    line[index].wrapping_sub(line[index - self.bytes_per_pixel])
+                };
+            }
+            self.zlib.write_all(&[SUB_FILTER])?;
+            self.zlib.write_all(&self.filtered)?;
+        }
+
+        Ok(())
+
+    }
+
+    pub fn finish(self) -> Result<W, std::io::Error> {
+
+        let mut output = self.zlib.finish()?.finish()?;
+        write_chunk(&mut output, b"IEND", &[])?;
+        output.flush()?;
+
+        Ok(output)
+
+    }
+
+}
+
+const SUB_FILTER: u8 = 1;
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+fn write_text_chunk<W: Write>(output: &mut W, keyword: &str, text: &str) -> Result<(), std::io::Error> {
+
+    let mut data = keyword.as_bytes().to_vec();
+    data.push(0);
+    data.extend(text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }));
+
+    write_chunk(output, b"tEXt", &data)
+
+}
+
+fn write_chunk<W: Write>(output: &mut W, name: &[u8; 4], data: &[u8]) -> Result<(), std::io::Error> {
+
+    let length = u32::try_from(data.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                      format!("{} bytes do not fit in a PNG chunk", data.len())))?;
+    output.write_all(&length.to_be_bytes())?;
+    output.write_all(name)?;
+    output.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(name);
+    crc.update(data);
+    output.write_all(&crc.checksum().to_be_bytes())
+
+}
+
+struct Crc32 {
+    value: u32
+}
+
+impl Crc32 {
+
+    fn new() -> Self {
+        Crc32 { value: 0xffff_ffff }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.value ^= *byte as u32;
+            for _ in 0..8 {
+                self.value = if self.value & 1 == 1 { (self.value >> 1) ^ 0xedb8_8320 } else { self.value >> 1 };
+            }
+        }
+    }
+
+    fn checksum(&self) -> u32 {
+        self.value ^ 0xffff_ffff
+    }
+
+}
+
+pub fn write_ppm(filename: &str, pixels: &[u8], bounds: (usize, usize), color: ColorType) -> Result<(), std::io::Error> {
+
+    if filename == "-" {
+        let stdout = io::stdout();
+        let mut output = stdout.lock();
+        encode_ppm(&mut output, pixels, bounds, color)?;
+        return output.flush();
+    }
+
+    let mut output = io::BufWriter::new(File::create(filename)?);
+    encode_ppm(&mut output, pixels, bounds, color)?;
+    output.flush()
+
+}
+
+fn encode_ppm<W: Write>(output: &mut W, pixels: &[u8], bounds: (usize, usize), color: ColorType) -> Result<(), std::io::Error> {
+
+    let (magic, max) = match color {
+        ColorType::Gray(8) => ("P5", 255),
+        ColorType::Gray(16) => ("P5", 65535),
+        ColorType::RGB(8) => ("P6", 255),
+        ColorType::RGB(16) => ("P6", 65535),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("Unsupported color type {:?} for PPM output", color)))
+    };
+
+    write!(output, "{}\n{} {}\n{}\n", magic, bounds.0, bounds.1, max)?;
+    output.write_all(pixels)
+
+}
+
+// Escape counts go out as a HEIGHT x WIDTH matrix, with points that never
+// escaped stored as the iteration limit.
+pub fn write_counts(filename: &str, counts: &[Option<usize>], bounds: (usize, usize), limit: usize) -> Result<(), std::io::Error> {
+
+    let extension = Path::new(filename).extension()
+                                       .and_then(|extension| extension.to_str())
+                                       .map(|extension| extension.to_ascii_lowercase());
+
+    let encode = match extension.as_deref() {
+        Some("csv") => encode_csv::<io::BufWriter<File>>,
+        Some("npy") => encode_npy::<io::BufWriter<File>>,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("Unsupported raw output format for {}, expected .csv or .npy", filename)))
+    };
+
+    let mut output = io::BufWriter::new(File::create(filename)?);
+    encode(&mut output, counts, bounds, limit)?;
+    output.flush()
+
+}
+
+// Escape counts as headerless little-endian u16 values, row by row, with the
+// iteration limit standing for points that never escaped and counts above
+// 65535 clamped to it.
+pub fn write_raw16(filename: &str, counts: &[Option<usize>], limit: usize) -> Result<(), std::io::Error> {
+
+    if filename == "-" {
+        let stdout = io::stdout();
+        let mut output = io::BufWriter::new(stdout.lock());
+        encode_raw16(&mut output, counts, limit)?;
+        return output.flush();
+    }
+
+    let mut output = io::BufWriter::new(File::create(filename)?);
+    encode_raw16(&mut output, counts, limit)?;
+    output.flush()
+
+}
+
+fn encode_raw16<W: Write>(output: &mut W, counts: &[Option<usize>], limit: usize) -> Result<(), std::io::Error> {
+
+    for count in counts {
+        output.write_all(&(count.unwrap_or(limit).min(u16::MAX as usize) as u16).to_le_bytes())?;
+    }
+
+    Ok(())
+
+}
+
+fn encode_csv<W: Write>(output: &mut W, counts: &[Option<usize>], bounds: (usize, usize), limit: usize) -> Result<(), std::io::Error> {
+
+    for row in counts.chunks(bounds.0.max(1)) {
+        let line: Vec<String> = row.iter().map(|count| count.unwrap_or(limit).to_string()).collect();
+        writeln!(output, "{}", line.join(","))?;
+    }
+
+    Ok(())
+
+}
+
+fn encode_npy<W: Write>(output: &mut W, counts: &[Option<usize>], bounds: (usize, usize), limit: usize) -> Result<(), std::io::Error> {
+
+    // Version 1.0 header, padded so the data starts on a 64 byte boundary.
+    let mut header = format!("{{'descr': '<u8', 'fortran_order': False, 'shape': ({}, {}), }}", bounds.1, bounds.0);
+    let padding = 63 - (10 + header.len()) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    output.write_all(b"\x93NUMPY\x01\x00")?;
+    output.write_all(&(header.len() as u16).to_le_bytes())?;
+    output.write_all(header.as_bytes())?;
+
+    for count in counts {
+        output.write_all(&(count.unwrap_or(limit) as u64).to_le_bytes())?;
+    }
+
+    Ok(())
+
+}
+
+#[test]
+fn test_write_image() {
+    use image::GenericImage;
+
+    let pixels: Vec<u8> = (0..16).map(|i| i * 16).collect();
+    let directory = std::env::temp_dir();
+
+    let png = directory.join("mandelbrot_test_write_image.png");
+    write_image(png.to_str().unwrap(), &pixels, (4, 4), ColorType::Gray(8)).unwrap();
+    let decoded = image::open(&png).unwrap();
+    assert_eq!(decoded.dimensions(), (4, 4));
+    assert_eq!(decoded.to_luma().into_raw(), pixels);
+
+    let jpeg = directory.join("mandelbrot_test_write_image.jpg");
+    write_image(jpeg.to_str().unwrap(), &pixels, (4, 4), ColorType::Gray(8)).unwrap();
+    assert_eq!(image::open(&jpeg).unwrap().dimensions(), (4, 4));
+
+    let error = write_image("mandelbrot_test_write_image.bmp", &pixels, (4, 4), ColorType::Gray(8)).unwrap_err();
+    assert!(matches!(error, RenderError::Io(error) if error.kind() == io::ErrorKind::InvalidInput));
+}
+
+#[test]
+fn test_write_image_16_bit() {
+    use png::HasParameters;
+
+    let values: Vec<u16> = (0..16).map(|i| i * 4369).collect();
+    let pixels: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+    let path = std::env::temp_dir().join("mandelbrot_test_write_image_16.png");
+    write_image(path.to_str().unwrap(), &pixels, (4, 4), ColorType::Gray(16)).unwrap();
+
+    let mut decoder = png::Decoder::new(File::open(&path).unwrap());
+    decoder.set(png::TRANSFORM_IDENTITY);
+    let (info, mut reader) = decoder.read_info().unwrap();
+    assert_eq!((info.width, info.height), (4, 4));
+    assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Grayscale, png::BitDepth::Sixteen));
+
+    let mut decoded = vec![0; info.buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+    let decoded: Vec<u16> = decoded.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+    assert_eq!(decoded, values);
+    assert_eq!((decoded[0], decoded[15]), (0, 65535));
+
+    let mut pgm = Vec::new();
+    encode_ppm(&mut pgm, &pixels, (4, 4), ColorType::Gray(16)).unwrap();
+    assert!(pgm.starts_with(b"P5\n4 4\n65535\n"));
+    assert!(pgm.ends_with(&pixels));
+}
+
+#[test]
+fn test_write_ppm() {
+    let pixels: Vec<u8> = (0..12).collect();
+    let path = std::env::temp_dir().join("mandelbrot_test_write_ppm.pgm");
+    write_ppm(path.to_str().unwrap(), &pixels, (4, 3), ColorType::Gray(8)).unwrap();
+
+    let written = std::fs::read(&path).unwrap();
+    let header = b"P5\n4 3\n255\n";
+    assert_eq!(&written[..header.len()], header);
+    assert_eq!(written.len() - header.len(), 4 * 3);
+    assert_eq!(&written[header.len()..], &pixels[..]);
+
+    let mut rgb = Vec::new();
+    encode_ppm(&mut rgb, &[0; 2 * 2 * 3], (2, 2), ColorType::RGB(8)).unwrap();
+    assert!(rgb.starts_with(b"P6\n2 2\n255\n"));
+    assert_eq!(rgb.len(), b"P6\n2 2\n255\n".len() + 2 * 2 * 3);
+
+    let error = encode_ppm(&mut Vec::new(), &[0; 4], (2, 1), ColorType::RGBA(8)).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_png_stream_writer() {
+    use image::png::PNGEncoder;
+
+    for (color, channels) in &[(ColorType::Gray(8), 1), (ColorType::Gray(16), 2), (ColorType::RGB(8), 3)] {
+        let bounds = (13, 7);
+        let pixels: Vec<u8> = (0..bounds.0 * bounds.1 * channels).map(|i| (i * 37 % 251) as u8).collect();
+
+        let mut expected = Vec::new();
+        PNGEncoder::new(&mut expected).encode(&pixels, bounds.0 as u32, bounds.1 as u32, *color).unwrap();
+
+        let mut writer = PngStreamWriter::new(Vec::new(), bounds, *color, None).unwrap();
+        for rows in pixels.chunks(bounds.0 * channels * 3) {
+            writer.write_rows(rows).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), expected);
+    }
+
+    // Noise hardly compresses, so this image spans several IDAT chunks.
+    let bounds = (300, 200);
+    let mut state = 1u32;
+    let pixels: Vec<u8> = (0..bounds.0 * bounds.1 * 3).map(|_| { state = state.wrapping_mul(1664525).wrapping_add(1013904223); (state >> 24) as u8 }).collect();
+
+    let mut writer = PngStreamWriter::new(Vec::new(), bounds, ColorType::RGB(8), Some(("comment", "noise"))).unwrap();
+    for rows in pixels.chunks(bounds.0 * 3 * 7) {
+        writer.write_rows(rows).unwrap();
+    }
+    let encoded = writer.finish().unwrap();
+
+    let mut whole = Vec::new();
+    encode_png_with_text(&mut whole, &pixels, bounds, ColorType::RGB(8), Some(("comment", "noise"))).unwrap();
+    assert_eq!(whole, encoded);
+
+    assert!(png_chunks(&encoded).iter().filter(|(name, length)| name == b"IDAT" && *length == IDAT_SIZE).count() >= 2);
+    assert_eq!(read_png_text(&encoded, "comment"), Some("noise".to_string()));
+    assert_eq!(decode_png(&encoded), (bounds, pixels));
+}
+
+// The name and data length of each chunk of a PNG file.
+#[cfg(test)]
+pub(crate) fn png_chunks(bytes: &[u8]) -> Vec<([u8; 4], usize)> {
+
+    let mut chunks = &bytes[PNG_SIGNATURE.len()..];
+    let mut found = Vec::new();
+
+    while chunks.len() >= 12 {
+        let length = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+        found.push(([chunks[4], chunks[5], chunks[6], chunks[7]], length));
+        chunks = &chunks[12 + length..];
+    }
+
+    found
+
+}
+
+// The bounds and raw bytes of a PNG file, 16 bit samples kept big endian.
+#[cfg(test)]
+pub(crate) fn decode_png(bytes: &[u8]) -> ((usize, usize), Vec<u8>) {
+    use png::HasParameters;
+
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set(png::TRANSFORM_IDENTITY);
+    let (info, mut reader) = decoder.read_info().unwrap();
+
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+
+    ((info.width as usize, info.height as usize), pixels)
+
+}
+
+#[test]
+fn test_write_counts() {
+    let bounds = (5, 3);
+    let counts: Vec<Option<usize>> = (0..15).map(|i| if i % 4 == 0 { None } else { Some(i) }).collect();
+    let directory = std::env::temp_dir();
+
+    let csv = directory.join("mandelbrot_test_write_counts.csv");
+    write_counts(csv.to_str().unwrap(), &counts, bounds, 255).unwrap();
+    let written = std::fs::read_to_string(&csv).unwrap();
+    assert_eq!(written.lines().count(), bounds.1);
+    assert!(written.lines().all(|line| line.split(',').count() == bounds.0));
+    assert_eq!(written.lines().next(), Some("255,1,2,3,255"));
+
+    let npy = directory.join("mandelbrot_test_write_counts.npy");
+    write_counts(npy.to_str().unwrap(), &counts, bounds, 255).unwrap();
+    let written = std::fs::read(&npy).unwrap();
+    assert!(written.starts_with(b"\x93NUMPY\x01\x00"));
+    let header_length = u16::from_le_bytes([written[8], written[9]]) as usize;
+    assert_eq!((10 + header_length) % 64, 0);
+    assert!(std::str::from_utf8(&written[10..10 + header_length]).unwrap().contains("'shape': (3, 5)"));
+    assert_eq!(written.len(), 10 + header_length + 8 * 15);
+    assert_eq!(&written[10 + header_length..10 + header_length + 16], &[255, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+
+    let error = write_counts("mandelbrot_test_write_counts.txt", &counts, bounds, 255).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_encode_raw16() {
+    let bounds = (7, 3);
+    let counts: Vec<Option<usize>> = (0..21).map(|i| if i == 0 { None } else { Some(i * 5000) }).collect();
+
+    let mut written = Vec::new();
+    encode_raw16(&mut written, &counts, 100000).unwrap();
+    assert_eq!(written.len(), 2 * bounds.0 * bounds.1);
+    assert_eq!(&written[..6], &[255, 255, 0x88, 0x13, 0x10, 0x27]);
+    assert_eq!(&written[written.len() - 2..], &[255, 255]);
+}
@@ -0,0 +1,161 @@
+use crate::Gradient;
+use std::str::FromStr;
+
+// Gradients are held inline rather than boxed, which would lose Copy.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    Grayscale,
+    Fire,
+    Ocean,
+    Ultra,
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Gradient(Gradient)
+}
+
+const ULTRA_STOPS: [(f64, [f64; 3]); 6] = [(0.0,    [0.0,   7.0,   100.0]),
+                                           (0.16,   [32.0,  107.0, 203.0]),
+                                           (0.42,   [237.0, 255.0, 255.0]),
+                                           (0.6425, [255.0, 170.0, 0.0]),
+                                           (0.8575, [0.0,   2.0,   0.0]),
+                                           (1.0,    [0.0,   7.0,   100.0])];
+
+// Matt Zucconi's degree 6 polynomial fits of matplotlib's perceptual colormaps,
+// lowest order first, which stay within a few levels of the published tables.
+const VIRIDIS: [[f64; 3]; 7] = [[0.2777273272234177, 0.005407344544966578, 0.3340998053353061],
+                                [0.1050930431085774, 1.404613529898575, 1.384590162594685],
+                                [-0.3308618287255563, 0.214847559468213, 0.09509516302823659],
+                                [-4.634230498983486, -5.799100973351585, -19.33244095627987],
+                                [6.228269936347081, 14.17993336680509, 56.69055260068105],
+                                [4.776384997670288, -13.74514537774601, -65.35303263337234],
+                                [-5.435455855934631, 4.645852612178535, 26.3124352495832]];
+const MAGMA: [[f64; 3]; 7] = [[-0.002136485053939582, -0.000749655052795221, -0.005386127855323933],
+                              [0.2516605407371642, 0.6775232436837668, 2.494026599312351],
+                              [8.353717279216625, -3.577719514958484, 0.3144679030132573],
+                              [-27.66873308576866, 14.26473078096533, -13.64921318813922],
+                              [52.17613981234068, -27.94360607168351, 12.94416944238394],
+                              [-50.76852536473588, 29.04658282127291, 4.23415299384598],
+                              [18.65570506591883, -11.48977351997711, -5.601961508734096]];
+const INFERNO: [[f64; 3]; 7] = [[0.0002189403691192265, 0.001651004631001012, -0.01948089843709184],
+                                [0.1065134194856116, 0.5639564367884091, 3.932712388889277],
+                                [11.60249308247187, -3.972853965665698, -15.9423941062914],
+                                [-41.70399613139459, 17.43639888205313, 44.35414519872813],
+                                [77.162935699427, -33.40235894210092, -81.80730925738993],
+                                [-71.31942824499214, 32.62606426397723, 73.20951985803202],
+                                [25.13112622477341, -12.24266895238567, -23.07032500287172]];
+const PLASMA: [[f64; 3]; 7] = [[0.05873234392399702, 0.02333670892565664, 0.5433401826748754],
+                               [2.176514634195958, 0.2383834171260182, 0.7539604599784036],
+                               [-2.689460476458034, -7.455851135738909, 3.110799939717086],
+                               [6.130348345893603, 42.3461881477227, -28.51885465332158],
+                               [-11.10743619062271, -82.66631109428045, 60.13984767418263],
+                               [10.02306557647065, 71.4136177009535, -54.07218655560067],
+                               [-3.658713842777788, -22.93153465461149, 18.19190778539828]];
+
+impl Palette {
+
+    pub const NAMES: [&'static str; 8] = ["grayscale", "fire", "ocean", "ultra", "viridis", "magma", "inferno", "plasma"];
+
+    pub const COLORMAPS: [&'static str; 4] = ["viridis", "magma", "inferno", "plasma"];
+
+    // The name it is parsed from, which gradients loaded from a file lack.
+    pub fn name(&self) -> Option<&'static str> {
+        Palette::NAMES.iter().copied().find(|name| name.parse::<Palette>() == Ok(*self))
+    }
+
+    pub fn is_grayscale(&self) -> bool {
+        *self == Palette::Grayscale
+    }
+
+    pub fn color(&self, t: f64) -> [u8; 3] {
+        self.levels(t).map(|level| level.round() as u8)
+    }
+
+    // The color before it is rounded to bytes, each channel between 0 and 255.
+    pub fn levels(&self, t: f64) -> [f64; 3] {
+
+        let t = t.clamp(0.0, 1.0);
+
+        match *self {
+            Palette::Grayscale => [to_level(t); 3],
+            Palette::Fire => [to_level(3.0 * t), to_level(3.0 * t - 1.0), to_level(3.0 * t - 2.0)],
+            Palette::Ocean => [to_level(2.0 * t - 1.0), to_level(1.5 * t - 0.25), to_level(0.2 + 1.6 * t)],
+            Palette::Ultra => {
+                let index = ULTRA_STOPS.iter().position(|stop| stop.0 >= t).unwrap_or(ULTRA_STOPS.len() - 1).max(1);
+                let (start, from) = ULTRA_STOPS[index - 1];
+                let (end, to) = ULTRA_STOPS[index];
+                let f = (t - start) / (end - start);
+                [0, 1, 2].map(|channel| from[channel] + f * (to[channel] - from[channel]))
+            },
+            Palette::Viridis => polynomial(&VIRIDIS, t),
+            Palette::Magma => polynomial(&MAGMA, t),
+            Palette::Inferno => polynomial(&INFERNO, t),
+            Palette::Plasma => polynomial(&PLASMA, t),
+            Palette::Gradient(gradient) => gradient.levels(t)
+        }
+
+    }
+
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grayscale" => Ok(Palette::Grayscale),
+            "fire" => Ok(Palette::Fire),
+            "ocean" => Ok(Palette::Ocean),
+            "ultra" => Ok(Palette::Ultra),
+            "viridis" => Ok(Palette::Viridis),
+            "magma" => Ok(Palette::Magma),
+            "inferno" => Ok(Palette::Inferno),
+            "plasma" => Ok(Palette::Plasma),
+            _ => Err(format!("Unknown palette {}, expected one of {}", s, Palette::NAMES.join(", ")))
+        }
+    }
+}
+
+fn to_level(value: f64) -> f64 {
+    value.clamp(0.0, 1.0) * 255.0
+}
+
+fn polynomial(coefficients: &[[f64; 3]; 7], t: f64) -> [f64; 3] {
+    [0, 1, 2].map(|channel| to_level(coefficients.iter().rev().fold(0.0, |value, coefficient| value * t + coefficient[channel])))
+}
+
+#[test]
+fn test_palette_from_str() {
+    for name in Palette::NAMES.iter() {
+        assert!(name.parse::<Palette>().is_ok());
+    }
+    assert_eq!("fire".parse::<Palette>(), Ok(Palette::Fire));
+    assert!("rainbow".parse::<Palette>().is_err());
+}
+
+#[test]
+fn test_palette_color() {
+    assert_eq!(Palette::Grayscale.color(0.0), [0, 0, 0]);
+    assert_eq!(Palette::Grayscale.color(128.0 / 255.0), [128, 128, 128]);
+    assert_eq!(Palette::Grayscale.color(1.0), [255, 255, 255]);
+    assert_eq!(Palette::Fire.color(0.0), [0, 0, 0]);
+    assert_eq!(Palette::Fire.color(1.0), [255, 255, 255]);
+    assert_eq!(Palette::Ultra.color(0.0), [0, 7, 100]);
+    assert_eq!(Palette::Ultra.color(0.42), [237, 255, 255]);
+    assert_eq!(Palette::Ultra.color(2.0), [0, 7, 100]);
+}
+
+#[test]
+fn test_colormap_luminance() {
+    for name in Palette::COLORMAPS.iter() {
+        let palette = name.parse::<Palette>().unwrap();
+        let luminance: Vec<f64> = (0..=64).map(|step| {
+            let [red, green, blue] = palette.color(step as f64 / 64.0);
+            0.2126 * red as f64 + 0.7152 * green as f64 + 0.0722 * blue as f64
+        }).collect();
+        assert!(luminance.windows(2).all(|pair| pair[0] < pair[1]), "{} is not monotonic", name);
+    }
+    assert_eq!(Palette::Viridis.color(1.0), [252, 231, 33]);
+}
@@ -0,0 +1,201 @@
+use crate::{biased_smooth_time, cast, deep_view, float, linear_shade, pixel_to_point, render_deep, shade, shade_color, smooth_shade, write_pixel};
+use crate::{Coloring, Config, DoubleDouble, Fractal};
+use num::Complex;
+use num::traits::{ToPrimitive, Zero};
+use rayon::prelude::*;
+
+// Pauldelbrot's criterion : once a pixel's orbit comes this close to zero
+// relative to the reference orbit, its f64 delta has lost too many digits.
+const GLITCH_TOLERANCE: f64 = 1e-6;
+
+// The series is followed while its cubic term stays this small next to the
+// linear one over the whole view.
+const SERIES_TOLERANCE: f64 = 1e-12;
+
+// Renders the same view as `render_deep`, but only the orbit of the center is
+// iterated in double-double precision. Every pixel then iterates its small
+// f64 offset from that reference orbit, starting past the iterations a cubic
+// series approximation can skip. Glitched pixels are recomputed directly in
+// double-double precision, and their count is returned.
+//
+// Other fractals, colorings and supersampling fall back to `render_deep`.
+pub fn render_perturbation(pixels: &mut [u8],
+                           bounds: (usize, usize),
+                           center: Complex<f64>,
+                           zoom: f64,
+                           config: Config)
+   -> usize
+{
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * config.bytes_per_pixel());
+
+    if config.fractal != Fractal::Mandelbrot || config.power != 2 || config.supersample > 1
+       || !matches!(config.coloring, Coloring::EscapeTime | Coloring::Smooth) {
+        render_deep(pixels, bounds, center, zoom, config);
+        return 0;
+    }
+
+    let center: Complex<DoubleDouble> = cast(center);
+    let (upper_left, lower_right) = deep_view(bounds, center, zoom);
+    let pixel_size = ((lower_right.re - upper_left.re) / float(bounds.0 as f64)).to_f64().unwrap();
+    let bailout_sqr = config.bailout * config.bailout;
+
+    let orbit = reference_orbit(center, config.limit, bailout_sqr);
+    let series = Series::new(&orbit, to_f64(lower_right - center).norm());
+
+    pixels.par_chunks_mut((bounds.0 * config.bytes_per_pixel()).max(1))
+          .enumerate()
+          .map(|(row, line)| {
+
+              let mut glitches = 0;
+
+              for (column, pixel) in line.chunks_mut(config.bytes_per_pixel()).enumerate() {
+
+                  let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+
+                  let shade = match perturbed_escape(&orbit, &series, to_f64(point - center), config.limit, bailout_sqr) {
+                      Some(escape) => escape.map(|(time, z)| match config.coloring {
+                          Coloring::Smooth => smooth_shade(biased_smooth_time(time, z, to_f64(point), config.power, false, config.smooth_bias), config),
+                          _ => linear_shade(time, config)
+                      }),
+                      None => {
+                          glitches += 1;
+                          shade(point, pixel_size, config)
+                      }
+                  };
+
+                  write_pixel(pixel, shade_color(shade, config), config);
+
+              }
+
+              glitches
+
+          })
+          .sum()
+
+}
+
+fn to_f64(c: Complex<DoubleDouble>) -> Complex<f64> {
+    Complex { re: c.re.to_f64().unwrap(), im: c.im.to_f64().unwrap() }
+}
+
+// The orbit of `c` rounded to f64, up to and including the first escaped
+// iterate, or `limit` iterates when it does not escape.
+fn reference_orbit(c: Complex<DoubleDouble>, limit: usize, bailout_sqr: f64) -> Vec<Complex<f64>> {
+
+    let mut orbit = Vec::with_capacity(limit);
+    let mut z = Complex { re: DoubleDouble::zero(), im: DoubleDouble::zero() };
+
+    for _ in 0..limit {
+        let rounded = to_f64(z);
+        orbit.push(rounded);
+        if rounded.norm_sqr() > bailout_sqr {
+            break;
+        }
+        z = z * z + c;
+    }
+
+    orbit
+
+}
+
+// Coefficients of the offset after `skip` iterations as a polynomial in the
+// offset of `c`, delta = a dc + b dc^2 + c dc^3.
+struct Series {
+    skip: usize,
+    a: Complex<f64>,
+    b: Complex<f64>,
+    c: Complex<f64>
+}
+
+impl Series {
+
+    fn new(orbit: &[Complex<f64>], radius: f64) -> Self {
+
+        let zero = Complex { re: 0.0, im: 0.0 };
+        let mut series = Series { skip: 0, a: zero, b: zero, c: zero };
+
+        while series.skip + 1 < orbit.len() {
+
+            let z = orbit[series.skip] * 2.0;
+            let a = z * series.a + 1.0;
+            let b = z * series.b + series.a * series.a;
+            let c = z * series.c + series.a * series.b * 2.0;
+
+            // Also stops once the coefficients overflow to infinity or NaN.
+            let accurate = c.norm() * radius * radius < SERIES_TOLERANCE * a.norm();
+            if !accurate {
+                break;
+            }
+
+            series = Series { skip: series.skip + 1, a, b, c };
+
+        }
+
+        series
+
+    }
+
+    fn delta(&self, dc: Complex<f64>) -> Complex<f64> {
+        ((self.c * dc + self.b) * dc + self.a) * dc
+    }
+
+}
+
+// Like `escape`, but iterating the offset from the reference orbit. Returns
+// None when the pixel glitches or outlives the reference orbit.
+fn perturbed_escape(orbit: &[Complex<f64>],
+                    series: &Series,
+                    dc: Complex<f64>,
+                    limit: usize,
+                    bailout_sqr: f64)
+   -> Option<Option<(usize, Complex<f64>)>>
+{
+
+    let mut delta = series.delta(dc);
+
+    for i in series.skip..limit {
+
+        let reference = *orbit.get(i)?;
+        let z = reference + delta;
+
+        if z.norm_sqr() > bailout_sqr {
+            return Some(Some((i, z)));
+        }
+        if z.norm_sqr() < GLITCH_TOLERANCE * reference.norm_sqr() {
+            return None;
+        }
+
+        delta = (reference * 2.0 + delta) * delta + dc;
+
+    }
+
+    Some(None)
+
+}
+
+#[test]
+fn test_render_perturbation() {
+    let bounds = (48, 32);
+    let center = Complex { re: -0.743643887037151, im: 0.13182590420533 };
+    let zoom = 1e9;
+
+    let orbit = reference_orbit(cast(center), 2000, 4.0);
+    assert_eq!(orbit.len(), 2000);
+    assert!(Series::new(&orbit, 4.0 / zoom).skip > 0);
+
+    for coloring in [Coloring::EscapeTime, Coloring::Smooth] {
+        let config = Config { limit: 2000, coloring, ..Config::default() };
+
+        let mut expected = vec![0; bounds.0 * bounds.1];
+        render_deep(&mut expected, bounds, center, zoom, config);
+
+        let mut actual = vec![0; bounds.0 * bounds.1];
+        render_perturbation(&mut actual, bounds, center, zoom, config);
+
+        // A few pixels on the boundary run for nearly the whole limit, long
+        // enough for rounding to change when they escape.
+        let differing = expected.iter().zip(&actual).filter(|(expected, actual)| expected != actual).count();
+        assert!(differing * 100 <= expected.len(), "{} of {} pixels differ", differing, expected.len());
+    }
+}
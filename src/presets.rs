@@ -0,0 +1,52 @@
+use num::Complex;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub center: Complex<f64>,
+    pub zoom: f64,
+    pub iterations: usize
+}
+
+pub const PRESETS: [Preset; 4] = [
+    Preset { name: "seahorse-valley", center: Complex { re: -0.7435, im: 0.1314 }, zoom: 200.0, iterations: 500 },
+    Preset { name: "elephant-valley", center: Complex { re: 0.2925, im: -0.0149 }, zoom: 100.0, iterations: 500 },
+    Preset { name: "triple-spiral", center: Complex { re: -0.088, im: 0.654 }, zoom: 200.0, iterations: 1000 },
+    Preset { name: "mini-mandelbrot", center: Complex { re: -1.7687, im: 0.0 }, zoom: 100.0, iterations: 500 }
+];
+
+pub fn find_preset(name: &str) -> Result<Preset, String> {
+    PRESETS.iter()
+           .find(|preset| preset.name == name)
+           .copied()
+           .ok_or_else(|| {
+               let names: Vec<&str> = PRESETS.iter().map(|preset| preset.name).collect();
+               format!("Unknown preset {}, expected one of {}", name, names.join(", "))
+           })
+}
+
+#[test]
+fn test_presets() {
+    use crate::{escape_time, pixel_to_point, validate_bounds, view_from_center};
+
+    let bounds = (160, 90);
+
+    for preset in PRESETS.iter() {
+        assert_eq!(find_preset(preset.name), Ok(*preset));
+
+        let (upper_left, lower_right) = view_from_center(bounds, preset.center, preset.zoom);
+        assert!(validate_bounds(bounds, upper_left, lower_right).is_ok());
+        assert!(lower_right.re - upper_left.re < 0.1);
+
+        // Every preset frames the boundary, so it shows both escaping and
+        // non-escaping points.
+        let times: Vec<Option<usize>> = (0..bounds.1).flat_map(|row| (0..bounds.0).map(move |column| (column, row)))
+                                                     .map(|pixel| escape_time(pixel_to_point(bounds, pixel, upper_left, lower_right), preset.iterations))
+                                                     .collect();
+        assert!(times.iter().any(|time| time.is_none()), "{}", preset.name);
+        assert!(times.iter().any(|time| time.is_some()), "{}", preset.name);
+    }
+
+    let error = find_preset("cauliflower").unwrap_err();
+    assert!(error.contains("seahorse-valley") && error.contains("mini-mandelbrot"));
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// What a thread's slot holds while it is between rows.
+pub(crate) const IDLE: usize = usize::MAX;
+
+// Which rows the render threads were on at each tick of a sampling clock. The
+// rows are only timed in ticks, which is coarse, but it takes no external
+// tools and maps the time straight back onto the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    /// Ticks at which some thread was rendering each row.
+    pub samples: Vec<usize>,
+    pub interval: Duration
+}
+
+impl Profile {
+
+    pub fn total(&self) -> usize {
+        self.samples.iter().sum()
+    }
+
+    // The samples of `count` consecutive bands of rows, as even as possible,
+    // which together cover every row.
+    pub fn bands(&self, count: usize) -> Vec<(Range<usize>, usize)> {
+
+        let rows = self.samples.len();
+        let count = count.clamp(1, rows.max(1));
+
+        (0..count).map(|band| band * rows / count..(band + 1) * rows / count)
+                  .map(|range| (range.clone(), self.samples[range].iter().sum()))
+                  .collect()
+
+    }
+
+}
+
+// Bands reported by `Display`.
+const BANDS: usize = 10;
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        let total = self.total();
+        writeln!(f, "{} samples every {:?}", total, self.interval)?;
+
+        for (range, samples) in self.bands(BANDS) {
+            let share = if total > 0 { samples as f64 / total as f64 } else { 0.0 };
+            writeln!(f, "  rows {:>5} to {:>5} : {:>5.1}% {}",
+                     range.start, range.end.saturating_sub(1), share * 100.0, "#".repeat((share * 50.0).round() as usize))?;
+        }
+
+        Ok(())
+
+    }
+}
+
+// Every `interval` until `done`, starting at once, counts a sample for the
+// row in each thread's slot. Slots hold `IDLE` between rows.
+pub(crate) fn sample_rows(working: &[AtomicUsize], done: &AtomicBool, rows: usize, interval: Duration) -> Vec<usize> {
+
+    let mut samples = vec![0; rows];
+
+    loop {
+        for slot in working {
+            let row = slot.load(Ordering::Relaxed);
+            if row < rows {
+                samples[row] += 1;
+            }
+        }
+        if done.load(Ordering::Relaxed) {
+            break;
+        }
+        thread::sleep(interval);
+    }
+
+    samples
+
+}
+
+#[test]
+fn test_profile_bands() {
+    let profile = Profile { samples: vec![0, 3, 1, 0, 0, 4, 2], interval: Duration::from_millis(1) };
+    assert_eq!(profile.total(), 10);
+    assert_eq!(profile.bands(3), vec![(0..2, 3), (2..4, 1), (4..7, 6)]);
+    assert_eq!(profile.bands(100).len(), 7);
+
+    let report = profile.to_string();
+    assert!(report.starts_with("10 samples every 1ms\n"));
+    assert!(report.contains("rows     5 to     5 :  40.0% ####################\n"));
+
+    // Every sample lands in exactly one band, and the shares add up to 100%.
+    let profile = Profile { samples: (0..90).map(|row| row * 7 % 13).collect(), interval: Duration::from_micros(50) };
+    for count in [1, 7, 10, 90] {
+        let bands = profile.bands(count);
+        assert_eq!(bands.iter().map(|(_, samples)| samples).sum::<usize>(), profile.total());
+        assert_eq!(bands.iter().map(|(range, _)| range.len()).sum::<usize>(), 90);
+    }
+    let shares: f64 = profile.to_string()
+                             .lines()
+                             .skip(1)
+                             .map(|line| line.split(':').nth(1).unwrap().trim().split('%').next().unwrap().parse::<f64>().unwrap())
+                             .sum();
+    assert!((shares - 100.0).abs() <= 0.5, "{}", shares);
+
+    let empty = Profile { samples: Vec::new(), interval: Duration::from_millis(1) };
+    assert_eq!(empty.bands(BANDS), vec![(0..0, 0)]);
+}
+
+#[test]
+fn test_sample_rows() {
+    // A slot already on a row is counted before the first sleep, so even a
+    // render that is done at once gets its sample.
+    let working = [AtomicUsize::new(2), AtomicUsize::new(IDLE)];
+    assert_eq!(sample_rows(&working, &AtomicBool::new(true), 4, Duration::from_secs(60)), vec![0, 0, 1, 0]);
+}
@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct Progress {
+    completed: AtomicUsize,
+    total: usize
+}
+
+impl Progress {
+
+    pub fn new(total: usize) -> Self {
+        Progress { completed: AtomicUsize::new(0), total }
+    }
+
+    pub fn completed(&self) -> usize {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    pub fn tick(&self) {
+
+        let done = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        let percent = done * 100 / self.total.max(1);
+
+        if percent != (done - 1) * 100 / self.total.max(1) {
+            eprint!("\rRendering... {:3}%", percent);
+            if done == self.total {
+                eprintln!();
+            }
+        }
+
+    }
+
+}
+
+#[test]
+fn test_progress_tick() {
+    let progress = Progress::new(3);
+    for _ in 0..3 {
+        progress.tick();
+    }
+    assert_eq!(progress.completed(), 3);
+}
@@ -0,0 +1,193 @@
+use crate::{parse_bounds, parse_complex, read_png_text, Palette, METADATA_KEYWORD};
+use num::Complex;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Only the flat `key = value` subset of TOML is understood, which is all a
+// render description needs : strings, integers, comments and blank lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderConfig {
+    pub output: Option<String>,
+    pub bounds: Option<(usize, usize)>,
+    pub upper_left: Option<Complex<f64>>,
+    pub lower_right: Option<Complex<f64>>,
+    pub iterations: Option<usize>,
+    pub palette: Option<Palette>
+}
+
+impl RenderConfig {
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read config {} : {}", path.display(), error))?;
+
+        contents.parse().map_err(|message| format!("Invalid config {} : {}", path.display(), message))
+
+    }
+
+    // The parameters embedded in a PNG written with `write_image_with_metadata`.
+    pub fn from_image<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {} : {}", path.display(), error))?;
+        let text = read_png_text(&bytes, METADATA_KEYWORD)
+            .ok_or_else(|| format!("{} has no render parameters", path.display()))?;
+
+        text.parse().map_err(|message| format!("Invalid parameters in {} : {}", path.display(), message))
+
+    }
+
+}
+
+// Written back in the form it is parsed from, leaving out unset keys and
+// palettes without a name.
+impl fmt::Display for RenderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        if let Some(output) = &self.output {
+            writeln!(f, "output = \"{}\"", output)?;
+        }
+        if let Some((width, height)) = self.bounds {
+            writeln!(f, "bounds = \"{}x{}\"", width, height)?;
+        }
+        if let Some(upper_left) = self.upper_left {
+            writeln!(f, "upper_left = \"{},{}\"", upper_left.re, upper_left.im)?;
+        }
+        if let Some(lower_right) = self.lower_right {
+            writeln!(f, "lower_right = \"{},{}\"", lower_right.re, lower_right.im)?;
+        }
+        if let Some(iterations) = self.iterations {
+            writeln!(f, "iterations = {}", iterations)?;
+        }
+        if let Some(name) = self.palette.and_then(|palette| palette.name()) {
+            writeln!(f, "palette = \"{}\"", name)?;
+        }
+
+        Ok(())
+
+    }
+}
+
+impl FromStr for RenderConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        let mut config = RenderConfig::default();
+
+        for (number, line) in s.lines().enumerate() {
+
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                None => return Err(format!("line {} : expected key = value", number + 1)),
+                Some(index) => (line[..index].trim(), unquote(line[index + 1..].trim()))
+            };
+            let invalid = || format!("line {} : invalid {} value '{}'", number + 1, key, value);
+
+            match key {
+                "output" => config.output = Some(value.to_string()),
+                "bounds" => config.bounds = Some(parse_bounds(value).map_err(|_| invalid())?),
+                "upper_left" => config.upper_left = Some(parse_complex(value).map_err(|_| invalid())?),
+                "lower_right" => config.lower_right = Some(parse_complex(value).map_err(|_| invalid())?),
+                "iterations" => config.iterations = Some(value.parse().map_err(|_| invalid())?),
+                "palette" => config.palette = Some(value.parse()?),
+                _ => return Err(format!("line {} : unknown key {}", number + 1, key))
+            }
+
+        }
+
+        Ok(config)
+
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+
+}
+
+fn unquote(value: &str) -> &str {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+#[test]
+fn test_render_config_from_str() {
+    let sample = "# Seahorse valley\n\
+                  output = \"seahorse.png\"\n\
+                  bounds = \"800x600\"\n\
+                  upper_left = \"-0.8,0.2\" # inline comment\n\
+                  lower_right = \"-0.7,0.1\"\n\
+                  \n\
+                  iterations = 1000\n\
+                  palette = \"ultra\"\n";
+
+    assert_eq!(sample.parse::<RenderConfig>(), Ok(RenderConfig {
+        output: Some("seahorse.png".to_string()),
+        bounds: Some((800, 600)),
+        upper_left: Some(Complex { re: -0.8, im: 0.2 }),
+        lower_right: Some(Complex { re: -0.7, im: 0.1 }),
+        iterations: Some(1000),
+        palette: Some(Palette::Ultra)
+    }));
+
+    assert_eq!("iterations = 10".parse::<RenderConfig>(),
+               Ok(RenderConfig { iterations: Some(10), ..RenderConfig::default() }));
+    assert!("iterations = many".parse::<RenderConfig>().is_err());
+    assert!("zoom = 2".parse::<RenderConfig>().is_err());
+    assert!("bounds".parse::<RenderConfig>().is_err());
+}
+
+#[test]
+fn test_render_config_from_image() {
+    use crate::{write_image, write_image_with_metadata, write_streamed_png, Config};
+    use image::ColorType;
+
+    let config = RenderConfig {
+        output: None,
+        bounds: Some((12, 9)),
+        upper_left: Some(Complex { re: -0.7453, im: 0.1127 }),
+        lower_right: Some(Complex { re: -0.7451, im: 0.11255 }),
+        iterations: Some(2000),
+        palette: Some(Palette::Inferno)
+    };
+    assert_eq!(config.to_string().parse::<RenderConfig>(), Ok(config.clone()));
+
+    let directory = std::env::temp_dir();
+    let tagged = directory.join("mandelbrot_test_metadata.png");
+    let pixels: Vec<u8> = (0..108).collect();
+    write_image_with_metadata(tagged.to_str().unwrap(), &pixels, (12, 9), ColorType::Gray(8), &config.to_string()).unwrap();
+
+    assert_eq!(RenderConfig::from_image(&tagged), Ok(config.clone()));
+    assert_eq!(image::open(&tagged).unwrap().to_luma().into_raw(), pixels);
+
+    // Streamed renders carry the same chunk.
+    let streamed = directory.join("mandelbrot_test_metadata_streamed.png");
+    let (upper_left, lower_right) = (config.upper_left.unwrap(), config.lower_right.unwrap());
+    write_streamed_png(streamed.to_str().unwrap(), (12, 9), upper_left, lower_right, Config::default(), Some(&config.to_string())).unwrap();
+    assert_eq!(RenderConfig::from_image(&streamed), Ok(config));
+
+    let untagged = directory.join("mandelbrot_test_metadata_untagged.png");
+    write_image(untagged.to_str().unwrap(), &pixels, (12, 9), ColorType::Gray(8)).unwrap();
+    assert!(RenderConfig::from_image(&untagged).unwrap_err().ends_with("has no render parameters"));
+}
@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+
+// Lobes on each side of the Lanczos window.
+const LOBES: f64 = 3.0;
+
+fn lanczos(x: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= LOBES {
+        return 0.0;
+    }
+    let x = x * PI;
+    LOBES * x.sin() * (x / LOBES).sin() / (x * x)
+}
+
+// Resizes an image of `channels` samples per pixel, each `depth` bits wide
+// and big-endian as the renderers store them, with a separable Lanczos
+// filter. When shrinking, the filter is widened to cover every source pixel.
+pub fn lanczos_resize(pixels: &[u8],
+                      from: (usize, usize),
+                      to: (usize, usize),
+                      channels: usize,
+                      depth: u8)
+   -> Vec<u8>
+{
+
+    let width = depth as usize / 8;
+    assert!(pixels.len() == from.0 * from.1 * channels * width);
+
+    let samples: Vec<f64> = pixels.chunks(width)
+                                  .map(|sample| sample.iter().fold(0, |value, byte| value << 8 | *byte as u32) as f64)
+                                  .collect();
+
+    // Rows first, then columns of the narrowed image.
+    let narrowed = resize_axis(&samples, (from.0, from.1), to.0, channels, (channels, from.0 * channels), (channels, to.0 * channels));
+    let resized = resize_axis(&narrowed, (from.1, to.0), to.1, channels, (to.0 * channels, channels), (to.0 * channels, channels));
+
+    let max = ((1u32 << depth) - 1) as f64;
+    resized.iter()
+           .flat_map(|sample| (sample.round().clamp(0.0, max) as u16).to_be_bytes()[2 - width..].to_vec())
+           .collect()
+
+}
+
+// Resamples `lines` lines of `length` pixels to `target` pixels each. Strides
+// give the distance between neighbouring pixels and between lines, in samples,
+// of the source and of the result.
+fn resize_axis(samples: &[f64],
+               (length, lines): (usize, usize),
+               target: usize,
+               channels: usize,
+               source_stride: (usize, usize),
+               result_stride: (usize, usize))
+   -> Vec<f64>
+{
+
+    let scale = length as f64 / target as f64;
+    let support = scale.max(1.0);
+
+    // Each result pixel's source range and normalized weights.
+    let filters: Vec<(usize, Vec<f64>)> = (0..target).map(|index| {
+        let center = (index as f64 + 0.5) * scale;
+        let first = ((center - LOBES * support).floor().max(0.0)) as usize;
+        let last = ((center + LOBES * support).ceil() as usize).min(length);
+        let weights: Vec<f64> = (first..last).map(|source| lanczos((source as f64 + 0.5 - center) / support)).collect();
+        let total: f64 = weights.iter().sum();
+        (first, weights.iter().map(|weight| weight / total).collect())
+    }).collect();
+
+    let mut result = vec![0.0; target * lines * channels];
+
+    for line in 0..lines {
+        for (index, (first, weights)) in filters.iter().enumerate() {
+            for channel in 0..channels {
+                let value: f64 = weights.iter()
+                                        .enumerate()
+                                        .map(|(offset, weight)| weight * samples[line * source_stride.1 + (first + offset) * source_stride.0 + channel])
+                                        .sum();
+                result[line * result_stride.1 + index * result_stride.0 + channel] = value;
+            }
+        }
+    }
+
+    result
+
+}
+
+#[test]
+fn test_lanczos_resize() {
+    // A horizontal ramp from 0 to 252, four times too wide.
+    let from = (64, 8);
+    let pixels: Vec<u8> = (0..from.0 * from.1).map(|index| (index % from.0 * 4) as u8).collect();
+
+    let resized = lanczos_resize(&pixels, from, (16, 2), 1, 8);
+    assert_eq!(resized.len(), 32);
+
+    // Away from the edges the filter reproduces the ramp at the new pixel
+    // centers, the mean of each group of four source pixels.
+    for (column, value) in resized[3..13].iter().enumerate() {
+        let expected = (column + 3) as f64 * 16.0 + 6.0;
+        assert!((*value as f64 - expected).abs() <= 1.0, "{} expected near {}", value, expected);
+    }
+    assert_eq!(&resized[..16], &resized[16..]);
+
+    // 16-bit samples keep their byte order, and flat images stay flat.
+    let flat: Vec<u8> = [0x12, 0x34].iter().copied().cycle().take(12 * 12 * 3 * 2).collect();
+    assert!(lanczos_resize(&flat, (12, 12), (5, 4), 3, 16).chunks(2).all(|sample| sample == [0x12, 0x34]));
+}
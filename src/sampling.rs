@@ -0,0 +1,117 @@
+use crate::buddhabrot::SplitMix;
+use std::str::FromStr;
+
+// Where the supersamples of a pixel fall in its unit cell. Random and Sobol
+// patterns differ from pixel to pixel, reproducibly from their seed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    Grid,
+    Random(u64),
+    Sobol(u64)
+}
+
+impl Sampling {
+
+    pub const NAMES: [&'static str; 3] = ["grid", "random", "sobol"];
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        match self {
+            Sampling::Grid => Sampling::Grid,
+            Sampling::Random(_) => Sampling::Random(seed),
+            Sampling::Sobol(_) => Sampling::Sobol(seed)
+        }
+    }
+
+    // The samples * samples offsets of a pixel, each in [0, 1) on both axes.
+    pub fn offsets(&self, pixel: (usize, usize), samples: usize) -> Vec<(f64, f64)> {
+
+        let count = samples * samples;
+        let stream = ((pixel.1 as u64) << 32) | pixel.0 as u64;
+
+        match *self {
+            Sampling::Grid => (0..count).map(|index| ((index % samples) as f64 / samples as f64, (index / samples) as f64 / samples as f64))
+                                        .collect(),
+            Sampling::Random(seed) => {
+                let mut random = SplitMix::new(seed, stream);
+                (0..count).map(|_| (random.next(), random.next())).collect()
+            },
+            Sampling::Sobol(seed) => {
+                // Scrambling every point with the same random bits keeps them
+                // a low-discrepancy set, so still distinct.
+                let mut random = SplitMix::new(seed, stream);
+                let scramble = [random.next(), random.next()].map(|value| (value * SOBOL_SCALE) as u32);
+                (0..count).map(|index| {
+                              let [x, y] = sobol(index as u32);
+                              ((x ^ scramble[0]) as f64 / SOBOL_SCALE, (y ^ scramble[1]) as f64 / SOBOL_SCALE)
+                          })
+                          .collect()
+            }
+        }
+
+    }
+
+}
+
+const SOBOL_SCALE: f64 = 4_294_967_296.0;
+
+// The first two dimensions of the Sobol sequence as 32 bit fractions : the
+// van der Corput sequence, then the one of the primitive polynomial x + 1.
+fn sobol(index: u32) -> [u32; 2] {
+
+    let mut point = [0, 0];
+    let mut direction = 1 << 31;
+
+    for bit in 0..32 {
+        if index >> bit & 1 == 1 {
+            point[0] ^= 1 << (31 - bit);
+            point[1] ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+
+    point
+
+}
+
+impl FromStr for Sampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(Sampling::Grid),
+            "random" => Ok(Sampling::Random(0)),
+            "sobol" => Ok(Sampling::Sobol(0)),
+            _ => Err(format!("Unknown sampling {}, expected one of {}", s, Sampling::NAMES.join(", ")))
+        }
+    }
+}
+
+#[test]
+fn test_sampling_offsets() {
+    for sampling in [Sampling::Grid, Sampling::Random(3), Sampling::Sobol(3)] {
+        for samples in [1, 2, 3, 4, 8] {
+            let offsets = sampling.offsets((5, 7), samples);
+            assert_eq!(offsets.len(), samples * samples, "{:?}", sampling);
+            assert!(offsets.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)), "{:?}", sampling);
+
+            let mut distinct = offsets.clone();
+            distinct.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            distinct.dedup();
+            assert_eq!(distinct.len(), offsets.len(), "{:?} with {} samples", sampling, samples);
+        }
+    }
+
+    // The first Sobol points split the cell evenly between its quadrants.
+    let quadrants: Vec<(bool, bool)> = Sampling::Sobol(0).offsets((0, 0), 2).iter().map(|&(x, y)| (x < 0.5, y < 0.5)).collect();
+    for quadrant in [(false, false), (false, true), (true, false), (true, true)] {
+        assert!(quadrants.contains(&quadrant));
+    }
+
+    assert_eq!(Sampling::Random(1).offsets((2, 2), 2), Sampling::Random(1).offsets((2, 2), 2));
+    assert_ne!(Sampling::Random(1).offsets((2, 2), 2), Sampling::Random(2).offsets((2, 2), 2));
+    assert_ne!(Sampling::Sobol(1).offsets((2, 2), 2), Sampling::Sobol(1).offsets((3, 2), 2));
+    assert_eq!(Sampling::Grid.offsets((0, 0), 2), vec![(0.0, 0.0), (0.5, 0.0), (0.0, 0.5), (0.5, 0.5)]);
+
+    assert_eq!("sobol".parse::<Sampling>().map(|sampling| sampling.with_seed(9)), Ok(Sampling::Sobol(9)));
+    assert!("halton".parse::<Sampling>().is_err());
+}
@@ -0,0 +1,99 @@
+use crate::parse_pair;
+use std::str::FromStr;
+
+// How the iteration limit of a zoom animation grows from frame to frame.
+// Linear and geometric schedules go from the base limit on the first frame to
+// the maximum on the last, while keyframes give the limit at chosen frames
+// and are interpolated linearly in between, held flat past either end.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IterationSchedule {
+    Linear,
+    Geometric,
+    Keyframes(Vec<(usize, usize)>)
+}
+
+impl IterationSchedule {
+
+    // The limit of each of `frames` frames, which must never decrease.
+    pub fn limits(&self, base: usize, max: usize, frames: usize) -> Result<Vec<usize>, String> {
+
+        let last = frames.saturating_sub(1).max(1) as f64;
+
+        let limits: Vec<usize> = (0..frames).map(|frame| {
+            let t = frame as f64 / last;
+            match self {
+                IterationSchedule::Linear => (base as f64 + t * (max as f64 - base as f64)).round() as usize,
+                IterationSchedule::Geometric => (base as f64 * (max as f64 / base as f64).powf(t)).round() as usize,
+                IterationSchedule::Keyframes(keyframes) => interpolate(keyframes, frame)
+            }
+        }).collect();
+
+        if let Some(frame) = limits.windows(2).position(|pair| pair[1] < pair[0]) {
+            return Err(format!("Iteration schedule decreases from {} at frame {} to {} at frame {}",
+                               limits[frame], frame, limits[frame + 1], frame + 1));
+        }
+        if limits.contains(&0) {
+            return Err("Iteration schedule reaches 0 iterations".to_string());
+        }
+
+        Ok(limits)
+
+    }
+
+}
+
+fn interpolate(keyframes: &[(usize, usize)], frame: usize) -> usize {
+
+    let after = keyframes.iter().position(|(key, _)| *key >= frame).unwrap_or(keyframes.len());
+    match (after.checked_sub(1).map(|before| keyframes[before]), keyframes.get(after)) {
+        (_, Some(&(key, limit))) if key == frame => limit,
+        (Some((from, low)), Some(&(to, high))) => {
+            let t = (frame - from) as f64 / (to - from) as f64;
+            (low as f64 + t * (high as f64 - low as f64)).round() as usize
+        },
+        (Some((_, limit)), None) | (None, Some(&(_, limit))) => limit,
+        (None, None) => 0
+    }
+
+}
+
+impl FromStr for IterationSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(IterationSchedule::Linear),
+            "geometric" => Ok(IterationSchedule::Geometric),
+            _ => {
+                let keyframes: Vec<(usize, usize)> = s.split(',')
+                    .map(|keyframe| parse_pair(keyframe, ':'))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("Invalid iteration schedule {}, expected linear, geometric or frame:limit keyframes like 0:255,100:2000", s))?;
+                if keyframes.windows(2).any(|pair| pair[1].0 <= pair[0].0) {
+                    return Err(format!("Keyframes of iteration schedule {} must be in increasing frame order", s));
+                }
+                Ok(IterationSchedule::Keyframes(keyframes))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_iteration_schedule() {
+    for schedule in [IterationSchedule::Linear, IterationSchedule::Geometric] {
+        let limits = schedule.limits(100, 6400, 7).unwrap();
+        assert_eq!((limits[0], limits[6]), (100, 6400), "{:?}", schedule);
+        assert!(limits.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(schedule.limits(500, 100, 7).unwrap_err().starts_with("Iteration schedule decreases from 500 at frame 0"));
+    }
+    assert_eq!(IterationSchedule::Linear.limits(100, 400, 4).unwrap(), vec![100, 200, 300, 400]);
+    assert_eq!(IterationSchedule::Geometric.limits(100, 800, 4).unwrap(), vec![100, 200, 400, 800]);
+    assert_eq!(IterationSchedule::Linear.limits(100, 400, 1).unwrap(), vec![100]);
+
+    let keyframes: IterationSchedule = "2:100,4:300,8:300".parse().unwrap();
+    assert_eq!(keyframes.limits(1, 1, 10).unwrap(), vec![100, 100, 100, 200, 300, 300, 300, 300, 300, 300]);
+    assert!("0:300,5:100".parse::<IterationSchedule>().unwrap().limits(1, 1, 6).is_err());
+    assert!("0:0,5:100".parse::<IterationSchedule>().unwrap().limits(1, 1, 6).is_err());
+    assert!("5:100,2:300".parse::<IterationSchedule>().is_err());
+    assert!("fast".parse::<IterationSchedule>().is_err());
+}
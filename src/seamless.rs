@@ -0,0 +1,87 @@
+// Makes an image tile seamlessly. Within `blend` pixels of each edge, every
+// pixel is cross-faded with its mirror image across the middle of the image,
+// half and half at the edges and fading out inward, so opposite edges end up
+// equal. Samples are `depth` bits wide and big-endian as the renderers store them.
+pub fn make_seamless(pixels: &mut [u8], bounds: (usize, usize), channels: usize, depth: u8, blend: usize) {
+
+    let width = depth as usize / 8;
+    assert!(pixels.len() == bounds.0 * bounds.1 * channels * width);
+
+    let mut samples: Vec<f64> = pixels.chunks(width)
+                                      .map(|sample| sample.iter().fold(0, |value, byte| value << 8 | *byte as u32) as f64)
+                                      .collect();
+
+    // Across the columns of each row, then across the rows of each column.
+    let row_length = bounds.0 * channels;
+    cross_fade(&mut samples, bounds.0, blend, channels, |x, channel| (0..bounds.1).map(move |row| row * row_length + x * channels + channel));
+    cross_fade(&mut samples, bounds.1, blend, channels, |y, channel| (0..bounds.0).map(move |column| y * row_length + column * channels + channel));
+
+    let max = ((1u32 << depth) - 1) as f64;
+    for (sample, value) in pixels.chunks_mut(width).zip(samples) {
+        sample.copy_from_slice(&(value.round().clamp(0.0, max) as u16).to_be_bytes()[2 - width..]);
+    }
+
+}
+
+// Blends the lines at positions `i` and `length - 1 - i` along one axis, the
+// samples of each line being given by `line`.
+fn cross_fade<F, I>(samples: &mut [f64], length: usize, blend: usize, channels: usize, line: F)
+    where F: Fn(usize, usize) -> I,
+          I: Iterator<Item = usize>
+{
+
+    let blend = blend.min(length / 2);
+
+    for i in 0..blend {
+        let weight = 0.5 * (1.0 - i as f64 / blend as f64);
+        for channel in 0..channels {
+            for (near, far) in line(i, channel).zip(line(length - 1 - i, channel)) {
+                let (a, b) = (samples[near], samples[far]);
+                samples[near] = (1.0 - weight) * a + weight * b;
+                samples[far] = (1.0 - weight) * b + weight * a;
+            }
+        }
+    }
+
+}
+
+#[test]
+fn test_make_seamless() {
+    use crate::{Config, Palette, render};
+    use num::Complex;
+
+    let bounds = (64, 48);
+    let (upper_left, lower_right) = (Complex { re: -0.8, im: 0.2 }, Complex { re: -0.7, im: 0.125 });
+
+    for config in [Config { palette: Palette::Fire, ..Config::default() }, Config { depth: 16, ..Config::default() }] {
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        render(&mut pixels, bounds, upper_left, lower_right, config);
+        let original = pixels.clone();
+
+        make_seamless(&mut pixels, bounds, config.channels(), config.depth, 8);
+
+        let (channels, width) = (config.channels(), config.depth as usize / 8);
+        let sample = |pixels: &[u8], x: usize, y: usize, channel: usize| {
+            let index = ((y * bounds.0 + x) * channels + channel) * width;
+            pixels[index..][..width].iter().fold(0, |value, byte| value << 8 | *byte as u32)
+        };
+
+        // Opposite edges match, and the middle is left alone.
+        let max = (1u32 << config.depth) - 1;
+        for channel in 0..channels {
+            for y in 0..bounds.1 {
+                assert!(sample(&pixels, 0, y, channel).abs_diff(sample(&pixels, bounds.0 - 1, y, channel)) <= 1);
+            }
+            for x in 0..bounds.0 {
+                assert!(sample(&pixels, x, 0, channel).abs_diff(sample(&pixels, x, bounds.1 - 1, channel)) <= 1);
+            }
+            assert_eq!(sample(&pixels, 32, 24, channel), sample(&original, 32, 24, channel));
+        }
+        assert!((0..bounds.1).any(|y| sample(&original, 0, y, 0).abs_diff(sample(&original, bounds.0 - 1, y, 0)) > max / 10));
+    }
+
+    // A blend wider than half the image is narrowed to it.
+    let mut pixels = vec![0, 100, 200];
+    make_seamless(&mut pixels, (3, 1), 1, 8, 10);
+    assert_eq!(pixels, vec![100, 100, 100]);
+}
@@ -0,0 +1,212 @@
+use crate::{Config, render_parallel};
+use crate::output::encode_png;
+use num::Complex;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+
+pub const TILE_SIZE: usize = 256;
+
+// Zoom level 0 is a single tile covering this square, which holds the whole
+// Mandelbrot set. Each level splits every tile of the previous one in four.
+const WORLD_UPPER_LEFT: Complex<f64> = Complex { re: -2.0, im: 2.0 };
+const WORLD_SIZE: f64 = 4.0;
+
+// Past this level, neighbouring pixels are no longer distinct f64 values.
+pub const MAX_ZOOM: u32 = 40;
+
+// The complex-plane corners of slippy-map tile x,y at zoom z, where x grows
+// to the right and y grows downwards, or None outside the map.
+pub fn tile_bounds(z: u32, x: u64, y: u64) -> Option<(Complex<f64>, Complex<f64>)> {
+
+    if z > MAX_ZOOM || x >= 1 << z || y >= 1 << z {
+        return None;
+    }
+
+    let size = WORLD_SIZE / (1u64 << z) as f64;
+    let upper_left = Complex { re: WORLD_UPPER_LEFT.re + x as f64 * size, im: WORLD_UPPER_LEFT.im - y as f64 * size };
+    let lower_right = Complex { re: upper_left.re + size, im: upper_left.im - size };
+
+    Some((upper_left, lower_right))
+
+}
+
+// Parses /z/x/y.png into its coordinates.
+pub fn parse_tile_path(path: &str) -> Option<(u32, u64, u64)> {
+
+    let mut parts = path.strip_prefix('/')?.strip_suffix(".png")?.split('/');
+    let tile = (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.parse().ok()?);
+
+    match parts.next() {
+        None => Some(tile),
+        Some(_) => None
+    }
+
+}
+
+// Encoded tiles, most recently used first.
+struct TileCache {
+    capacity: usize,
+    tiles: VecDeque<((u32, u64, u64), Vec<u8>)>
+}
+
+impl TileCache {
+
+    fn get(&mut self, tile: (u32, u64, u64)) -> Option<Vec<u8>> {
+        let index = self.tiles.iter().position(|(key, _)| *key == tile)?;
+        let entry = self.tiles.remove(index)?;
+        self.tiles.push_front(entry);
+        self.tiles.front().map(|(_, png)| png.clone())
+    }
+
+    fn insert(&mut self, tile: (u32, u64, u64), png: Vec<u8>) {
+        self.tiles.push_front((tile, png));
+        self.tiles.truncate(self.capacity);
+    }
+
+}
+
+pub struct TileServer {
+    config: Config,
+    cache: Mutex<TileCache>
+}
+
+impl TileServer {
+
+    pub fn new(config: Config, capacity: usize) -> Self {
+        TileServer { config, cache: Mutex::new(TileCache { capacity, tiles: VecDeque::new() }) }
+    }
+
+    // The PNG of a tile, rendered on the first request and then kept until
+    // enough other tiles have been requested since.
+    pub fn tile(&self, z: u32, x: u64, y: u64) -> Result<Option<Vec<u8>>, io::Error> {
+
+        let (upper_left, lower_right) = match tile_bounds(z, x, y) {
+            None => return Ok(None),
+            Some(corners) => corners
+        };
+
+        if let Some(png) = self.cache.lock().unwrap().get((z, x, y)) {
+            return Ok(Some(png));
+        }
+
+        let bounds = (TILE_SIZE, TILE_SIZE);
+        let mut pixels = vec![0; bounds.0 * bounds.1 * self.config.bytes_per_pixel()];
+        render_parallel(&mut pixels, bounds, upper_left, lower_right, self.config);
+
+        let mut png = Vec::new();
+        encode_png(&mut png, &pixels, bounds, self.config.color_type())?;
+        self.cache.lock().unwrap().insert((z, x, y), png.clone());
+
+        Ok(Some(png))
+
+    }
+
+    // The full HTTP response to a request line like GET /0/0/0.png HTTP/1.1.
+    pub fn respond(&self, request: &str) -> Vec<u8> {
+
+        let mut words = request.split_whitespace();
+        let (method, path) = (words.next(), words.next().map(|path| path.split('?').next().unwrap_or(path)));
+
+        match (method, path.and_then(parse_tile_path)) {
+            (Some("GET"), Some((z, x, y))) => match self.tile(z, x, y) {
+                Ok(Some(png)) => response("200 OK", "image/png", &png),
+                Ok(None) => response("404 Not Found", "text/plain", b"No such tile\n"),
+                Err(error) => response("500 Internal Server Error", "text/plain", format!("{}\n", error).as_bytes())
+            },
+            (Some("GET"), None) => response("404 Not Found", "text/plain", b"Expected /z/x/y.png\n"),
+            _ => response("405 Method Not Allowed", "text/plain", b"Only GET is supported\n")
+        }
+
+    }
+
+    fn handle(&self, stream: TcpStream) -> Result<(), io::Error> {
+
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+
+        // Headers are read and ignored, up to the blank line ending them.
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+            header.clear();
+        }
+
+        let mut stream = reader.into_inner();
+        stream.write_all(&self.respond(&request))?;
+        stream.flush()
+
+    }
+
+    // Serves tiles forever, one connection at a time. Each tile is already
+    // rendered across all threads.
+    pub fn serve(&self, address: &str) -> Result<(), io::Error> {
+
+        let listener = TcpListener::bind(address)?;
+        for stream in listener.incoming() {
+            if let Err(error) = stream.and_then(|stream| self.handle(stream)) {
+                eprintln!("Warning : {}", error);
+            }
+        }
+
+        Ok(())
+
+    }
+
+}
+
+// A tile's pixels only depend on its coordinates, so browsers may keep it.
+fn response(status: &str, content_type: &str, body: &[u8]) -> Vec<u8> {
+
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: public, max-age=86400\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+                               status, content_type, body.len()).into_bytes();
+    response.extend_from_slice(body);
+    response
+
+}
+
+#[test]
+fn test_tile_bounds() {
+    assert_eq!(tile_bounds(0, 0, 0), Some((Complex { re: -2.0, im: 2.0 }, Complex { re: 2.0, im: -2.0 })));
+    assert_eq!(tile_bounds(1, 1, 0), Some((Complex { re: 0.0, im: 2.0 }, Complex { re: 2.0, im: 0.0 })));
+    assert_eq!(tile_bounds(2, 0, 3), Some((Complex { re: -2.0, im: -1.0 }, Complex { re: -1.0, im: -2.0 })));
+    assert_eq!(tile_bounds(1, 2, 0), None);
+    assert_eq!(tile_bounds(MAX_ZOOM + 1, 0, 0), None);
+
+    assert_eq!(parse_tile_path("/3/5/7.png"), Some((3, 5, 7)));
+    assert_eq!(parse_tile_path("/3/5/7.jpg"), None);
+    assert_eq!(parse_tile_path("/3/5.png"), None);
+    assert_eq!(parse_tile_path("/3/5/7/9.png"), None);
+    assert_eq!(parse_tile_path("/3/-5/7.png"), None);
+}
+
+#[test]
+fn test_tile_server() {
+    use image::GenericImage;
+
+    let server = TileServer::new(Config::default(), 2);
+    let response = server.respond("GET /0/0/0.png HTTP/1.1\r\n");
+    let split = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8(response[..split].to_vec()).unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(head.contains("Content-Type: image/png"));
+    assert!(head.contains("Cache-Control: public"));
+
+    let tile = image::load_from_memory(&response[split + 4..]).unwrap();
+    assert_eq!(tile.dimensions(), (TILE_SIZE as u32, TILE_SIZE as u32));
+    let luma = tile.to_luma();
+    assert_eq!(luma.get_pixel(128, 128).data[0], 0);
+    assert!(luma.get_pixel(0, 0).data[0] > 0);
+
+    // The least recently used tile is evicted once the cache is full.
+    server.tile(1, 0, 0).unwrap();
+    server.tile(0, 0, 0).unwrap();
+    server.tile(1, 1, 1).unwrap();
+    let cached: Vec<_> = server.cache.lock().unwrap().tiles.iter().map(|(key, _)| *key).collect();
+    assert_eq!(cached, vec![(1, 1, 1), (0, 0, 0)]);
+
+    assert!(server.respond("GET /1/2/0.png HTTP/1.1\r\n").starts_with(b"HTTP/1.1 404"));
+    assert!(server.respond("GET /index.html HTTP/1.1\r\n").starts_with(b"HTTP/1.1 404"));
+    assert!(server.respond("POST /0/0/0.png HTTP/1.1\r\n").starts_with(b"HTTP/1.1 405"));
+}
@@ -0,0 +1,177 @@
+use crate::{escape_time, in_cardioid_or_bulb, PERIODICITY_EPSILON};
+use num::Complex;
+
+// Escape times of four Mandelbrot points at once, with a bailout radius of 2.
+// The AVX2 path is used when the CPU supports it and gives exactly the same
+// counts as `escape_time`, since it performs the same operations in the same
+// order on each lane, periodicity checks included.
+pub fn escape_time_x4(points: [Complex<f64>; 4], limit: usize) -> [Option<usize>; 4] {
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { escape_time_avx2(points, limit) };
+        }
+    }
+
+    points.map(|point| escape_time(point, limit))
+
+}
+
+// The escape time of one Mandelbrot point with the real and imaginary parts
+// kept in plain f64s rather than a `Complex`, so the squares computed for the
+// bailout test are reused by the next step. The counts are exactly those of
+// `escape_time`: the imaginary part is doubled after the product, which is
+// exact, and the periodicity checks are the same.
+pub fn escape_time_fast(c: Complex<f64>, limit: usize) -> Option<usize> {
+
+    if in_cardioid_or_bulb(c) {
+        return None;
+    }
+
+    let (mut zr, mut zi) = (0.0f64, 0.0f64);
+    let (mut zr2, mut zi2) = (0.0f64, 0.0f64);
+
+    let (mut reference_re, mut reference_im) = (zr, zi);
+    let (mut steps, mut period) = (0, 8);
+
+    for i in 0..limit {
+
+        if zr2 + zi2 > 4.0 {
+            return Some(i);
+        }
+
+        zi = 2.0 * (zr * zi) + c.im;
+        zr = zr2 - zi2 + c.re;
+        zr2 = zr * zr;
+        zi2 = zi * zi;
+
+        let (delta_re, delta_im) = (zr - reference_re, zi - reference_im);
+        if delta_re * delta_re + delta_im * delta_im < PERIODICITY_EPSILON {
+            return None;
+        }
+
+        steps += 1;
+        if steps == period {
+            reference_re = zr;
+            reference_im = zi;
+            steps = 0;
+            period *= 2;
+        }
+
+    }
+
+    None
+
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn escape_time_avx2(points: [Complex<f64>; 4], limit: usize) -> [Option<usize>; 4] {
+
+    use std::arch::x86_64::*;
+
+    let mut times = [None; 4];
+
+    // Lanes stop counting once they escape, or right away when the point is
+    // known to be in the set.
+    let mut done = 0;
+    for (lane, point) in points.iter().enumerate() {
+        if in_cardioid_or_bulb(*point) {
+            done |= 1 << lane;
+        }
+    }
+
+    let c_re = _mm256_setr_pd(points[0].re, points[1].re, points[2].re, points[3].re);
+    let c_im = _mm256_setr_pd(points[0].im, points[1].im, points[2].im, points[3].im);
+    let bailout = _mm256_set1_pd(4.0);
+    let epsilon = _mm256_set1_pd(PERIODICITY_EPSILON);
+
+    let mut z_re = _mm256_setzero_pd();
+    let mut z_im = _mm256_setzero_pd();
+
+    let (mut reference_re, mut reference_im) = (z_re, z_im);
+    let (mut steps, mut period) = (0, 8);
+
+    for i in 0..limit {
+
+        if done == 0b1111 {
+            break;
+        }
+
+        let re_sqr = _mm256_mul_pd(z_re, z_re);
+        let im_sqr = _mm256_mul_pd(z_im, z_im);
+        let norm_sqr = _mm256_add_pd(re_sqr, im_sqr);
+
+        let escaped = _mm256_movemask_pd(_mm256_cmp_pd::<_CMP_GT_OQ>(norm_sqr, bailout)) & !done;
+        for (lane, time) in times.iter_mut().enumerate() {
+            if escaped & (1 << lane) != 0 {
+                *time = Some(i);
+            }
+        }
+        done |= escaped;
+
+        let re = _mm256_add_pd(_mm256_sub_pd(re_sqr, im_sqr), c_re);
+        let im = _mm256_add_pd(_mm256_add_pd(_mm256_mul_pd(z_re, z_im), _mm256_mul_pd(z_im, z_re)), c_im);
+        z_re = re;
+        z_im = im;
+
+        let delta_re = _mm256_sub_pd(z_re, reference_re);
+        let delta_im = _mm256_sub_pd(z_im, reference_im);
+        let delta = _mm256_add_pd(_mm256_mul_pd(delta_re, delta_re), _mm256_mul_pd(delta_im, delta_im));
+        done |= _mm256_movemask_pd(_mm256_cmp_pd::<_CMP_LT_OQ>(delta, epsilon));
+
+        steps += 1;
+        if steps == period {
+            reference_re = z_re;
+            reference_im = z_im;
+            steps = 0;
+            period *= 2;
+        }
+
+    }
+
+    times
+
+}
+
+#[test]
+fn test_escape_time_x4() {
+    let bounds = (64, 48);
+    let upper_left = Complex { re: -2.2, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+
+    for row in 0..bounds.1 {
+        for column in (0..bounds.0).step_by(4) {
+            let points = [0, 1, 2, 3].map(|lane| crate::pixel_to_point(bounds, (column + lane, row), upper_left, lower_right));
+            for &limit in &[1, 50, 1000] {
+                assert_eq!(escape_time_x4(points, limit), points.map(|point| escape_time(point, limit)));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_escape_time_fast() {
+    let bounds = (96, 72);
+    let upper_left = Complex { re: -2.2, im: 1.2 };
+    let lower_right = Complex { re: 1.0, im: -1.2 };
+
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+            let point = crate::pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            for &limit in &[0, 1, 50, 1000] {
+                assert_eq!(escape_time_fast(point, limit), escape_time(point, limit));
+            }
+        }
+    }
+
+    // Near the boundary, where counts are long and sensitive to rounding.
+    let (upper_left, lower_right) = (Complex { re: -0.7454, im: 0.1131 }, Complex { re: -0.7452, im: 0.1129 });
+    for row in 0..32 {
+        for column in 0..32 {
+            let point = crate::pixel_to_point((32, 32), (column, row), upper_left, lower_right);
+            assert_eq!(escape_time_fast(point, 5000), escape_time(point, 5000));
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use std::time::Duration;
+
+// Wall-clock render time of each row, which is the unit of parallel work.
+pub struct RenderStats {
+    pub timings: Vec<Duration>
+}
+
+impl RenderStats {
+
+    pub fn min(&self) -> Duration {
+        self.timings.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.timings.iter().max().copied().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.timings.iter().sum::<Duration>() / self.timings.len().max(1) as u32
+    }
+
+    // How much longer the slowest row took than the average one, 1 when the
+    // work is perfectly balanced.
+    pub fn imbalance(&self) -> f64 {
+        let mean = self.mean().as_secs_f64();
+        if mean > 0.0 { self.max().as_secs_f64() / mean } else { 1.0 }
+    }
+
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} rows rendered in min {:.3?}, max {:.3?}, mean {:.3?}, imbalance {:.2}",
+               self.timings.len(), self.min(), self.max(), self.mean(), self.imbalance())
+    }
+}
+
+#[test]
+fn test_render_stats() {
+    let stats = RenderStats { timings: vec![Duration::from_millis(2), Duration::from_millis(6), Duration::from_millis(4)] };
+    assert_eq!(stats.min(), Duration::from_millis(2));
+    assert_eq!(stats.max(), Duration::from_millis(6));
+    assert_eq!(stats.mean(), Duration::from_millis(4));
+    assert!((stats.imbalance() - 1.5).abs() < 1e-9);
+    assert_eq!(stats.to_string(), "3 rows rendered in min 2.000ms, max 6.000ms, mean 4.000ms, imbalance 1.50");
+
+    let empty = RenderStats { timings: Vec::new() };
+    assert_eq!(empty.mean(), Duration::ZERO);
+    assert_eq!(empty.imbalance(), 1.0);
+}
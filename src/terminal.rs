@@ -0,0 +1,208 @@
+use crate::{Config, Palette, render};
+use num::Complex;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    ZoomIn,
+    ZoomOut,
+    Write,
+    Quit
+}
+
+// Fraction of the view moved by one arrow key, and the zoom of one + or -.
+const PAN_FRACTION: f64 = 0.1;
+const ZOOM_FACTOR: f64 = 2.0;
+
+// Keys in a chunk of terminal input, arrows arriving as ESC [ A to D. Anything
+// else is ignored.
+pub fn parse_keys(input: &[u8]) -> Vec<Key> {
+
+    let mut keys = Vec::new();
+    let mut index = 0;
+
+    while index < input.len() {
+        let key = match &input[index..] {
+            [0x1b, b'[', arrow, ..] => {
+                index += 2;
+                match arrow {
+                    b'A' => Some(Key::Up),
+                    b'B' => Some(Key::Down),
+                    b'C' => Some(Key::Right),
+                    b'D' => Some(Key::Left),
+                    _ => None
+                }
+            },
+            [b'h', ..] => Some(Key::Left),
+            [b'l', ..] => Some(Key::Right),
+            [b'k', ..] => Some(Key::Up),
+            [b'j', ..] => Some(Key::Down),
+            [b'+', ..] | [b'=', ..] => Some(Key::ZoomIn),
+            [b'-', ..] | [b'_', ..] => Some(Key::ZoomOut),
+            [b'w', ..] => Some(Key::Write),
+            [b'q', ..] | [0x03, ..] => Some(Key::Quit),
+            _ => None
+        };
+        keys.extend(key);
+        index += 1;
+    }
+
+    keys
+
+}
+
+// The view after a key press. Panning moves by a fraction of the view and
+// zooming keeps the center in place.
+pub fn apply_key(key: Key, upper_left: Complex<f64>, lower_right: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+
+    let span = lower_right - upper_left;
+    let center = (upper_left + lower_right) / 2.0;
+
+    let offset = match key {
+        Key::Left => Complex { re: -span.re, im: 0.0 },
+        Key::Right => Complex { re: span.re, im: 0.0 },
+        Key::Up => Complex { re: 0.0, im: -span.im },
+        Key::Down => Complex { re: 0.0, im: span.im },
+        Key::ZoomIn | Key::ZoomOut => {
+            let factor = if key == Key::ZoomIn { 1.0 / ZOOM_FACTOR } else { ZOOM_FACTOR };
+            return (center - span * factor / 2.0, center + span * factor / 2.0);
+        },
+        Key::Write | Key::Quit => return (upper_left, lower_right)
+    } * PAN_FRACTION;
+
+    (upper_left + offset, lower_right + offset)
+
+}
+
+// The largest preview with the image's aspect ratio that fits the terminal,
+// keeping the last line free. Each character cell shows two pixels stacked.
+pub fn preview_bounds(terminal: (usize, usize), bounds: (usize, usize)) -> (usize, usize) {
+
+    let available = (terminal.0.max(1), (terminal.1.saturating_sub(1) * 2).max(2));
+
+    let width = available.0.min(available.1 * bounds.0 / bounds.1).max(1);
+    let height = (width * bounds.1 / bounds.0).clamp(1, available.1);
+
+    (width, height)
+
+}
+
+// Draws 8-bit gray, RGB or RGBA pixels as truecolor half blocks, the upper
+// pixel of each pair in the foreground and the lower one in the background.
+pub fn preview(pixels: &[u8], bounds: (usize, usize), channels: usize) -> String {
+
+    assert!(pixels.len() == bounds.0 * bounds.1 * channels);
+
+    let rgb = |x: usize, y: usize| {
+        let pixel = &pixels[(y * bounds.0 + x) * channels..][..channels];
+        if channels < 3 { [pixel[0]; 3] } else { [pixel[0], pixel[1], pixel[2]] }
+    };
+
+    let mut buffer = String::new();
+
+    for y in (0..bounds.1).step_by(2) {
+        for x in 0..bounds.0 {
+            let [red, green, blue] = rgb(x, y);
+            write!(buffer, "\x1b[38;2;{};{};{}m", red, green, blue).unwrap();
+            if y + 1 < bounds.1 {
+                let [red, green, blue] = rgb(x, y + 1);
+                write!(buffer, "\x1b[48;2;{};{};{}m", red, green, blue).unwrap();
+            } else {
+                buffer.push_str("\x1b[49m");
+            }
+            buffer.push('▀');
+        }
+        buffer.push_str("\x1b[0m\r\n");
+    }
+
+    buffer
+
+}
+
+// Characters from the quickest escapes to the points in the set.
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+// The largest character grid with the image's aspect ratio that fits in
+// `size`. Characters are about twice as tall as they are wide.
+pub fn ascii_bounds(size: (usize, usize), bounds: (usize, usize)) -> (usize, usize) {
+
+    let width = size.0.min(2 * size.1 * bounds.0 / bounds.1).max(1);
+    let height = (width * bounds.1 / bounds.0 / 2).clamp(1, size.1.max(1));
+
+    (width, height)
+
+}
+
+// A plain text thumbnail of the view, one line per row of characters. Counts
+// are shaded logarithmically so the few characters still show the bands.
+pub fn ascii_preview(size: (usize, usize),
+                     bounds: (usize, usize),
+                     upper_left: Complex<f64>,
+                     lower_right: Complex<f64>,
+                     config: Config)
+   -> String
+{
+
+    let config = Config { palette: Palette::Grayscale, supersample: 1, depth: 8, invert: false, transparent: false, interior: [0; 3], gamma: 1.0, log_scale: true, ..config };
+    let size = ascii_bounds(size, bounds);
+
+    let mut pixels = vec![0; size.0 * size.1];
+    render(&mut pixels, size, upper_left, lower_right, config);
+
+    let mut buffer = String::new();
+    for line in pixels.chunks(size.0) {
+        buffer.extend(line.iter().map(|pixel| RAMP[(255 - *pixel as usize) * (RAMP.len() - 1) / 255] as char));
+        buffer.push('\n');
+    }
+
+    buffer
+
+}
+
+#[test]
+fn test_keys() {
+    assert_eq!(parse_keys(b"\x1b[A\x1b[Dx+-wq"), vec![Key::Up, Key::Left, Key::ZoomIn, Key::ZoomOut, Key::Write, Key::Quit]);
+    assert_eq!(parse_keys(b"\x1b[Z"), vec![]);
+
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.0 }, Complex { re: 2.0, im: -1.0 });
+    assert_eq!(apply_key(Key::Right, upper_left, lower_right), (Complex { re: -1.6, im: 1.0 }, Complex { re: 2.4, im: -1.0 }));
+    assert_eq!(apply_key(Key::Up, upper_left, lower_right), (Complex { re: -2.0, im: 1.2 }, Complex { re: 2.0, im: -0.8 }));
+    assert_eq!(apply_key(Key::ZoomIn, upper_left, lower_right), (Complex { re: -1.0, im: 0.5 }, Complex { re: 1.0, im: -0.5 }));
+    assert_eq!(apply_key(Key::ZoomOut, upper_left, lower_right), (Complex { re: -4.0, im: 2.0 }, Complex { re: 4.0, im: -2.0 }));
+
+    assert_eq!(preview_bounds((80, 24), (1920, 1080)), (80, 45));
+    assert_eq!(preview_bounds((80, 24), (1000, 1000)), (46, 46));
+}
+
+#[test]
+fn test_preview() {
+    let pixels = [10, 20, 30, 40, 50, 60];
+    assert_eq!(preview(&pixels, (2, 3), 1),
+               "\x1b[38;2;10;10;10m\x1b[48;2;30;30;30m▀\x1b[38;2;20;20;20m\x1b[48;2;40;40;40m▀\x1b[0m\r\n\
+                \x1b[38;2;50;50;50m\x1b[49m▀\x1b[38;2;60;60;60m\x1b[49m▀\x1b[0m\r\n");
+
+    let pixels = [255, 0, 0, 0, 0, 255];
+    assert_eq!(preview(&pixels, (1, 2), 3), "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m\r\n");
+}
+
+#[test]
+fn test_ascii_preview() {
+    let (upper_left, lower_right) = (Complex { re: -2.0, im: 1.2 }, Complex { re: 1.0, im: -1.2 });
+
+    assert_eq!(ascii_bounds((80, 40), (1920, 1080)), (80, 22));
+    assert_eq!(ascii_bounds((80, 40), (1000, 1000)), (80, 40));
+    assert_eq!(ascii_bounds((80, 40), (1000, 4000)), (20, 40));
+
+    for (bounds, size) in [((1920, 1080), (80, 22)), ((300, 240), (80, 32)), ((1000, 1000), (80, 40))] {
+        let text = ascii_preview((80, 40), bounds, upper_left, lower_right, Config::default());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), size.1);
+        assert!(lines.iter().all(|line| line.chars().count() == size.0));
+        assert!(text.contains('@') && text.contains('.'));
+        assert!(text.chars().all(|c| c == '\n' || RAMP.contains(&(c as u8))));
+    }
+}
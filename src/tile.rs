@@ -0,0 +1,178 @@
+use crate::{Config, render_row, render_tile};
+use crate::output::{PngStreamWriter, METADATA_KEYWORD};
+use num::Complex;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+
+pub fn write_tiled_png(filename: &str,
+                       bounds: (usize, usize),
+                       upper_left: Complex<f64>,
+                       lower_right: Complex<f64>,
+                       config: Config,
+                       tile_size: usize)
+   -> Result<(), std::io::Error>
+{
+
+    assert!(tile_size > 0);
+
+    let output = BufWriter::new(File::create(filename)?);
+    let mut writer = PngStreamWriter::new(output, bounds, config.color_type(), None)?;
+
+    let bytes_per_pixel = config.bytes_per_pixel();
+    let mut strip = vec![0; bounds.0 * tile_size * bytes_per_pixel];
+    let mut tile = vec![0; tile_size * tile_size * bytes_per_pixel];
+
+    for top in (0..bounds.1).step_by(tile_size) {
+
+        let height = tile_size.min(bounds.1 - top);
+
+        for left in (0..bounds.0).step_by(tile_size) {
+
+            let width = tile_size.min(bounds.0 - left);
+            let tile = &mut tile[..width * height * bytes_per_pixel];
+            render_tile(tile, bounds, (left, top), (width, height), upper_left, lower_right, config);
+
+            for (row, line) in tile.chunks(width * bytes_per_pixel).enumerate() {
+                let start = (row * bounds.0 + left) * bytes_per_pixel;
+                strip[start..start + line.len()].copy_from_slice(line);
+            }
+
+        }
+
+        writer.write_rows(&strip[..bounds.0 * height * bytes_per_pixel])?;
+
+    }
+
+    writer.finish()?;
+
+    Ok(())
+
+}
+
+// Rows rendered ahead of the encoder. They finish out of order across threads,
+// so each strip is completed in memory before its rows are encoded in order.
+const STRIP_ROWS: usize = 64;
+
+// Like `render_parallel` followed by `write_image` for a .png file, or by
+// `write_image_with_metadata` when given metadata, with the same bytes, but
+// only one strip of pixels is ever held in memory.
+pub fn write_streamed_png(filename: &str,
+                          bounds: (usize, usize),
+                          upper_left: Complex<f64>,
+                          lower_right: Complex<f64>,
+                          config: Config,
+                          metadata: Option<&str>)
+   -> Result<(), std::io::Error>
+{
+
+    let output = BufWriter::new(File::create(filename)?);
+    let mut writer = PngStreamWriter::new(output, bounds, config.color_type(), metadata.map(|metadata| (METADATA_KEYWORD, metadata)))?;
+
+    let row_length = (bounds.0 * config.bytes_per_pixel()).max(1);
+    let mut strip = vec![0; row_length * STRIP_ROWS.min(bounds.1)];
+
+    for top in (0..bounds.1).step_by(STRIP_ROWS) {
+
+        let strip = &mut strip[..row_length * STRIP_ROWS.min(bounds.1 - top)];
+        strip.par_chunks_mut(row_length)
+             .enumerate()
+             .for_each(|(row, line)| render_row(line, bounds, (0, top + row), upper_left, lower_right, config));
+
+        writer.write_rows(strip)?;
+
+    }
+
+    writer.finish()?;
+
+    Ok(())
+
+}
+
+#[test]
+fn test_write_tiled_png() {
+    use crate::{Palette, render, write_image};
+
+    let bounds = (37, 23);
+    let upper_left = Complex { re: -2.0, im: 1.2 };
+    let lower_right = Complex { re: 0.6, im: -1.2 };
+    let directory = std::env::temp_dir();
+
+    for config in &[Config::default(), Config { palette: Palette::Fire, ..Config::default() }] {
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.channels()];
+        render(&mut pixels, bounds, upper_left, lower_right, *config);
+
+        let whole = directory.join("mandelbrot_test_tiled_whole.png");
+        write_image(whole.to_str().unwrap(), &pixels, bounds, config.color_type()).unwrap();
+
+        let tiled = directory.join("mandelbrot_test_tiled.png");
+        write_tiled_png(tiled.to_str().unwrap(), bounds, upper_left, lower_right, *config, 8).unwrap();
+
+        assert_eq!(std::fs::read(&tiled).unwrap(), std::fs::read(&whole).unwrap());
+    }
+}
+
+#[test]
+fn test_write_streamed_png() {
+    use crate::{Coloring, Palette, render_parallel, write_image, write_image_with_metadata};
+    use crate::output::png_chunks;
+
+    let bounds = (300, 157);
+    let upper_left = Complex { re: -2.2, im: 1.2 };
+    let lower_right = Complex { re: 0.8, im: -1.2 };
+    let directory = std::env::temp_dir();
+
+    for config in &[Config::default(), Config { coloring: Coloring::Smooth, palette: Palette::Ultra, depth: 16, ..Config::default() }] {
+        let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+        render_parallel(&mut pixels, bounds, upper_left, lower_right, *config);
+
+        let whole = directory.join("mandelbrot_test_streamed_whole.png");
+        write_image(whole.to_str().unwrap(), &pixels, bounds, config.color_type()).unwrap();
+
+        let streamed = directory.join("mandelbrot_test_streamed.png");
+        write_streamed_png(streamed.to_str().unwrap(), bounds, upper_left, lower_right, *config, None).unwrap();
+
+        assert_eq!(std::fs::read(&streamed).unwrap(), std::fs::read(&whole).unwrap());
+    }
+
+    // Smooth 16 bit colors compress poorly, so this image data spans several
+    // IDAT chunks, and the metadata goes in front of them.
+    let bounds = (400, 300);
+    let upper_left = Complex { re: -0.8, im: 0.2 };
+    let lower_right = Complex { re: -0.7, im: 0.125 };
+    let config = Config { coloring: Coloring::Smooth, palette: Palette::Ultra, depth: 16, limit: 500, ..Config::default() };
+    let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+    render_parallel(&mut pixels, bounds, upper_left, lower_right, config);
+
+    let whole = directory.join("mandelbrot_test_streamed_whole_chunks.png");
+    write_image_with_metadata(whole.to_str().unwrap(), &pixels, bounds, config.color_type(), "view").unwrap();
+
+    let streamed = directory.join("mandelbrot_test_streamed_chunks.png");
+    write_streamed_png(streamed.to_str().unwrap(), bounds, upper_left, lower_right, config, Some("view")).unwrap();
+
+    let bytes = std::fs::read(&streamed).unwrap();
+    assert!(png_chunks(&bytes).iter().filter(|(name, _)| name == b"IDAT").count() >= 2);
+    assert_eq!(bytes, std::fs::read(&whole).unwrap());
+}
+
+#[test]
+fn test_write_tiled_png_chunks() {
+    use crate::{Coloring, Palette, render};
+    use crate::output::{decode_png, png_chunks};
+
+    // Smooth 16 bit colors compress poorly, so the data spans several chunks.
+    let bounds = (400, 300);
+    let upper_left = Complex { re: -0.8, im: 0.2 };
+    let lower_right = Complex { re: -0.7, im: 0.125 };
+    let config = Config { coloring: Coloring::Smooth, palette: Palette::Ultra, depth: 16, limit: 500, ..Config::default() };
+
+    let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+    render(&mut pixels, bounds, upper_left, lower_right, config);
+
+    let tiled = std::env::temp_dir().join("mandelbrot_test_tiled_chunks.png");
+    write_tiled_png(tiled.to_str().unwrap(), bounds, upper_left, lower_right, config, 64).unwrap();
+    let bytes = std::fs::read(&tiled).unwrap();
+
+    assert!(png_chunks(&bytes).iter().filter(|(name, _)| name == b"IDAT").count() >= 2);
+    assert_eq!(decode_png(&bytes), (bounds, pixels));
+}
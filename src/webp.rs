@@ -0,0 +1,802 @@
+use image::ColorType;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+
+// Lossless WebP (VP8L). The image is stored with the subtract green and left
+// predictor transforms, which leave mostly zero residuals on smooth fractal
+// regions, followed by one set of Huffman codes built from the residuals.
+pub fn encode_webp<W: Write>(output: &mut W, pixels: &[u8], bounds: (usize, usize), color: ColorType) -> Result<(), io::Error> {
+
+    let channels = match color {
+        ColorType::Gray(8) => 1,
+        ColorType::RGB(8) => 3,
+        ColorType::RGBA(8) => 4,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                       format!("Unsupported color type {:?} for WebP output", color)))
+    };
+    if bounds.0 == 0 || bounds.1 == 0 || bounds.0 > MAX_SIZE || bounds.1 > MAX_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("WebP images must be between 1x1 and {}x{}, got {}x{}", MAX_SIZE, MAX_SIZE, bounds.0, bounds.1)));
+    }
+    assert!(pixels.len() == bounds.0 * bounds.1 * channels);
+
+    // Pixels are kept as [green, red, blue, alpha], the order they are coded in.
+    let argb: Vec<[u8; 4]> = pixels.chunks(channels).map(|pixel| match *pixel {
+        [gray] => [gray, gray, gray, 255],
+        [red, green, blue] => [green, red, blue, 255],
+        [red, green, blue, alpha] => [green, red, blue, alpha],
+        _ => unreachable!()
+    }).collect();
+
+    let mut writer = BitWriter::new();
+    writer.write(SIGNATURE, 8);
+    writer.write(bounds.0 as u32 - 1, 14);
+    writer.write(bounds.1 as u32 - 1, 14);
+    writer.write(argb.iter().any(|pixel| pixel[3] != 255) as u32, 1);
+    writer.write(0, 3);
+
+    writer.write(1, 1);
+    writer.write(SUBTRACT_GREEN, 2);
+    let argb: Vec<[u8; 4]> = argb.iter().map(|&[green, red, blue, alpha]| [green, red.wrapping_sub(green), blue.wrapping_sub(green), alpha]).collect();
+
+    writer.write(1, 1);
+    writer.write(PREDICTOR, 2);
+    writer.write(PREDICTOR_BITS - 2, 3);
+    let blocks = (bounds.0.div_ceil(1 << PREDICTOR_BITS), bounds.1.div_ceil(1 << PREDICTOR_BITS));
+    write_entropy_coded(&mut writer, &vec![[LEFT_PREDICTOR, 0, 0, 255]; blocks.0 * blocks.1], false);
+    writer.write(0, 1);
+
+    write_entropy_coded(&mut writer, &predict_left(&argb, bounds), true);
+
+    let data = writer.finish();
+    let padding = data.len() % 2;
+
+    output.write_all(b"RIFF")?;
+    output.write_all(&(4 + 8 + data.len() as u32 + padding as u32).to_le_bytes())?;
+    output.write_all(b"WEBP")?;
+    output.write_all(b"VP8L")?;
+    output.write_all(&(data.len() as u32).to_le_bytes())?;
+    output.write_all(&data)?;
+    output.write_all(&[0][..padding])?;
+
+    Ok(())
+
+}
+
+const MAX_SIZE: usize = 1 << 14;
+const SIGNATURE: u32 = 0x2f;
+const PREDICTOR: u32 = 0;
+const SUBTRACT_GREEN: u32 = 2;
+const PREDICTOR_BITS: u32 = 9;
+const LEFT_PREDICTOR: u8 = 1;
+const GREEN_ALPHABET: usize = 256 + 24;
+const DISTANCE_ALPHABET: usize = 40;
+const MIN_LENGTH: usize = 3;
+const MAX_LENGTH: usize = 4096;
+const MAX_CHAIN: usize = 32;
+// Distance codes up to 120 stand for nearby pixels in two dimensions, so plain
+// distances are offset past them.
+const DISTANCE_OFFSET: usize = 120;
+const MAX_DISTANCE: usize = (1 << 20) - DISTANCE_OFFSET;
+const CODE_LENGTH_ORDER: [usize; 19] = [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+// Residuals against the left pixel, or the one above in the first column, as
+// the decoder predicts them for mode 1 everywhere.
+fn predict_left(argb: &[[u8; 4]], bounds: (usize, usize)) -> Vec<[u8; 4]> {
+
+    argb.iter().enumerate().map(|(index, pixel)| {
+        let prediction = match (index % bounds.0, index / bounds.0) {
+            (0, 0) => [0, 0, 0, 255],
+            (0, _) => argb[index - bounds.0],
+            _ => argb[index - 1]
+        };
+        [0, 1, 2, 3].map(|channel| pixel[channel].wrapping_sub(prediction[channel]))
+    }).collect()
+
+}
+
+// A pixel residual, or a copy of `length` pixels from `distance` back.
+enum Symbol {
+    Literal([u8; 4]),
+    Copy(usize, usize)
+}
+
+// Greedy LZ77 over whole pixels, following a hash chain of earlier positions
+// that start with the same two pixels.
+fn backward_references(argb: &[[u8; 4]]) -> Vec<Symbol> {
+
+    let hash = |index: usize| {
+        let pair = [argb[index], argb[index + 1]].concat();
+        (pair.iter().fold(0u32, |hash, byte| hash.wrapping_mul(0x9e37_79b1) ^ *byte as u32) >> 16) as usize
+    };
+
+    let mut heads = vec![usize::MAX; 1 << 16];
+    let mut previous = vec![usize::MAX; argb.len()];
+
+    let mut symbols = Vec::new();
+    let mut index = 0;
+
+    while index < argb.len() {
+
+        let mut best = (0, 0);
+        if index + 1 < argb.len() {
+            let mut candidate = heads[hash(index)];
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || index - candidate > MAX_DISTANCE {
+                    break;
+                }
+                let length = argb[index..].iter()
+                                          .zip(&argb[candidate..])
+                                          .take(MAX_LENGTH)
+                                          .take_while(|(pixel, earlier)| pixel == earlier)
+                                          .count();
+                if length > best.0 {
+                    best = (length, index - candidate);
+                }
+                candidate = previous[candidate];
+            }
+        }
+
+        let length = if best.0 >= MIN_LENGTH {
+            symbols.push(Symbol::Copy(best.0, best.1));
+            best.0
+        } else {
+            symbols.push(Symbol::Literal(argb[index]));
+            1
+        };
+        for position in (index..index + length).filter(|position| position + 1 < argb.len()) {
+            let bucket = hash(position);
+            previous[position] = heads[bucket];
+            heads[bucket] = position;
+        }
+        index += length;
+
+    }
+
+    symbols
+
+}
+
+// Lengths and distances are coded as a prefix symbol followed by extra bits.
+fn prefix_code(value: usize) -> (usize, u32, u32) {
+    if value <= 4 {
+        return (value - 1, 0, 0);
+    }
+    let value = value - 1;
+    let highest = usize::BITS - 1 - value.leading_zeros();
+    let second = (value >> (highest - 1)) & 1;
+    let extra_bits = highest - 1;
+    (2 * highest as usize + second, extra_bits, (value & ((1 << extra_bits) - 1)) as u32)
+}
+
+fn write_entropy_coded(writer: &mut BitWriter, argb: &[[u8; 4]], main: bool) {
+
+    writer.write(0, 1);
+    if main {
+        writer.write(0, 1);
+    }
+
+    let symbols = backward_references(argb);
+
+    let mut histograms = [vec![0; GREEN_ALPHABET], vec![0; 256], vec![0; 256], vec![0; 256], vec![0; DISTANCE_ALPHABET]];
+    for symbol in &symbols {
+        match symbol {
+            Symbol::Literal(pixel) => for (histogram, value) in histograms.iter_mut().zip(pixel) {
+                histogram[*value as usize] += 1;
+            },
+            Symbol::Copy(length, distance) => {
+                histograms[0][256 + prefix_code(*length).0] += 1;
+                histograms[4][prefix_code(distance + DISTANCE_OFFSET).0] += 1;
+            }
+        }
+    }
+
+    let codes: Vec<Vec<(u32, u32)>> = histograms.iter().map(|histogram| write_code(writer, histogram)).collect();
+
+    for symbol in &symbols {
+        match symbol {
+            Symbol::Literal(pixel) => for (code, value) in codes.iter().zip(pixel) {
+                let (bits, length) = code[*value as usize];
+                writer.write(bits, length);
+            },
+            Symbol::Copy(length, distance) => {
+                for (code, offset, value) in [(&codes[0], 256, *length), (&codes[4], 0, distance + DISTANCE_OFFSET)] {
+                    let (prefix, extra_bits, extra) = prefix_code(value);
+                    let (bits, length) = code[offset + prefix];
+                    writer.write(bits, length);
+                    writer.write(extra, extra_bits);
+                }
+            }
+        }
+    }
+
+}
+
+// Writes a prefix code for the symbol counts and returns each symbol's code,
+// bit-reversed as the stream expects, with its length.
+fn write_code(writer: &mut BitWriter, counts: &[u32]) -> Vec<(u32, u32)> {
+
+    let used: Vec<usize> = (0..counts.len()).filter(|symbol| counts[*symbol] > 0).collect();
+
+    if used.len() <= 1 {
+        let symbol = used.first().copied().unwrap_or(0) as u32;
+        writer.write(1, 1);
+        writer.write(0, 1);
+        if symbol < 2 {
+            writer.write(0, 1);
+            writer.write(symbol, 1);
+        } else {
+            writer.write(1, 1);
+            writer.write(symbol, 8);
+        }
+        return vec![(0, 0); counts.len()];
+    }
+
+    let lengths = code_lengths(counts, 15);
+
+    let mut length_counts = [0; 19];
+    for length in &lengths {
+        length_counts[*length as usize] += 1;
+    }
+    // A code needs two symbols, so pad with an unused one if every length is equal.
+    let present: Vec<usize> = (0..length_counts.len()).filter(|length| length_counts[*length] > 0).collect();
+    if let [only] = present[..] {
+        length_counts[if only == 0 { 1 } else { 0 }] = 1;
+    }
+    let length_lengths = code_lengths(&length_counts, 7);
+    let length_codes = canonical_codes(&length_lengths);
+
+    let count = CODE_LENGTH_ORDER.iter().rposition(|symbol| length_lengths[*symbol] > 0).unwrap_or(0).max(3) + 1;
+    writer.write(0, 1);
+    writer.write(count as u32 - 4, 4);
+    for symbol in &CODE_LENGTH_ORDER[..count] {
+        writer.write(length_lengths[*symbol], 3);
+    }
+
+    writer.write(0, 1);
+    for length in &lengths {
+        let (bits, length) = length_codes[*length as usize];
+        writer.write(bits, length);
+    }
+
+    canonical_codes(&lengths)
+
+}
+
+// Huffman code lengths no longer than `limit`. Rare symbols are made more
+// frequent until the tree is shallow enough.
+fn code_lengths(counts: &[u32], limit: u32) -> Vec<u32> {
+
+    let mut floor = 1;
+
+    loop {
+
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::new();
+        for (symbol, count) in counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+            heap.push(Reverse(((*count).max(floor) as u64, symbol)));
+        }
+
+        // Nodes past the symbols are internal, and each records its children.
+        let mut children: Vec<(usize, usize)> = Vec::new();
+        while heap.len() > 1 {
+            let Reverse((first_weight, first)) = heap.pop().unwrap();
+            let Reverse((second_weight, second)) = heap.pop().unwrap();
+            children.push((first, second));
+            heap.push(Reverse((first_weight + second_weight, counts.len() + children.len() - 1)));
+        }
+
+        let mut lengths = vec![0; counts.len()];
+        let mut parents = vec![0; children.len()];
+        for (node, (first, second)) in children.iter().enumerate().rev() {
+            let depth = parents[node] + 1;
+            for child in [*first, *second] {
+                if child < counts.len() {
+                    lengths[child] = depth;
+                } else {
+                    parents[child - counts.len()] = depth;
+                }
+            }
+        }
+
+        if lengths.iter().all(|length| *length <= limit) {
+            return lengths;
+        }
+        floor *= 2;
+
+    }
+
+}
+
+fn canonical_codes(lengths: &[u32]) -> Vec<(u32, u32)> {
+
+    let mut length_counts = [0u32; 16];
+    for length in lengths {
+        length_counts[*length as usize] += 1;
+    }
+    length_counts[0] = 0;
+
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for length in 1..16 {
+        code = (code + length_counts[length - 1]) << 1;
+        next[length] = code;
+    }
+
+    lengths.iter().map(|length| {
+        if *length == 0 {
+            return (0, 0);
+        }
+        let code = next[*length as usize];
+        next[*length as usize] += 1;
+        (code.reverse_bits() >> (32 - length), *length)
+    }).collect()
+
+}
+
+// Bits are packed from the least significant end of each byte.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32
+}
+
+impl BitWriter {
+
+    fn new() -> Self {
+        BitWriter { bytes: Vec::new(), buffer: 0, count: 0 }
+    }
+
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+
+}
+
+// A VP8L decoder written from the format specification rather than from the
+// encoder above, so that round trips check the bitstream and not just the
+// encoder's own assumptions. Color indexing and the two-dimensional distance
+// codes are never written here and are rejected rather than decoded.
+#[cfg(test)]
+mod decode {
+
+    use std::collections::HashMap;
+
+    const GREEN: usize = 0;
+    const RED: usize = 1;
+    const BLUE: usize = 2;
+    const ALPHA: usize = 3;
+    const DISTANCE: usize = 4;
+
+    // The bounds and the pixels as RGBA.
+    type Decoded = ((usize, usize), Vec<[u8; 4]>);
+
+    pub(super) fn decode_webp(bytes: &[u8]) -> Result<Decoded, String> {
+
+        let word = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize;
+        if bytes.len() < 21 || &bytes[0..4] != b"RIFF" || &bytes[8..16] != b"WEBPVP8L" {
+            return Err("not a lossless WebP file".to_string());
+        }
+        if word(4) + 8 != bytes.len() || 20 + word(16) > bytes.len() {
+            return Err("chunk sizes do not match the file".to_string());
+        }
+
+        let mut reader = BitReader { bytes: &bytes[20..20 + word(16)], position: 0 };
+        if reader.read(8)? != 0x2f {
+            return Err("bad signature".to_string());
+        }
+        let bounds = (reader.read(14)? as usize + 1, reader.read(14)? as usize + 1);
+        reader.read(1)?;
+        if reader.read(3)? != 0 {
+            return Err("unknown version".to_string());
+        }
+
+        let mut transforms = Vec::new();
+        while reader.read(1)? == 1 {
+            let transform = match reader.read(2)? {
+                kind @ (0 | 1) => {
+                    let bits = reader.read(3)? + 2;
+                    let blocks = (bounds.0.div_ceil(1 << bits), bounds.1.div_ceil(1 << bits));
+                    Transform::Blocks(kind, bits, decode_image(&mut reader, blocks, false)?)
+                },
+                2 => Transform::SubtractGreen,
+                _ => return Err("color indexing is not supported".to_string())
+            };
+            transforms.push(transform);
+        }
+
+        let mut argb = decode_image(&mut reader, bounds, true)?;
+        for transform in transforms.iter().rev() {
+            match transform {
+                Transform::Blocks(0, bits, modes) => unpredict(&mut argb, bounds, *bits, modes)?,
+                Transform::Blocks(_, bits, multipliers) => uncorrelate(&mut argb, bounds, *bits, multipliers),
+                Transform::SubtractGreen => for pixel in &mut argb {
+                    let green = (*pixel >> 8) & 0xff;
+                    let red = (((*pixel >> 16) + green) & 0xff) << 16;
+                    let blue = (*pixel + green) & 0xff;
+                    *pixel = (*pixel & 0xff00_ff00) | red | blue;
+                }
+            }
+        }
+
+        Ok((bounds, argb.iter().map(|pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8, (pixel >> 24) as u8]).collect()))
+
+    }
+
+    enum Transform {
+        // A predictor (0) or color (1) transform with its block bits and the
+        // sub-image of per-block parameters.
+        Blocks(u32, u32, Vec<u32>),
+        SubtractGreen
+    }
+
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        position: usize
+    }
+
+    impl BitReader<'_> {
+
+        fn read(&mut self, bits: u32) -> Result<u32, String> {
+            let mut value = 0;
+            for bit in 0..bits {
+                let byte = self.bytes.get(self.position / 8).ok_or("unexpected end of data")?;
+                value |= ((byte >> (self.position % 8)) as u32 & 1) << bit;
+                self.position += 1;
+            }
+            Ok(value)
+        }
+
+    }
+
+    // A canonical prefix code, keyed by (length, code) with the code read most
+    // significant bit first. A code with a single symbol takes no bits.
+    struct Code {
+        symbols: HashMap<(u32, u32), usize>,
+        single: Option<usize>
+    }
+
+    impl Code {
+
+        fn from_lengths(lengths: &[u32]) -> Result<Code, String> {
+            let used: Vec<usize> = (0..lengths.len()).filter(|symbol| lengths[*symbol] > 0).collect();
+            if used.is_empty() {
+                return Err("prefix code without symbols".to_string());
+            }
+            if let [only] = used[..] {
+                return Ok(Code { symbols: HashMap::new(), single: Some(only) });
+            }
+            let mut symbols = HashMap::new();
+            let mut code = 0;
+            let mut space = 0u64;
+            for length in 1..=15 {
+                for symbol in used.iter().filter(|symbol| lengths[**symbol] == length) {
+                    symbols.insert((length, code), *symbol);
+                    code += 1;
+                    space += 1 << (15 - length);
+                }
+                code <<= 1;
+            }
+            if space != 1 << 15 {
+                return Err("prefix code is not complete".to_string());
+            }
+            Ok(Code { symbols, single: None })
+        }
+
+        fn read(&self, reader: &mut BitReader) -> Result<usize, String> {
+            if let Some(symbol) = self.single {
+                return Ok(symbol);
+            }
+            let mut code = 0;
+            for length in 1..=15 {
+                code = (code << 1) | reader.read(1)?;
+                if let Some(symbol) = self.symbols.get(&(length, code)) {
+                    return Ok(*symbol);
+                }
+            }
+            Err("invalid prefix code".to_string())
+        }
+
+    }
+
+    fn read_code(reader: &mut BitReader, alphabet: usize) -> Result<Code, String> {
+
+        let mut lengths = vec![0; alphabet];
+
+        if reader.read(1)? == 1 {
+            let count = reader.read(1)? + 1;
+            let first_bits = if reader.read(1)? == 1 { 8 } else { 1 };
+            lengths[reader.read(first_bits)? as usize] = 1;
+            if count == 2 {
+                lengths[reader.read(8)? as usize] = 1;
+            }
+            return Code::from_lengths(&lengths);
+        }
+
+        let mut length_lengths = [0; 19];
+        for symbol in &super::CODE_LENGTH_ORDER[..reader.read(4)? as usize + 4] {
+            length_lengths[*symbol] = reader.read(3)?;
+        }
+        let length_code = Code::from_lengths(&length_lengths)?;
+
+        let mut remaining = if reader.read(1)? == 0 {
+            alphabet
+        } else {
+            let bits = 2 + 2 * reader.read(3)?;
+            2 + reader.read(bits)? as usize
+        };
+        let (mut symbol, mut previous) = (0, 8);
+        while symbol < alphabet && remaining > 0 {
+            remaining -= 1;
+            let (repeat, length) = match length_code.read(reader)? {
+                length @ 0..=15 => {
+                    if length != 0 {
+                        previous = length as u32;
+                    }
+                    (1, length as u32)
+                },
+                16 => (3 + reader.read(2)? as usize, previous),
+                17 => (3 + reader.read(3)? as usize, 0),
+                _ => (11 + reader.read(7)? as usize, 0)
+            };
+            if symbol + repeat > alphabet {
+                return Err("code lengths run past the alphabet".to_string());
+            }
+            lengths[symbol..symbol + repeat].fill(length);
+            symbol += repeat;
+        }
+
+        Code::from_lengths(&lengths)
+
+    }
+
+    fn read_prefixed(reader: &mut BitReader, prefix: usize) -> Result<usize, String> {
+        if prefix < 4 {
+            return Ok(prefix + 1);
+        }
+        let extra_bits = (prefix as u32 - 2) >> 1;
+        let offset = (2 + (prefix & 1)) << extra_bits;
+        Ok(offset + reader.read(extra_bits)? as usize + 1)
+    }
+
+    // An entropy-coded image of ARGB words, with meta prefix codes only for
+    // the main image.
+    fn decode_image(reader: &mut BitReader, bounds: (usize, usize), main: bool) -> Result<Vec<u32>, String> {
+
+        let cache_bits = match reader.read(1)? {
+            0 => 0,
+            _ => match reader.read(4)? {
+                bits @ 1..=11 => bits,
+                bits => return Err(format!("invalid color cache size {}", bits))
+            }
+        };
+        let mut cache = vec![0u32; if cache_bits > 0 { 1 << cache_bits } else { 0 }];
+
+        let groups = if main && reader.read(1)? == 1 {
+            let bits = reader.read(3)? + 2;
+            let blocks = (bounds.0.div_ceil(1 << bits), bounds.1.div_ceil(1 << bits));
+            Some((bits, blocks.0, decode_image(reader, blocks, false)?))
+        } else {
+            None
+        };
+        let group_count = groups.as_ref().map_or(1, |(_, _, image)| image.iter().map(|group| (group >> 8) as usize & 0xffff).max().unwrap() + 1);
+
+        let mut codes = Vec::new();
+        for _ in 0..group_count {
+            let mut group = Vec::new();
+            for alphabet in [256 + 24 + cache.len(), 256, 256, 256, 40] {
+                group.push(read_code(reader, alphabet)?);
+            }
+            codes.push(group);
+        }
+
+        let mut argb: Vec<u32> = Vec::with_capacity(bounds.0 * bounds.1);
+        let insert = |cache: &mut Vec<u32>, pixel: u32| if cache_bits > 0 {
+            cache[(0x1e35_a7bd_u32.wrapping_mul(pixel) >> (32 - cache_bits)) as usize] = pixel;
+        };
+
+        while argb.len() < bounds.0 * bounds.1 {
+            let group = match &groups {
+                None => &codes[0],
+                Some((bits, width, image)) => {
+                    let (x, y) = (argb.len() % bounds.0, argb.len() / bounds.0);
+                    &codes[(image[(y >> bits) * width + (x >> bits)] >> 8) as usize & 0xffff]
+                }
+            };
+            match group[GREEN].read(reader)? {
+                green @ 0..=255 => {
+                    let red = group[RED].read(reader)? as u32;
+                    let blue = group[BLUE].read(reader)? as u32;
+                    let alpha = group[ALPHA].read(reader)? as u32;
+                    let pixel = (alpha << 24) | (red << 16) | ((green as u32) << 8) | blue;
+                    argb.push(pixel);
+                    insert(&mut cache, pixel);
+                },
+                symbol @ 256..=279 => {
+                    let length = read_prefixed(reader, symbol - 256)?;
+                    let prefix = group[DISTANCE].read(reader)?;
+                    let distance = match read_prefixed(reader, prefix)? {
+                        code @ 0..=120 => return Err(format!("two-dimensional distance code {} is not supported", code)),
+                        code => code - 120
+                    };
+                    if distance > argb.len() || argb.len() + length > bounds.0 * bounds.1 {
+                        return Err("copy runs outside the image".to_string());
+                    }
+                    for _ in 0..length {
+                        let pixel = argb[argb.len() - distance];
+                        argb.push(pixel);
+                        insert(&mut cache, pixel);
+                    }
+                },
+                symbol => {
+                    let pixel = cache[symbol - 280];
+                    argb.push(pixel);
+                    insert(&mut cache, pixel);
+                }
+            }
+        }
+
+        Ok(argb)
+
+    }
+
+    fn channels(pixel: u32) -> [i32; 4] {
+        [24, 16, 8, 0].map(|shift| (pixel >> shift) as i32 & 0xff)
+    }
+
+    fn pack(channels: [i32; 4]) -> u32 {
+        channels.iter().fold(0, |pixel, channel| (pixel << 8) | (*channel as u32 & 0xff))
+    }
+
+    fn average(a: u32, b: u32) -> u32 {
+        let (a, b) = (channels(a), channels(b));
+        pack([0, 1, 2, 3].map(|channel| (a[channel] + b[channel]) / 2))
+    }
+
+    fn clamp_add_subtract_full(a: u32, b: u32, c: u32) -> u32 {
+        let (a, b, c) = (channels(a), channels(b), channels(c));
+        pack([0, 1, 2, 3].map(|channel| (a[channel] + b[channel] - c[channel]).clamp(0, 255)))
+    }
+
+    fn clamp_add_subtract_half(a: u32, b: u32) -> u32 {
+        let (a, b) = (channels(a), channels(b));
+        pack([0, 1, 2, 3].map(|channel| (a[channel] + (a[channel] - b[channel]) / 2).clamp(0, 255)))
+    }
+
+    fn select(left: u32, top: u32, top_left: u32) -> u32 {
+        let (l, t, tl) = (channels(left), channels(top), channels(top_left));
+        let estimate = [0, 1, 2, 3].map(|channel| l[channel] + t[channel] - tl[channel]);
+        let to_left: i32 = (0..4).map(|channel| (estimate[channel] - l[channel]).abs()).sum();
+        let to_top: i32 = (0..4).map(|channel| (estimate[channel] - t[channel]).abs()).sum();
+        if to_left < to_top { left } else { top }
+    }
+
+    fn unpredict(argb: &mut [u32], bounds: (usize, usize), bits: u32, modes: &[u32]) -> Result<(), String> {
+
+        let blocks = bounds.0.div_ceil(1 << bits);
+
+        for index in 0..argb.len() {
+            let (x, y) = (index % bounds.0, index / bounds.0);
+            let mode = match (x, y) {
+                (0, 0) => 0,
+                (_, 0) => 1,
+                (0, _) => 2,
+                _ => (modes[(y >> bits) * blocks + (x >> bits)] >> 8) & 0xff
+            };
+            let left = || argb[index - 1];
+            let top = || argb[index - bounds.0];
+            // The top right of the last column is the first pixel of the row.
+            let top_right = || argb[index - bounds.0 + 1];
+            let top_left = || argb[index - bounds.0 - 1];
+            let prediction = match mode {
+                0 => 0xff00_0000,
+                1 => left(),
+                2 => top(),
+                3 => top_right(),
+                4 => top_left(),
+                5 => average(average(left(), top_right()), top()),
+                6 => average(left(), top_left()),
+                7 => average(left(), top()),
+                8 => average(top_left(), top()),
+                9 => average(top(), top_right()),
+                10 => average(average(left(), top_left()), average(top(), top_right())),
+                11 => select(left(), top(), top_left()),
+                12 => clamp_add_subtract_full(left(), top(), top_left()),
+                13 => clamp_add_subtract_half(average(left(), top()), top_left()),
+                mode => return Err(format!("unknown predictor mode {}", mode))
+            };
+            let (residual, prediction) = (channels(argb[index]), channels(prediction));
+            argb[index] = pack([0, 1, 2, 3].map(|channel| residual[channel] + prediction[channel]));
+        }
+
+        Ok(())
+
+    }
+
+    fn uncorrelate(argb: &mut [u32], bounds: (usize, usize), bits: u32, multipliers: &[u32]) {
+
+        let blocks = bounds.0.div_ceil(1 << bits);
+        let delta = |multiplier: u32, channel: i32| ((multiplier as u8 as i8 as i32) * (channel as u8 as i8 as i32)) >> 5;
+
+        for (index, pixel) in argb.iter_mut().enumerate() {
+            let (x, y) = (index % bounds.0, index / bounds.0);
+            let multiplier = multipliers[(y >> bits) * blocks + (x >> bits)];
+            let [alpha, red, green, blue] = channels(*pixel);
+            let red = red + delta(multiplier, green);
+            let blue = blue + delta(multiplier >> 8, green) + delta(multiplier >> 16, red & 0xff);
+            *pixel = pack([alpha, red, green, blue]);
+        }
+
+    }
+
+}
+
+#[test]
+fn test_encode_webp() {
+    let bounds = (37, 23);
+    let pixels: Vec<u8> = (0..bounds.0 * bounds.1 * 3).map(|index| (index * 7 % 251) as u8).collect();
+
+    let mut encoded = Vec::new();
+    encode_webp(&mut encoded, &pixels, bounds, ColorType::RGB(8)).unwrap();
+
+    assert_eq!(&encoded[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes([encoded[4], encoded[5], encoded[6], encoded[7]]) as usize, encoded.len() - 8);
+    assert_eq!(&encoded[8..16], b"WEBPVP8L");
+    assert_eq!(encoded[20], SIGNATURE as u8);
+
+    let header = u32::from_le_bytes([encoded[21], encoded[22], encoded[23], encoded[24]]);
+    assert_eq!(((header & 0x3fff) + 1, ((header >> 14) & 0x3fff) + 1), (37, 23));
+
+    assert_eq!(prefix_code(3), (2, 0, 0));
+    assert_eq!(prefix_code(5), (4, 1, 0));
+    assert_eq!(prefix_code(4096), (23, 10, 1023));
+
+    assert_eq!(code_lengths(&[5, 0, 1, 1, 3], 15), vec![1, 0, 3, 3, 2]);
+    assert!(code_lengths(&(0..40).map(|symbol| 1 << (symbol % 30)).collect::<Vec<_>>(), 7).iter().all(|length| *length <= 7));
+    assert!(encode_webp(&mut Vec::new(), &[0; 8], (2, 2), ColorType::Gray(16)).is_err());
+
+    // Round trips through an independent decoder, across several predictor
+    // blocks, long copies, noise that needs length-limited codes, alpha and
+    // a single pixel.
+    let rgba = |pixels: &[u8], channels: usize| -> Vec<[u8; 4]> {
+        pixels.chunks(channels).map(|pixel| match *pixel {
+            [gray] => [gray, gray, gray, 255],
+            [red, green, blue] => [red, green, blue, 255],
+            [red, green, blue, alpha] => [red, green, blue, alpha],
+            _ => unreachable!()
+        }).collect()
+    };
+    let mut state = 1u32;
+    let mut noise = || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 16) as u8
+    };
+    let stripes: Vec<u8> = (0..600 * 5).map(|index| ((index % 600) / 37 * 40) as u8).collect();
+    let noisy: Vec<u8> = (0..61 * 29 * 3).map(|_| noise()).collect();
+    let translucent: Vec<u8> = (0..40 * 30 * 4).map(|index| if index % 4 == 3 { (index / 4 % 7 * 36) as u8 } else { (index * 13 % 256) as u8 }).collect();
+    let cases = [
+        (&pixels[..], bounds, ColorType::RGB(8), 3),
+        (&stripes[..], (600, 5), ColorType::Gray(8), 1),
+        (&noisy[..], (61, 29), ColorType::RGB(8), 3),
+        (&translucent[..], (40, 30), ColorType::RGBA(8), 4),
+        (&[200, 100, 50][..], (1, 1), ColorType::RGB(8), 3)
+    ];
+    for (pixels, bounds, color, channels) in cases {
+        let mut encoded = Vec::new();
+        encode_webp(&mut encoded, pixels, bounds, color).unwrap();
+        assert_eq!(decode::decode_webp(&encoded).unwrap(), (bounds, rgba(pixels, channels)));
+    }
+}
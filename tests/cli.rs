@@ -0,0 +1,104 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_dry_run_writes_nothing() {
+    let output = std::env::temp_dir().join("mandelbrot_test_dry_run.png");
+    let _ = std::fs::remove_file(&output);
+
+    let result = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+        .args([output.to_str().unwrap(), "64x48", "-2,1.2", "1,-1.2", "--iterations", "100", "--dry-run", "--quiet"])
+        .output()
+        .unwrap();
+
+    assert!(result.status.success());
+    let report = String::from_utf8(result.stdout).unwrap();
+    assert!(report.contains("Pixels : 3072 per frame (64x48)"));
+    assert!(report.contains("Iterations : at most 307200"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_interactive_keys() {
+    let output = std::env::temp_dir().join("mandelbrot_test_interactive.ppm");
+
+    for (keys, written) in [(&b"+-q"[..], false), (&b"\x1b[C+w"[..], true)] {
+        let _ = std::fs::remove_file(&output);
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+            .args([output.to_str().unwrap(), "64x48", "-2,1.2", "1,-1.2", "--iterations", "100", "--interactive", "--quiet"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(keys).unwrap();
+        let result = child.wait_with_output().unwrap();
+
+        assert!(result.status.success());
+        assert!(String::from_utf8(result.stdout).unwrap().contains("\u{1b}[38;2;"));
+        assert_eq!(output.exists(), written);
+    }
+
+    let _ = std::fs::remove_file(&output);
+}
+
+#[test]
+fn test_help_and_unknown_flags() {
+    let result = Command::new(env!("CARGO_BIN_EXE_mandelbrot")).arg("--help").output().unwrap();
+    assert!(result.status.success());
+    assert!(String::from_utf8(result.stdout).unwrap().starts_with("Usage :"));
+
+    let result = Command::new(env!("CARGO_BIN_EXE_mandelbrot")).arg("--version").output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), format!("mandelbrot {}\n", env!("CARGO_PKG_VERSION")));
+
+    let result = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+        .args(["out.png", "64x48", "-2,1.2", "1,-1.2", "--iterationz", "100"])
+        .output()
+        .unwrap();
+    assert!(!result.status.success());
+    assert!(String::from_utf8(result.stderr).unwrap().contains("Unknown flag --iterationz"));
+}
+
+#[test]
+fn test_json_round_trip() {
+    let to_json = |args: &[&str], stdin: &[u8]| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+            .args(args)
+            .arg("--quiet")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(stdin).unwrap();
+        let result = child.wait_with_output().unwrap();
+        assert!(result.status.success());
+        result.stdout
+    };
+
+    let json = to_json(&["out.png", "64x48", "-2,1.2", "1,-1.2", "--julia", "-0.8,0.156", "--smooth", "--palette", "fire", "--to-json"], b"");
+    let text = String::from_utf8(json.clone()).unwrap();
+    assert!(text.contains("\"julia\": [-0.8, 0.156]") && text.contains("\"palette\": \"fire\"") && text.contains("\"dither\": false"), "{}", text);
+
+    assert_eq!(to_json(&["--from-json", "-", "--to-json"], &json), json);
+    let overridden = String::from_utf8(to_json(&["--from-json", "-", "--to-json", "--iterations", "99"], &json)).unwrap();
+    assert_eq!(overridden, text.replace("\"limit\": 255", "\"limit\": 99"));
+}
+
+#[test]
+fn test_raw16_rejected_before_writing() {
+    let output = std::env::temp_dir().join("mandelbrot_test_raw16.raw");
+    let map = std::env::temp_dir().join("mandelbrot_test_raw16_map.png");
+    let _ = std::fs::remove_file(&output);
+    let _ = std::fs::remove_file(&map);
+
+    let result = Command::new(env!("CARGO_BIN_EXE_mandelbrot"))
+        .args([output.to_str().unwrap(), "64x48", "-2,1.2", "1,-1.2", "--format", "raw16", "--scale", "2", "--iterations-map", map.to_str().unwrap(), "--quiet"])
+        .output()
+        .unwrap();
+
+    assert!(!result.status.success());
+    assert!(String::from_utf8(result.stderr).unwrap().contains("--scale above 1 cannot be combined with --format raw16"));
+    assert!(!map.exists());
+    assert!(!output.exists());
+}
@@ -0,0 +1,51 @@
+use mandelbrot::{Coloring, Config, Palette, Sampling, buddhabrot_density, render, render_adaptive, render_histogram, render_parallel};
+use num::Complex;
+
+#[test]
+fn test_render_small_image() {
+    let mut pixels = vec![0; 4 * 4];
+    render(&mut pixels,
+           (4, 4),
+           Complex { re: -2.0, im: 2.0 },
+           Complex { re: 2.0, im: -2.0 },
+           Config::default());
+
+    assert_eq!(pixels[0], 254);
+    assert_eq!(pixels[2 * 4 + 2], 0);
+    assert_eq!(pixels[2 * 4 + 1], 0);
+}
+
+#[test]
+fn test_render_reproducible_across_threads() {
+    let bounds = (61, 47);
+    let upper_left = Complex { re: -0.78, im: 0.16 };
+    let lower_right = Complex { re: -0.72, im: 0.11 };
+    let config = Config { supersample: 3, sampling: Sampling::Random(5), supersample_threshold: 1.5, coloring: Coloring::Smooth, palette: Palette::Ultra, limit: 500, ..Config::default() };
+
+    let renders = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| {
+            let mut pixels = vec![0; bounds.0 * bounds.1 * config.bytes_per_pixel()];
+            render_parallel(&mut pixels, bounds, upper_left, lower_right, config);
+
+            let mut adaptive = vec![0; pixels.len()];
+            render_adaptive(&mut adaptive, bounds, upper_left, lower_right, config, 4);
+
+            let mut histogram = vec![0; pixels.len()];
+            render_histogram(&mut histogram, bounds, upper_left, lower_right, config);
+
+            let density = buddhabrot_density(bounds, Complex { re: -2.0, im: 1.5 }, Complex { re: 1.0, im: -1.5 }, config, 20_000, 3);
+
+            (pixels, adaptive, histogram, density)
+        })
+    };
+
+    let single = renders(1);
+    let mut serial = vec![0; single.0.len()];
+    render(&mut serial, bounds, upper_left, lower_right, config);
+    assert_eq!(serial, single.0);
+
+    for threads in [2, 7] {
+        assert!(renders(threads) == single, "output differs with {} threads", threads);
+    }
+}